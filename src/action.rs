@@ -0,0 +1,567 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::error::{Error, Result};
+use crate::event::EventType;
+use crate::input::{InputState, MouseButton};
+use crate::keys::Key;
+
+/// Something that can trigger an action, a key or a mouse button. Physical binds the
+/// key in the key's place on a US keyboard, whatever the layout makes it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(Key),
+    Physical(Key),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    /// Gets the binding pressed by an event, repeats and unknown keys don't bind anything
+    pub fn pressed_by(e_type: &EventType) -> Option<Binding> {
+        return match *e_type {
+            EventType::KeyDown { key: Key::None, .. } => None,
+            EventType::KeyDown { key, is_repeat: false, .. } => Some(Binding::Key(key)),
+            EventType::MouseLeftBtnDown { .. } => Some(Binding::Mouse(MouseButton::Left)),
+            EventType::MouseMidBtnDown { .. } => Some(Binding::Mouse(MouseButton::Middle)),
+            EventType::MouseRightBtnDown { .. } => Some(Binding::Mouse(MouseButton::Right)),
+            _ => None,
+        };
+    }
+
+    /// Gets the binding pressed by an event, with keys bound by where they are rather than
+    /// what the layout makes them. Keys with no place on a US keyboard are bound as they are
+    pub fn physically_pressed_by(e_type: &EventType) -> Option<Binding> {
+        return match *e_type {
+            EventType::KeyDown { scancode, is_repeat: false, .. } if scancode.get_key() != Key::None => {
+                Some(Binding::Physical(scancode.get_key()))
+            }
+            _ => Binding::pressed_by(e_type),
+        };
+    }
+
+    /// Whether an event presses the binding, repeats don't count
+    fn is_pressed_by(self, e_type: &EventType) -> bool {
+        return match (self, e_type) {
+            (Binding::Physical(key), EventType::KeyDown { scancode, is_repeat: false, .. }) => {
+                scancode.get_key() == key
+            }
+            _ => Binding::pressed_by(e_type) == Some(self),
+        };
+    }
+
+    #[inline]
+    fn is_down(self, input: &InputState) -> bool {
+        return match self {
+            Binding::Key(key) => input.is_down(key),
+            Binding::Physical(key) => input.is_physical_down(key),
+            Binding::Mouse(button) => input.is_mouse_down(button),
+        };
+    }
+
+    #[inline]
+    fn just_pressed(self, input: &InputState) -> bool {
+        return match self {
+            Binding::Key(key) => input.just_pressed(key),
+            Binding::Physical(key) => input.physical_just_pressed(key),
+            Binding::Mouse(button) => input.mouse_just_pressed(button),
+        };
+    }
+
+    #[inline]
+    fn just_released(self, input: &InputState) -> bool {
+        return match self {
+            Binding::Key(key) => input.just_released(key),
+            Binding::Physical(key) => input.physical_just_released(key),
+            Binding::Mouse(button) => input.mouse_just_released(button),
+        };
+    }
+}
+
+/// Written as the key's name, Physical: and the key's name, or MouseLeft, MouseMiddle
+/// and MouseRight
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{}", key.get_name()),
+            Binding::Physical(key) => write!(f, "{}{}", PHYSICAL_PREFIX, key.get_name()),
+            Binding::Mouse(MouseButton::Left) => write!(f, "MouseLeft"),
+            Binding::Mouse(MouseButton::Middle) => write!(f, "MouseMiddle"),
+            Binding::Mouse(MouseButton::Right) => write!(f, "MouseRight"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Binding> {
+        let binding = if s.eq_ignore_ascii_case("MouseLeft") {
+            Binding::Mouse(MouseButton::Left)
+        } else if s.eq_ignore_ascii_case("MouseMiddle") {
+            Binding::Mouse(MouseButton::Middle)
+        } else if s.eq_ignore_ascii_case("MouseRight") {
+            Binding::Mouse(MouseButton::Right)
+        } else {
+            let (name, physical) = match s.get(..PHYSICAL_PREFIX.len()) {
+                Some(prefix) if prefix.eq_ignore_ascii_case(PHYSICAL_PREFIX) => (&s[PHYSICAL_PREFIX.len()..], true),
+                _ => (s, false),
+            };
+
+            match Key::from_name(name.trim()) {
+                Some(Key::None) | None => {
+                    return Err(Error::InvalidBindings(format!("unknown key or button \"{}\"", s)));
+                }
+                Some(key) if physical => Binding::Physical(key),
+                Some(key) => Binding::Key(key),
+            }
+        };
+
+        return Ok(binding);
+    }
+}
+
+/// What physical bindings are written with in front of the key's name
+const PHYSICAL_PREFIX: &str = "Physical:";
+
+/// An action waiting for its binding to be pressed
+struct Capture {
+    action: String,
+    replace: bool,
+    physical: bool,
+}
+
+/// Named actions, like "confirm" or "menu", each triggered by any of its bindings.
+/// It's saved as lines of `action = binding, binding`, with # starting a comment
+#[derive(Default)]
+pub struct ActionMap {
+    // in the order they were first bound, so saving keeps the file's order
+    actions: Vec<(String, Vec<Binding>)>,
+    capture: Option<Capture>,
+    captured: Option<(String, Binding)>,
+}
+
+impl ActionMap {
+    #[inline]
+    pub fn new() -> ActionMap {
+        ActionMap::default()
+    }
+
+    /// Adds a binding to an action, creating the action if it's new.
+    /// Names can't be empty or contain '=', '#' or line breaks, as they couldn't be saved
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        if !is_valid_name(action) {
+            log_warn!("\"{}\" can't be used as an action name", action);
+            return;
+        }
+
+        let bindings = match self.actions.iter().position(|(name, _)| name == action) {
+            Some(index) => &mut self.actions[index].1,
+            None => {
+                self.actions.push((action.to_string(), Vec::new()));
+                &mut self.actions.last_mut().unwrap().1
+            }
+        };
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding from an action, returning whether it was bound
+    pub fn unbind(&mut self, action: &str, binding: Binding) -> bool {
+        return match self.get_bindings_mut(action) {
+            Some(bindings) => {
+                let len = bindings.len();
+                bindings.retain(|bound| *bound != binding);
+                bindings.len() != len
+            }
+            None => false,
+        };
+    }
+
+    /// Removes every binding of an action, the action itself is kept
+    pub fn clear(&mut self, action: &str) {
+        if let Some(bindings) = self.get_bindings_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    /// Gets the bindings of an action, empty if it doesn't exist
+    pub fn get_bindings(&self, action: &str) -> &[Binding] {
+        return match self.actions.iter().find(|(name, _)| name == action) {
+            Some((_, bindings)) => bindings,
+            None => &[],
+        };
+    }
+
+    /// Gets the names of every action, in the order they were added
+    pub fn get_actions(&self) -> impl Iterator<Item = &str> {
+        return self.actions.iter().map(|(name, _)| name.as_str());
+    }
+
+    /// Whether any of the action's bindings are held down
+    pub fn is_down(&self, action: &str, input: &InputState) -> bool {
+        return self.get_bindings(action).iter().any(|binding| binding.is_down(input));
+    }
+
+    /// Whether one of the action's bindings was pressed this frame
+    pub fn just_pressed(&self, action: &str, input: &InputState) -> bool {
+        return self.get_bindings(action).iter().any(|binding| binding.just_pressed(input));
+    }
+
+    /// Whether one of the action's bindings was released this frame, leaving none held
+    pub fn just_released(&self, action: &str, input: &InputState) -> bool {
+        let bindings = self.get_bindings(action);
+
+        return bindings.iter().any(|binding| binding.just_released(input))
+            && !bindings.iter().any(|binding| binding.is_down(input));
+    }
+
+    /// Whether an event presses one of the action's bindings, for event driven loops
+    pub fn is_pressed_by(&self, action: &str, e_type: &EventType) -> bool {
+        return self.get_bindings(action).iter().any(|binding| binding.is_pressed_by(e_type));
+    }
+
+    /// Waits for the next key or mouse button pressed and binds it to the action, either in
+    /// place of its bindings or alongside them. The press is kept from the app, when it's
+    /// been bound take_captured returns it
+    pub fn start_capture(&mut self, action: &str, replace: bool) {
+        self.begin_capture(action, replace, false);
+    }
+
+    /// Like start_capture, but keys are bound by where they are, as Binding::Physical, so a
+    /// binding captured on one layout stays in the same place on any other
+    pub fn start_physical_capture(&mut self, action: &str, replace: bool) {
+        self.begin_capture(action, replace, true);
+    }
+
+    fn begin_capture(&mut self, action: &str, replace: bool, physical: bool) {
+        if !is_valid_name(action) {
+            log_warn!("\"{}\" can't be used as an action name", action);
+            return;
+        }
+
+        self.capture = Some(Capture {
+            action: action.to_string(),
+            replace,
+            physical,
+        });
+    }
+
+    /// Stops waiting for a press, leaving the bindings as they were
+    #[inline]
+    pub fn cancel_capture(&mut self) {
+        self.capture = None;
+    }
+
+    #[inline]
+    pub fn is_capturing(&self) -> bool {
+        return self.capture.is_some();
+    }
+
+    /// Gets the action and binding the last capture bound, once
+    #[inline]
+    pub fn take_captured(&mut self) -> Option<(String, Binding)> {
+        return self.captured.take();
+    }
+
+    /// Binds the event's press if a capture is waiting for one, returning whether it was used
+    pub(crate) fn capture(&mut self, e_type: &EventType) -> bool {
+        let pressed = match self.capture.as_ref() {
+            Some(capture) if capture.physical => Binding::physically_pressed_by(e_type),
+            Some(_) => Binding::pressed_by(e_type),
+            None => return false,
+        };
+
+        let binding = match pressed {
+            Some(binding) => binding,
+            None => return false,
+        };
+
+        let capture = self.capture.take().unwrap();
+
+        if capture.replace {
+            self.clear(&capture.action);
+        }
+
+        self.bind(&capture.action, binding);
+        self.captured = Some((capture.action, binding));
+
+        return true;
+    }
+
+    /// Writes the actions to a file, replacing it if it exists
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        return fs::write(path, self.to_string()).map_err(Error::BindingsIo);
+    }
+
+    /// Reads actions written by save, or by hand
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ActionMap> {
+        return fs::read_to_string(path).map_err(Error::BindingsIo)?.parse();
+    }
+
+    fn get_bindings_mut(&mut self, action: &str) -> Option<&mut Vec<Binding>> {
+        return self
+            .actions
+            .iter_mut()
+            .find(|(name, _)| name == action)
+            .map(|(_, bindings)| bindings);
+    }
+}
+
+impl Display for ActionMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, bindings) in self.actions.iter() {
+            write!(f, "{} =", name)?;
+
+            for (index, binding) in bindings.iter().enumerate() {
+                let separator = if index == 0 { " " } else { ", " };
+                write!(f, "{}{}", separator, binding)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl FromStr for ActionMap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ActionMap> {
+        let mut map = ActionMap::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let line_error = |reason: String| Error::InvalidBindings(format!("line {}: {}", index + 1, reason));
+
+            let (action, bindings) = match line.split_once('=') {
+                Some((action, bindings)) => (action.trim(), bindings.trim()),
+                None => return Err(line_error("expected action = bindings".to_string())),
+            };
+
+            if !is_valid_name(action) {
+                return Err(line_error("missing action name".to_string()));
+            }
+
+            // an action with no bindings is still kept, so it can be bound later
+            if map.get_bindings_mut(action).is_none() {
+                map.actions.push((action.to_string(), Vec::new()));
+            }
+
+            for binding in bindings.split(',').map(str::trim).filter(|binding| !binding.is_empty()) {
+                match binding.parse() {
+                    Ok(binding) => map.bind(action, binding),
+                    Err(Error::InvalidBindings(reason)) => return Err(line_error(reason)),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        return Ok(map);
+    }
+}
+
+#[inline]
+fn is_valid_name(name: &str) -> bool {
+    return !name.is_empty()
+        && name.trim() == name
+        && !name.contains(['=', '#', '\n', '\r']);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{Modifiers, ScanCode};
+
+    fn key_down(key: Key) -> EventType {
+        return EventType::KeyDown {
+            key,
+            scancode: ScanCode::from_key(key).unwrap_or(ScanCode(0)),
+            modifiers: Modifiers::default(),
+            is_repeat: false,
+        };
+    }
+
+    fn key_repeat(key: Key) -> EventType {
+        return EventType::KeyDown {
+            key,
+            scancode: ScanCode::from_key(key).unwrap_or(ScanCode(0)),
+            modifiers: Modifiers::default(),
+            is_repeat: true,
+        };
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        let bindings = [
+            Binding::Key(Key::Escape),
+            Binding::Key(Key::N1),
+            Binding::Physical(Key::W),
+            Binding::Mouse(MouseButton::Left),
+            Binding::Mouse(MouseButton::Middle),
+            Binding::Mouse(MouseButton::Right),
+        ];
+
+        for binding in bindings {
+            assert_eq!(binding.to_string().parse::<Binding>().unwrap(), binding);
+        }
+    }
+
+    #[test]
+    fn physical_prefix_ignores_case() {
+        assert_eq!("physical:W".parse::<Binding>().unwrap(), Binding::Physical(Key::W));
+        assert_eq!("PHYSICAL: A".parse::<Binding>().unwrap(), Binding::Physical(Key::A));
+        assert_eq!("mouseright".parse::<Binding>().unwrap(), Binding::Mouse(MouseButton::Right));
+        assert!("Physical:".parse::<Binding>().is_err());
+        assert!("Physical:NotAKey".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn map_round_trips() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::Key(Key::Space));
+        map.bind("jump", Binding::Mouse(MouseButton::Right));
+        map.bind("forward", Binding::Physical(Key::W));
+        map.bind("unbound", Binding::Key(Key::F1));
+        map.clear("unbound");
+
+        let read: ActionMap = map.to_string().parse().unwrap();
+
+        assert_eq!(read.get_actions().collect::<Vec<_>>(), ["jump", "forward", "unbound"]);
+        assert_eq!(read.get_bindings("jump"), map.get_bindings("jump"));
+        assert_eq!(read.get_bindings("forward"), [Binding::Physical(Key::W)]);
+        assert!(read.get_bindings("unbound").is_empty());
+        assert_eq!(read.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let text = "# the controls\n\n   \njump = Space # or W\n\t# indented comment\nfire=MouseLeft,Physical:F\n";
+        let map: ActionMap = text.parse().unwrap();
+
+        assert_eq!(map.get_actions().collect::<Vec<_>>(), ["jump", "fire"]);
+        assert_eq!(map.get_bindings("jump"), [Binding::Key(Key::Space)]);
+        assert_eq!(
+            map.get_bindings("fire"),
+            [Binding::Mouse(MouseButton::Left), Binding::Physical(Key::F)]
+        );
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let line_of = |text: &str| match text.parse::<ActionMap>() {
+            Err(Error::InvalidBindings(reason)) => reason,
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("\"{}\" was parsed", text),
+        };
+
+        assert!(line_of("jump = Space\n# fine\nfire MouseLeft").starts_with("line 3:"));
+        assert!(line_of("\n = Space").starts_with("line 2:"));
+        assert!(line_of("jump = Space, Nope").starts_with("line 1:"));
+    }
+
+    #[test]
+    fn capture_replaces_bindings() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::Key(Key::Space));
+        map.start_capture("jump", true);
+
+        // repeats and releases don't bind anything
+        assert!(!map.capture(&key_repeat(Key::J)));
+        assert!(!map.capture(&EventType::KeyUp {
+            key: Key::J,
+            scancode: ScanCode(0),
+            modifiers: Modifiers::default(),
+        }));
+        assert!(map.is_capturing());
+
+        assert!(map.capture(&key_down(Key::J)));
+        assert!(!map.is_capturing());
+        assert_eq!(map.get_bindings("jump"), [Binding::Key(Key::J)]);
+        assert_eq!(map.take_captured(), Some(("jump".to_string(), Binding::Key(Key::J))));
+        assert_eq!(map.take_captured(), None);
+
+        // nothing is captured once the capture is done
+        assert!(!map.capture(&key_down(Key::K)));
+    }
+
+    #[test]
+    fn repeats_dont_press_actions() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::Key(Key::Space));
+        map.bind("forward", Binding::Physical(Key::W));
+
+        assert!(map.is_pressed_by("jump", &key_down(Key::Space)));
+        assert!(map.is_pressed_by("forward", &key_down(Key::W)));
+        assert!(!map.is_pressed_by("jump", &key_repeat(Key::Space)));
+        assert!(!map.is_pressed_by("forward", &key_repeat(Key::W)));
+
+        map.start_physical_capture("forward", true);
+        assert!(!map.capture(&key_repeat(Key::A)));
+        assert!(map.is_capturing());
+    }
+
+    #[test]
+    fn capture_adds_to_bindings() {
+        let mut map = ActionMap::new();
+        map.bind("fire", Binding::Key(Key::F));
+        map.start_capture("fire", false);
+
+        assert!(map.capture(&EventType::MouseLeftBtnDown { modifiers: Modifiers::default() }));
+        assert_eq!(map.get_bindings("fire"), [Binding::Key(Key::F), Binding::Mouse(MouseButton::Left)]);
+    }
+
+    #[test]
+    fn physical_capture_binds_the_position() {
+        let mut map = ActionMap::new();
+        map.start_physical_capture("forward", true);
+
+        // W on a US keyboard types Z on AZERTY
+        assert!(map.capture(&EventType::KeyDown {
+            key: Key::Z,
+            scancode: ScanCode::from_key(Key::W).unwrap(),
+            modifiers: Modifiers::default(),
+            is_repeat: false,
+        }));
+        assert_eq!(map.get_bindings("forward"), [Binding::Physical(Key::W)]);
+
+        // mouse buttons and keys with no position are bound as they are
+        map.start_physical_capture("forward", false);
+        assert!(map.capture(&EventType::KeyDown {
+            key: Key::F13,
+            scancode: ScanCode(u32::MAX),
+            modifiers: Modifiers::default(),
+            is_repeat: false,
+        }));
+        map.start_physical_capture("forward", false);
+        assert!(map.capture(&EventType::MouseRightBtnDown { modifiers: Modifiers::default() }));
+
+        assert_eq!(
+            map.get_bindings("forward"),
+            [Binding::Physical(Key::W), Binding::Key(Key::F13), Binding::Mouse(MouseButton::Right)]
+        );
+    }
+
+    #[test]
+    fn cancelled_capture_binds_nothing() {
+        let mut map = ActionMap::new();
+        map.start_capture("fire", true);
+        map.cancel_capture();
+
+        assert!(!map.capture(&key_down(Key::F)));
+        assert!(map.get_bindings("fire").is_empty());
+        assert_eq!(map.take_captured(), None);
+    }
+}
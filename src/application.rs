@@ -1,43 +1,413 @@
 use raw_window_handle::HasRawWindowHandle;
-use crate::event::EventManager;
+use std::time::{Duration, Instant};
+use crate::error::Result;
+use crate::event::{Event, EventLoopProxy, EventManager};
+use crate::gamepad::GamepadManager;
+use crate::action::ActionMap;
+use crate::input::InputState;
+use crate::keys::Modifiers;
 use crate::platform::*;
+use crate::replay::{Player, Recorder, Recording};
 
 pub struct App {
-    window: Window,
+    windows: Vec<Window>,
+    main_window: WindowId,
     event_manager: EventManager,
+    waker: EventLoopWaker,
+    gamepads: GamepadManager,
+    frame_timing: FrameTiming,
+    recorder: Option<Recorder>,
+    player: Option<Player>,
     running: bool
 }
 
+/// How long frames take, updated by App::next_frame
+struct FrameTiming {
+    created: Instant,
+    frame_count: u64,
+    // None until the first frame starts
+    frame_start: Option<Instant>,
+    delta: Duration,
+    fps: f32,
+    // the frames counted towards the next fps measurement, and when counting started
+    fps_frames: u32,
+    fps_since: Instant,
+}
+
+/// The shortest time fps is averaged over, so it doesn't jump around every frame
+const FPS_INTERVAL: Duration = Duration::from_secs(1);
+
+impl FrameTiming {
+    fn new() -> FrameTiming {
+        let now = Instant::now();
+
+        FrameTiming {
+            created: now,
+            frame_count: 0,
+            frame_start: None,
+            delta: Duration::ZERO,
+            fps: 0.0,
+            fps_frames: 0,
+            fps_since: now,
+        }
+    }
+
+    fn next_frame(&mut self) {
+        let now = Instant::now();
+
+        match self.frame_start {
+            Some(last) => self.delta = now - last,
+            None => self.fps_since = now,
+        }
+
+        self.frame_start = Some(now);
+        self.frame_count += 1;
+
+        let counted = now - self.fps_since;
+        if counted >= FPS_INTERVAL {
+            self.fps = self.fps_frames as f32 / counted.as_secs_f32();
+            self.fps_frames = 0;
+            self.fps_since = now;
+        }
+
+        self.fps_frames += 1;
+    }
+}
+
 impl App {
     /// Creates the application by creating the window and event manager
     #[inline]
-    pub fn create(name: &'static str, x: i16, y: i16, width: u16, height: u16) -> Option<Self> {
+    pub fn create(name: &str, x: i16, y: i16, width: u16, height: u16) -> Result<Self> {
+        return App::create_with(
+            WindowBuilder::new()
+                .with_title(name)
+                .with_size(width, height)
+                .with_position(x, y),
+        );
+    }
+
+    /// Creates the application with a window configured by a WindowBuilder
+    #[inline]
+    pub fn create_with(window_builder: WindowBuilder) -> Result<Self> {
         if cfg!(debug_assertions) {
             log_info!("Debug Mode is on, so logging will be more verbose.");
         }
 
-        let proto_window = Window::new(name, width, height, x, y);
+        let window = match window_builder.build() {
+            Ok(window) => window,
+            Err(err) => {
+                log_fatal!("Failed to create window");
+                return Err(err);
+            }
+        };
 
-        if proto_window.is_none() {
-            log_fatal!("Failed to create window");
-            return None;
-        }
+        let waker = match EventLoopWaker::new() {
+            Ok(waker) => waker,
+            Err(err) => {
+                log_fatal!("Failed to create the event loop waker");
+                window.shutdown();
+                return Err(err);
+            }
+        };
+
+        // headless apps run in tests and CI, where the machine's gamepads would only get in the way
+        let gamepads = if window.is_headless() {
+            GamepadManager::disabled()
+        } else {
+            GamepadManager::new()
+        };
 
-        return Some(App {
-            window: proto_window.unwrap(),
+        return Ok(App {
+            main_window: window.get_id(),
+            windows: vec![window],
             event_manager: EventManager::new(),
+            waker,
+            gamepads,
+            frame_timing: FrameTiming::new(),
+            recorder: None,
+            player: None,
             running: true
         })
     }
 
-    /// Gets the next event
+    /// Creates another window, sharing the display connection with the app's other windows
+    pub fn create_window(&mut self, window_builder: WindowBuilder) -> Result<WindowId> {
+        let res = match self.windows.first() {
+            Some(other) => window_builder.build_sharing(other),
+            None => window_builder.build(),
+        };
+
+        let window = match res {
+            Ok(window) => window,
+            Err(err) => {
+                log_error!("Failed to create window");
+                return Err(err);
+            }
+        };
+
+        let id = window.get_id();
+        self.windows.push(window);
+
+        return Ok(id);
+    }
+
+    /// Destroys one of the app's windows, events it already sent are still delivered
+    pub fn destroy_window(&mut self, id: WindowId) {
+        match self.windows.iter().position(|window| window.get_id() == id) {
+            Some(index) => {
+                let window = self.windows.remove(index);
+                window.shutdown();
+            }
+            None => {
+                log_warn!("Attempted to destroy a window that doesn't exist");
+            }
+        }
+    }
+
+    /// Gets the next event, from any of the app's windows or the replay running
     #[inline]
-    pub fn poll_events(&mut self) -> Option<crate::event::Event> {
-        // Todo: Benchmark this, while its simpler, it causes excess calls
-        self.window.update(self.event_manager.get_event_que());
+    pub fn poll_events(&mut self) -> Option<Event> {
+        self.update_windows();
         return self.event_manager.poll_events();
     }
 
+    /// Reads the windows' and gamepads' events into the event que, swapping in replayed
+    /// events and recording what arrived
+    fn update_windows(&mut self) {
+        let que = self.event_manager.get_event_que();
+        let first_new = que.len();
+
+        // Todo: Benchmark this, while its simpler, it causes excess calls
+        for window in self.windows.iter_mut() {
+            window.update(que);
+        }
+
+        self.gamepads.update(que);
+
+        if let Some(player) = self.player.as_mut() {
+            if !player.keeps_live_events() {
+                que.truncate(first_new);
+            }
+
+            player.play(self.frame_timing.frame_count, que);
+
+            if player.is_finished() {
+                log_info!("Replay finished");
+                self.player = None;
+            }
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            for event in que.range(first_new..) {
+                recorder.record(event, self.frame_timing.frame_count);
+            }
+        }
+    }
+
+    /// Gets the next event, sleeping until there is one rather than returning None
+    #[inline]
+    pub fn wait_events(&mut self) -> Option<Event> {
+        return self.wait(None);
+    }
+
+    /// Gets the next event, sleeping until there is one or the timeout has elapsed.
+    /// Returns None on timeout
+    #[inline]
+    pub fn wait_events_timeout(&mut self, timeout: Duration) -> Option<Event> {
+        return self.wait(Some(timeout));
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Option<Event> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            // a wake from before this poll has nothing left to wake for
+            self.waker.clear();
+
+            if let Some(event) = self.poll_events() {
+                return Some(event);
+            }
+
+            let remaining = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+
+                    Some(deadline - now)
+                }
+                None => None,
+            };
+
+            // what was read may not have made an event, like a compositor's ping, so it's
+            // polled again before waiting any longer
+            wait_for_events(&self.windows, &self.gamepads, &self.waker, remaining);
+        }
+    }
+
+    /// Gets a waker, which can be sent to other threads to make wait_events check for events
+    #[inline]
+    pub fn get_waker(&self) -> EventLoopWaker {
+        return self.waker.clone();
+    }
+
+    /// Gets the event manager, to subscribe event handlers with
+    #[inline]
+    pub fn get_event_manager_mut(&mut self) -> &mut EventManager {
+        return &mut self.event_manager;
+    }
+
+    /// Gives every event to the subscribed handlers, dropping the ones none of them consumed.
+    /// An alternative to polling for apps that handle everything through handlers
+    #[inline]
+    pub fn dispatch_events(&mut self) {
+        self.update_windows();
+        self.event_manager.dispatch_events();
+    }
+
+    /// Creates a proxy other threads can send user events to the app with
+    #[inline]
+    pub fn create_proxy(&self) -> EventLoopProxy {
+        return self.event_manager.create_proxy(self.waker.clone());
+    }
+
+    /// Marks the start of a new frame, call it once per frame for the frame timing and
+    /// the per frame input state to be kept
+    #[inline]
+    pub fn next_frame(&mut self) {
+        self.frame_timing.next_frame();
+        self.event_manager.next_frame();
+    }
+
+    /// Gets the time between the starts of the last two frames, zero during the first
+    #[inline]
+    pub fn get_delta_time(&self) -> Duration {
+        return self.frame_timing.delta;
+    }
+
+    /// Gets the frames per second averaged over the last second or so, zero until then
+    #[inline]
+    pub fn get_fps(&self) -> f32 {
+        return self.frame_timing.fps;
+    }
+
+    /// Gets how many frames have been started with next_frame
+    #[inline]
+    pub fn get_frame_count(&self) -> u64 {
+        return self.frame_timing.frame_count;
+    }
+
+    /// Gets when the current frame started, event times can be compared against it
+    #[inline]
+    pub fn get_frame_start(&self) -> Instant {
+        return self.frame_timing.frame_start.unwrap_or(self.frame_timing.created);
+    }
+
+    /// Gets the time since the app was created
+    #[inline]
+    pub fn get_elapsed_time(&self) -> Duration {
+        return self.frame_timing.created.elapsed();
+    }
+
+    /// Starts recording every event the windows send, replacing any recording in progress.
+    /// Events are placed by the frame they arrived in, so call next_frame every frame
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder::new(self.main_window, self.frame_timing.frame_count));
+    }
+
+    /// Stops recording, returning what was recorded or None if the app wasn't recording
+    #[inline]
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        return self.recorder.take().map(Recorder::finish);
+    }
+
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        return self.recorder.is_some();
+    }
+
+    /// Replays a recording in place of the windows' events, each event delivered in the same
+    /// frame after the start as it was recorded in. With keep_live_events the windows' events
+    /// are delivered as well, e.g. to stop a demo when a key is pressed
+    pub fn start_replay(&mut self, recording: Recording, keep_live_events: bool) {
+        self.player = Some(Player::new(
+            recording,
+            self.main_window,
+            self.frame_timing.frame_count,
+            keep_live_events,
+        ));
+    }
+
+    /// Stops the replay running, the windows' events are delivered again
+    #[inline]
+    pub fn stop_replay(&mut self) {
+        self.player = None;
+    }
+
+    /// Whether a replay is running, it stops by itself after its last event
+    #[inline]
+    pub fn is_replaying(&self) -> bool {
+        return self.player.is_some();
+    }
+
+    /// Gets the gamepads, kept up to date by poll_events. Apps whose main window is headless
+    /// never look for any
+    #[inline]
+    pub fn get_gamepads(&self) -> &GamepadManager {
+        return &self.gamepads;
+    }
+
+    /// Gets the gamepads, to add mappings or set dead zones
+    #[inline]
+    pub fn get_gamepads_mut(&mut self) -> &mut GamepadManager {
+        return &mut self.gamepads;
+    }
+
+    /// Gets the state of the keyboard and mouse, kept up to date by poll_events
+    #[inline]
+    pub fn get_input(&self) -> &InputState {
+        return self.event_manager.get_input();
+    }
+
+    #[inline]
+    pub fn get_actions(&self) -> &ActionMap {
+        return self.event_manager.get_actions();
+    }
+
+    /// Gets the actions, to bind them, load them from a file or capture a binding
+    #[inline]
+    pub fn get_actions_mut(&mut self) -> &mut ActionMap {
+        return self.event_manager.get_actions_mut();
+    }
+
+    /// Whether any of the action's bindings are held down
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        return self.get_actions().is_down(action, self.get_input());
+    }
+
+    /// Whether one of the action's bindings was pressed this frame
+    #[inline]
+    pub fn action_just_pressed(&self, action: &str) -> bool {
+        return self.get_actions().just_pressed(action, self.get_input());
+    }
+
+    /// Whether one of the action's bindings was released this frame, leaving none held
+    #[inline]
+    pub fn action_just_released(&self, action: &str) -> bool {
+        return self.get_actions().just_released(action, self.get_input());
+    }
+
+    /// Gets the modifier keys currently held and lock keys active
+    #[inline]
+    pub fn get_modifiers(&self) -> Modifiers {
+        return match self.windows.first() {
+            Some(window) => window.get_modifiers(),
+            None => Modifiers::default(),
+        };
+    }
+
     /// Sets an internal 'running' boolean to false
     #[inline]
     pub fn quit(&mut self) {
@@ -54,16 +424,161 @@ impl App {
     /// functions on the platform or with the renderer
     #[inline]
     pub fn shutdown(&self) {
-        self.window.shutdown();
+        for window in self.windows.iter() {
+            window.shutdown();
+        }
     }
 
     #[inline]
     pub fn get_raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
-        return self.window.raw_window_handle();
+        return self.get_window_ref().raw_window_handle();
     }
 
+    /// Gets the id of the window the app was created with
+    #[inline]
+    pub fn get_main_window_id(&self) -> WindowId {
+        return self.main_window;
+    }
+
+    /// Gets the window the app was created with, panics if it has been destroyed
     #[inline]
     pub fn get_window_ref(&self) -> &Window {
-        return &self.window;
+        return self
+            .get_window_by_id(self.main_window)
+            .expect("the main window has been destroyed");
+    }
+
+    /// Gets the window the app was created with, panics if it has been destroyed
+    #[inline]
+    pub fn get_window_mut(&mut self) -> &mut Window {
+        return self
+            .get_window_by_id_mut(self.main_window)
+            .expect("the main window has been destroyed");
     }
-}
\ No newline at end of file
+
+    #[inline]
+    pub fn get_window_by_id(&self, id: WindowId) -> Option<&Window> {
+        return self.windows.iter().find(|window| window.get_id() == id);
+    }
+
+    #[inline]
+    pub fn get_window_by_id_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        return self.windows.iter_mut().find(|window| window.get_id() == id);
+    }
+
+    /// Gets all of the app's windows
+    #[inline]
+    pub fn get_windows(&self) -> &[Window] {
+        return &self.windows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+    use crate::keys::{Key, ScanCode};
+
+    fn headless_app() -> App {
+        return App::create_with(WindowBuilder::new().with_size(640, 480).with_headless(true)).unwrap();
+    }
+
+    fn key_down(key: Key) -> EventType {
+        return EventType::KeyDown {
+            key,
+            scancode: ScanCode(0),
+            modifiers: Modifiers::default(),
+            is_repeat: false,
+        };
+    }
+
+    #[test]
+    fn polls_injected_events_in_order() {
+        let mut app = headless_app();
+        let sent = [key_down(Key::A), EventType::MouseMove { x: 3, y: 4 }, EventType::WinClose];
+
+        for e_type in sent.iter() {
+            app.get_window_mut().inject_event(e_type.clone());
+        }
+
+        for e_type in sent.iter() {
+            let event = app.poll_events().expect("an injected event was lost");
+            assert!(event.e_type == *e_type);
+            assert!(event.window == Some(app.get_main_window_id()));
+        }
+
+        assert!(app.poll_events().is_none());
+    }
+
+    #[test]
+    fn resize_updates_the_virtual_size() {
+        let mut app = headless_app();
+        app.get_window_mut().inject_event(EventType::WinResize { width: 800, height: 600 });
+
+        assert!(app.poll_events().is_some());
+        assert_eq!(app.get_window_ref().get_width(), 800);
+        assert_eq!(app.get_window_ref().get_height(), 600);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn headless_apps_dont_look_for_gamepads() {
+        let app = headless_app();
+
+        // nothing is opened, not even the hotplug monitor
+        assert!(app.get_gamepads().get_fds().is_empty());
+    }
+
+    #[test]
+    fn wait_returns_on_an_injected_event() {
+        let mut app = headless_app();
+        app.get_window_mut().inject_event(EventType::WinExpose);
+
+        let start = Instant::now();
+        let event = app.wait_events_timeout(Duration::from_secs(10));
+
+        assert!(event.is_some_and(|event| event.e_type == EventType::WinExpose));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn wait_times_out_without_events() {
+        let mut app = headless_app();
+        let timeout = Duration::from_millis(20);
+
+        let start = Instant::now();
+        assert!(app.wait_events_timeout(timeout).is_none());
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn stale_wakes_dont_end_a_wait() {
+        let mut app = headless_app();
+        let timeout = Duration::from_millis(20);
+
+        // a wake from while the app wasn't waiting, and one whose event was already polled
+        app.get_waker().wake();
+        app.create_proxy().send_event(1u8);
+        assert!(app.poll_events().is_some());
+
+        let start = Instant::now();
+        assert!(app.wait_events_timeout(timeout).is_none());
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn wait_returns_on_a_sent_event() {
+        let mut app = headless_app();
+        let proxy = app.create_proxy();
+
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            proxy.send_event(1u8);
+        });
+
+        let event = app.wait_events_timeout(Duration::from_secs(10));
+        sender.join().unwrap();
+
+        assert!(event.is_some_and(|event| matches!(event.e_type, EventType::User(_))));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt::{Display, Formatter};
+
+/// The reasons creating a window, application or renderer can fail
+#[derive(Debug)]
+pub enum Error {
+    /// No X display could be opened, e.g. DISPLAY isn't set or no X server is running
+    NoDisplay,
+    /// The XCB connection reported an error, holds the code from xcb_connection_has_error
+    XcbConnection(i32),
+    /// The platform refused to create the window
+    WindowCreation(String),
+    /// No graphics adapter compatible with the window's surface was found
+    NoAdapter,
+    /// The adapter couldn't provide a device and queue
+    RequestDevice(wgpu::RequestDeviceError),
+    /// The surface can't be configured for the window
+    SurfaceConfiguration(String),
+    /// Headless windows have nothing to render to
+    Headless,
+    /// What wakes the event loop from other threads couldn't be created, holds the OS error
+    EventLoopWaker(std::io::Error),
+    /// A recording couldn't be read or written
+    RecordingIo(std::io::Error),
+    /// The data read isn't a recording this version of rovella understands
+    InvalidRecording(String),
+    /// A bindings file couldn't be read or written
+    BindingsIo(std::io::Error),
+    /// A bindings file has a line that isn't an action and its bindings
+    InvalidBindings(String),
+    /// A gamepad mapping database couldn't be read
+    GamepadMappingsIo(std::io::Error),
+    /// The text isn't the name of a key
+    UnknownKey(String),
+}
+
+/// A Result with rovella's Error type
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NoDisplay => write!(f, "Could not open the X display, is an X server running?"),
+            Error::XcbConnection(code) => {
+                let reason = match code {
+                    1 => "socket, pipe or stream error",
+                    2 => "extension not supported",
+                    3 => "insufficient memory",
+                    4 => "request length exceeded",
+                    5 => "error parsing the display string",
+                    6 => "invalid screen",
+                    7 => "error passing a file descriptor",
+                    _ => "unknown error",
+                };
+
+                write!(f, "Unable to connect to the X server: {} (code {})", reason, code)
+            }
+            Error::WindowCreation(reason) => write!(f, "Failed to create window: {}", reason),
+            Error::NoAdapter => write!(f, "No graphics adapter compatible with the window was found"),
+            Error::RequestDevice(err) => write!(f, "Failed to create renderer device and queue: {}", err),
+            Error::SurfaceConfiguration(reason) => write!(f, "Failed to configure surface: {}", reason),
+            Error::Headless => write!(f, "Headless windows have no surface to render to"),
+            Error::EventLoopWaker(err) => write!(f, "Failed to create the event loop waker: {}", err),
+            Error::RecordingIo(err) => write!(f, "Failed to read or write the recording: {}", err),
+            Error::InvalidRecording(reason) => write!(f, "Invalid recording: {}", reason),
+            Error::BindingsIo(err) => write!(f, "Failed to read or write the bindings: {}", err),
+            Error::InvalidBindings(reason) => write!(f, "Invalid bindings: {}", reason),
+            Error::GamepadMappingsIo(err) => write!(f, "Failed to read the gamepad mappings: {}", err),
+            Error::UnknownKey(name) => write!(f, "\"{}\" isn't the name of a key", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::RequestDevice(err) => Some(err),
+            Error::EventLoopWaker(err) => Some(err),
+            Error::RecordingIo(err) => Some(err),
+            Error::BindingsIo(err) => Some(err),
+            Error::GamepadMappingsIo(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(err)
+    }
+}
@@ -1,26 +1,73 @@
+use std::any::Any;
 use std::borrow::BorrowMut;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
-use crate::keys::Key;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Instant;
+use crate::action::ActionMap;
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use crate::input::InputState;
+use crate::keys::{Key, Modifiers, ScanCode};
+use crate::platform::{EventLoopWaker, WindowId};
 
-/// The kind of Event that's been triggered
+/// The kind of Event that's been triggered, along with the data that comes with it
+#[derive(Clone, PartialEq)]
 pub enum EventType {
     // Direct Wrapping of Platform Events
+    /// The window has been mapped or restored and is being shown
     WinShow,
+    /// The window has been unmapped or minimized, there's no need to render
+    WinHide,
     WinClose,
-    WinResize,
-    KeyDown,
-    KeyUp,
-    MouseMove,
-    MouseWheel,
-    MouseMidBtnUp,
-    MouseMidBtnDown,
-    MouseLeftBtnUp,
-    MouseLeftBtnDown,
-    MouseRightBtnUp,
-    MouseRightBtnDown,
+    /// Part of the window needs redrawing
+    WinExpose,
+    WinFocusGained,
+    WinFocusLost,
+    WinResize { width: u16, height: u16 },
+    WinMove { x: i16, y: i16 },
+    /// key is what the layout makes the key and scancode is where it is on the keyboard.
+    /// is_repeat is set for presses generated by holding the key down
+    KeyDown { key: Key, scancode: ScanCode, modifiers: Modifiers, is_repeat: bool },
+    KeyUp { key: Key, scancode: ScanCode, modifiers: Modifiers },
+    /// The cursor's position relative to the top left of the window
+    MouseMove { x: i16, y: i16 },
+    /// Motion straight from the mouse, before acceleration and not stopped by the edges of
+    /// the screen, for camera controls. Sent to the focused window, or on Wayland the one
+    /// under the cursor
+    MouseRawMotion { delta_x: f32, delta_y: f32 },
+    /// The cursor has entered the window
+    MouseEnter,
+    /// The cursor has left the window
+    MouseLeave,
+    /// Scroll amount in wheel notches, positive is up and to the right. Smooth scrolling
+    /// gives fractions of a notch, except on X11 where it's always whole notches
+    MouseWheel { delta_x: f32, delta_y: f32 },
+    MouseMidBtnUp { modifiers: Modifiers },
+    MouseMidBtnDown { modifiers: Modifiers },
+    MouseLeftBtnUp { modifiers: Modifiers },
+    MouseLeftBtnDown { modifiers: Modifiers },
+    MouseRightBtnUp { modifiers: Modifiers },
+    MouseRightBtnDown { modifiers: Modifiers },
+    /// Text produced by the keyboard layout or committed by an input method, sent after
+    /// the KeyDown that produced it
+    TextInput { text: String },
+    /// An input method has started composing text
+    TextCompositionStart,
+    /// The text being composed so far, with the cursor position counted in chars
+    TextComposition { text: String, cursor: usize },
+    /// Composition has ended, either committed through TextInput or cancelled
+    TextCompositionEnd,
+    /// A gamepad has been plugged in, or was already connected when the app started
+    GamepadConnected { id: GamepadId },
+    GamepadDisconnected { id: GamepadId },
+    GamepadButtonDown { id: GamepadId, button: GamepadButton },
+    GamepadButtonUp { id: GamepadId, button: GamepadButton },
+    /// An axis has moved, value has the dead zone applied, see GamepadAxis for the ranges
+    GamepadAxisMotion { id: GamepadId, axis: GamepadAxis, value: f32 },
 
     // rovella Events
+    /// An event of the app's own, sent through an EventLoopProxy
+    User(UserEvent),
 
     // other
     None,
@@ -30,20 +77,38 @@ pub enum EventType {
 impl Display for EventType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            EventType::WinShow => write!(f, "WinShow"),
+            EventType::WinHide => write!(f, "WinHide"),
             EventType::WinClose => write!(f, "WinClose"),
-            EventType::WinResize => write!(f, "WinResize"),
-            EventType::KeyDown => write!(f, "KeyDown"),
-            EventType::KeyUp => write!(f, "KeyUp"),
-            EventType::MouseMove => write!(f, "MouseMove"),
+            EventType::WinExpose => write!(f, "WinExpose"),
+            EventType::WinFocusGained => write!(f, "WinFocusGained"),
+            EventType::WinFocusLost => write!(f, "WinFocusLost"),
+            EventType::WinResize { .. } => write!(f, "WinResize"),
+            EventType::WinMove { .. } => write!(f, "WinMove"),
+            EventType::KeyDown { .. } => write!(f, "KeyDown"),
+            EventType::KeyUp { .. } => write!(f, "KeyUp"),
+            EventType::MouseMove { .. } => write!(f, "MouseMove"),
+            EventType::MouseRawMotion { .. } => write!(f, "MouseRawMotion"),
+            EventType::MouseEnter => write!(f, "MouseEnter"),
+            EventType::MouseLeave => write!(f, "MouseLeave"),
             EventType::None => write!(f, "None"),
-            EventType::MouseWheel => write!(f, "MouseWheel"),
-            EventType::MouseMidBtnUp => write!(f, "MouseMidBtnUp"),
-            EventType::MouseMidBtnDown => write!(f, "MouseMidBtnDown"),
-            EventType::MouseLeftBtnUp => write!(f, "MouseLeftBtnUp"),
-            EventType::MouseLeftBtnDown => write!(f, "MouseLeftBtnDown"),
-            EventType::MouseRightBtnUp => write!(f, "MouseRightBtnUp"),
-            EventType::MouseRightBtnDown => write!(f, "MouseRightBtnDown"),
-            _ => write!(f, "Unknown"),
+            EventType::MouseWheel { .. } => write!(f, "MouseWheel"),
+            EventType::MouseMidBtnUp { .. } => write!(f, "MouseMidBtnUp"),
+            EventType::MouseMidBtnDown { .. } => write!(f, "MouseMidBtnDown"),
+            EventType::MouseLeftBtnUp { .. } => write!(f, "MouseLeftBtnUp"),
+            EventType::MouseLeftBtnDown { .. } => write!(f, "MouseLeftBtnDown"),
+            EventType::MouseRightBtnUp { .. } => write!(f, "MouseRightBtnUp"),
+            EventType::MouseRightBtnDown { .. } => write!(f, "MouseRightBtnDown"),
+            EventType::TextInput { .. } => write!(f, "TextInput"),
+            EventType::TextCompositionStart => write!(f, "TextCompositionStart"),
+            EventType::TextComposition { .. } => write!(f, "TextComposition"),
+            EventType::TextCompositionEnd => write!(f, "TextCompositionEnd"),
+            EventType::GamepadConnected { .. } => write!(f, "GamepadConnected"),
+            EventType::GamepadDisconnected { .. } => write!(f, "GamepadDisconnected"),
+            EventType::GamepadButtonDown { .. } => write!(f, "GamepadButtonDown"),
+            EventType::GamepadButtonUp { .. } => write!(f, "GamepadButtonUp"),
+            EventType::GamepadAxisMotion { .. } => write!(f, "GamepadAxisMotion"),
+            EventType::User(_) => write!(f, "User"),
         }
         .unwrap();
 
@@ -51,38 +116,184 @@ impl Display for EventType {
     }
 }
 
-/// For storing signed and unsigned types
-pub union EventData {
-    pub m_i32: i32,
-    pub m_u32: u32,
-    pub m_arr2_i16: [i16;2],
-    pub m_arr2_u16: [u16;2]
+/// The kind of an EventType without its data, what event handlers are subscribed by
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventKind {
+    WinShow,
+    WinHide,
+    WinClose,
+    WinExpose,
+    WinFocusGained,
+    WinFocusLost,
+    WinResize,
+    WinMove,
+    KeyDown,
+    KeyUp,
+    MouseMove,
+    MouseRawMotion,
+    MouseEnter,
+    MouseLeave,
+    MouseWheel,
+    MouseMidBtnUp,
+    MouseMidBtnDown,
+    MouseLeftBtnUp,
+    MouseLeftBtnDown,
+    MouseRightBtnUp,
+    MouseRightBtnDown,
+    TextInput,
+    TextCompositionStart,
+    TextComposition,
+    TextCompositionEnd,
+    GamepadConnected,
+    GamepadDisconnected,
+    GamepadButtonDown,
+    GamepadButtonUp,
+    GamepadAxisMotion,
+    User,
+    None,
 }
 
-/// setting a default value
-impl Default for EventData {
-    fn default() -> Self {
-        EventData { m_u32: 0 }
+impl EventType {
+    /// Gets the kind of event this is
+    pub fn kind(&self) -> EventKind {
+        return match self {
+            EventType::WinShow => EventKind::WinShow,
+            EventType::WinHide => EventKind::WinHide,
+            EventType::WinClose => EventKind::WinClose,
+            EventType::WinExpose => EventKind::WinExpose,
+            EventType::WinFocusGained => EventKind::WinFocusGained,
+            EventType::WinFocusLost => EventKind::WinFocusLost,
+            EventType::WinResize { .. } => EventKind::WinResize,
+            EventType::WinMove { .. } => EventKind::WinMove,
+            EventType::KeyDown { .. } => EventKind::KeyDown,
+            EventType::KeyUp { .. } => EventKind::KeyUp,
+            EventType::MouseMove { .. } => EventKind::MouseMove,
+            EventType::MouseRawMotion { .. } => EventKind::MouseRawMotion,
+            EventType::MouseEnter => EventKind::MouseEnter,
+            EventType::MouseLeave => EventKind::MouseLeave,
+            EventType::MouseWheel { .. } => EventKind::MouseWheel,
+            EventType::MouseMidBtnUp { .. } => EventKind::MouseMidBtnUp,
+            EventType::MouseMidBtnDown { .. } => EventKind::MouseMidBtnDown,
+            EventType::MouseLeftBtnUp { .. } => EventKind::MouseLeftBtnUp,
+            EventType::MouseLeftBtnDown { .. } => EventKind::MouseLeftBtnDown,
+            EventType::MouseRightBtnUp { .. } => EventKind::MouseRightBtnUp,
+            EventType::MouseRightBtnDown { .. } => EventKind::MouseRightBtnDown,
+            EventType::TextInput { .. } => EventKind::TextInput,
+            EventType::TextCompositionStart => EventKind::TextCompositionStart,
+            EventType::TextComposition { .. } => EventKind::TextComposition,
+            EventType::TextCompositionEnd => EventKind::TextCompositionEnd,
+            EventType::GamepadConnected { .. } => EventKind::GamepadConnected,
+            EventType::GamepadDisconnected { .. } => EventKind::GamepadDisconnected,
+            EventType::GamepadButtonDown { .. } => EventKind::GamepadButtonDown,
+            EventType::GamepadButtonUp { .. } => EventKind::GamepadButtonUp,
+            EventType::GamepadAxisMotion { .. } => EventKind::GamepadAxisMotion,
+            EventType::User(_) => EventKind::User,
+            EventType::None => EventKind::None,
+        };
     }
 }
 
-
-/// The Event struct, it's packed to optimise memory access
-#[repr(packed)]
+/// The Event struct, the data specific to each kind of event lives in its EventType
+#[derive(Clone, PartialEq)]
 pub struct Event {
+    /// The window the event came from, set by Window::update
+    pub window: Option<WindowId>,
     pub e_type: EventType,
-    pub data: EventData
+    /// When the event happened, taken from the platform's own event time where it has one
+    pub time: Instant,
 }
 
 impl Event {
+    /// Creates an event that happened now
     #[inline]
-    pub fn get_key(&self) -> Key {
-        Key::from(unsafe { self.data.m_u32 })
+    pub fn new(e_type: EventType) -> Event {
+        Event::at(e_type, Instant::now())
     }
 
+    /// Creates an event that happened at time
     #[inline]
-    pub fn get_xy(&self) -> (i16, i16) {
-        return unsafe { (self.data.m_arr2_i16[0], self.data.m_arr2_i16[1]) }
+    pub fn at(e_type: EventType, time: Instant) -> Event {
+        Event { window: None, e_type, time }
+    }
+}
+
+/// The data of a user event, which can be of any type and is got back with downcast_ref
+#[derive(Clone)]
+pub struct UserEvent(Arc<dyn Any + Send + Sync>);
+
+impl UserEvent {
+    #[inline]
+    pub fn new<T: Any + Send + Sync>(data: T) -> UserEvent {
+        UserEvent(Arc::new(data))
+    }
+
+    /// Whether the event holds a T
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        return self.0.is::<T>();
+    }
+
+    /// Gets the data if it's a T
+    #[inline]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        return self.0.downcast_ref::<T>();
+    }
+}
+
+/// User events are only equal to copies of themselves, as the data can't be compared
+impl PartialEq for UserEvent {
+    fn eq(&self, other: &Self) -> bool {
+        return Arc::ptr_eq(&self.0, &other.0);
+    }
+}
+
+/// Sends user events to an App from any thread, waking it if it's waiting on events.
+/// Get one with App::create_proxy, it can be cloned for every thread that needs one
+#[derive(Clone)]
+pub struct EventLoopProxy {
+    que: Weak<Mutex<VecDeque<Event>>>,
+    waker: EventLoopWaker,
+}
+
+impl EventLoopProxy {
+    /// Sends an event, delivered as an EventType::User with no window.
+    /// Returns false if the app it was created from is gone
+    pub fn send_event<T: Any + Send + Sync>(&self, data: T) -> bool {
+        let que = match self.que.upgrade() {
+            Some(que) => que,
+            None => return false,
+        };
+
+        // a thread panicking mid-push can't leave the que half-changed, so poisoning is ignored
+        que.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push_back(Event::new(EventType::User(UserEvent::new(data))));
+
+        self.waker.wake();
+
+        return true;
+    }
+}
+
+/// Identifies a subscribed handler, to unsubscribe it with
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HandlerId(u64);
+
+/// A handler returns true to consume the event, so handlers after it, polling and the input
+/// state never see it
+pub type EventCallback = Box<dyn FnMut(&Event) -> bool>;
+
+struct EventHandler {
+    id: HandlerId,
+    // None handles every kind of event
+    kind: Option<EventKind>,
+    priority: i32,
+    callback: EventCallback,
+}
+
+impl Default for EventManager {
+    fn default() -> Self {
+        EventManager::new()
     }
 }
 
@@ -90,7 +301,14 @@ impl Event {
 pub type EventDeque = VecDeque<Event>;
 
 pub struct EventManager {
-    pub que: EventDeque
+    pub que: EventDeque,
+    // user events sent from other threads, moved into que as events are polled
+    user_que: Arc<Mutex<VecDeque<Event>>>,
+    // highest priority first, equal priorities in the order they subscribed
+    handlers: Vec<EventHandler>,
+    next_handler_id: u64,
+    input: InputState,
+    actions: ActionMap,
 }
 
 impl EventManager {
@@ -98,6 +316,11 @@ impl EventManager {
     pub fn new() -> EventManager {
         EventManager {
             que: EventDeque::new(),
+            user_que: Arc::new(Mutex::new(VecDeque::new())),
+            handlers: Vec::new(),
+            next_handler_id: 0,
+            input: InputState::new(),
+            actions: ActionMap::new(),
         }
     }
 
@@ -107,9 +330,230 @@ impl EventManager {
         return self.que.borrow_mut();
     }
 
-    /// Gets the next Event in the event que
+    /// Gets the state of the keyboard and mouse as of the events polled so far
+    #[inline]
+    pub fn get_input(&self) -> &InputState {
+        return &self.input;
+    }
+
+    #[inline]
+    pub fn get_actions(&self) -> &ActionMap {
+        return &self.actions;
+    }
+
+    /// Gets the actions, to bind them or start capturing a binding
     #[inline]
+    pub fn get_actions_mut(&mut self) -> &mut ActionMap {
+        return &mut self.actions;
+    }
+
+    /// Starts a new input frame, see InputState
+    #[inline]
+    pub(crate) fn next_frame(&mut self) {
+        self.input.next_frame();
+    }
+
+    /// Gets the next Event in the event que, after adding the user events sent since the last poll.
+    /// Each event is given to a binding capture if one is waiting and to the subscribed handlers,
+    /// and skipped if one of them consumes it. Only the events that aren't consumed update the
+    /// input state, apart from releases, so a UI layer can swallow clicks before gameplay sees them
     pub fn poll_events(&mut self) -> Option<Event> {
-        return self.que.pop_front();
+        {
+            let mut user_que = self.user_que.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            self.que.extend(user_que.drain(..));
+        }
+
+        while let Some(event) = self.que.pop_front() {
+            if self.actions.capture(&event.e_type) || self.dispatch(&event) {
+                self.input.handle_consumed_event(&event);
+                continue;
+            }
+
+            self.input.handle_event(&event);
+            return Some(event);
+        }
+
+        self.input.finish_events();
+        return None;
+    }
+
+    /// Gives every queued event to the handlers, dropping the ones none of them consumed.
+    /// For when all events are handled through handlers rather than polling
+    pub fn dispatch_events(&mut self) {
+        while self.poll_events().is_some() {}
+    }
+
+    /// Subscribes a handler to one kind of event. Handlers with a higher priority see events
+    /// first, ones with the same priority in the order they were subscribed
+    pub fn subscribe<F>(&mut self, kind: EventKind, priority: i32, callback: F) -> HandlerId
+    where
+        F: FnMut(&Event) -> bool + 'static,
+    {
+        return self.add_handler(Some(kind), priority, Box::new(callback));
+    }
+
+    /// Subscribes a handler to every kind of event, see subscribe
+    pub fn subscribe_all<F>(&mut self, priority: i32, callback: F) -> HandlerId
+    where
+        F: FnMut(&Event) -> bool + 'static,
+    {
+        return self.add_handler(None, priority, Box::new(callback));
+    }
+
+    /// Removes a handler, returning whether it was subscribed
+    pub fn unsubscribe(&mut self, id: HandlerId) -> bool {
+        let len = self.handlers.len();
+        self.handlers.retain(|handler| handler.id != id);
+
+        return self.handlers.len() != len;
+    }
+
+    fn add_handler(&mut self, kind: Option<EventKind>, priority: i32, callback: EventCallback) -> HandlerId {
+        let id = HandlerId(self.next_handler_id);
+        self.next_handler_id += 1;
+
+        // after every handler with the same or a higher priority
+        let index = self
+            .handlers
+            .iter()
+            .position(|handler| handler.priority < priority)
+            .unwrap_or(self.handlers.len());
+
+        self.handlers.insert(index, EventHandler { id, kind, priority, callback });
+
+        return id;
+    }
+
+    /// Runs the handlers for an event in priority order, returning whether one consumed it
+    fn dispatch(&mut self, event: &Event) -> bool {
+        let kind = event.e_type.kind();
+
+        for handler in self.handlers.iter_mut() {
+            if handler.kind.is_some_and(|handler_kind| handler_kind != kind) {
+                continue;
+            }
+
+            if (handler.callback)(event) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    /// Creates a proxy that sends user events into this manager, waking the loop with waker
+    #[inline]
+    pub fn create_proxy(&self, waker: EventLoopWaker) -> EventLoopProxy {
+        return EventLoopProxy {
+            que: Arc::downgrade(&self.user_que),
+            waker,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::MouseButton;
+
+    fn left_down() -> EventType {
+        return EventType::MouseLeftBtnDown { modifiers: Modifiers::default() };
+    }
+
+    fn left_up() -> EventType {
+        return EventType::MouseLeftBtnUp { modifiers: Modifiers::default() };
+    }
+
+    #[test]
+    fn consumed_clicks_are_hidden_from_the_input_state() {
+        let mut manager = EventManager::new();
+        manager.subscribe(EventKind::MouseLeftBtnDown, 0, |_| true);
+
+        manager.que.push_back(Event::new(left_down()));
+        assert!(manager.poll_events().is_none());
+        assert!(!manager.get_input().is_mouse_down(MouseButton::Left));
+        assert!(!manager.get_input().mouse_just_pressed(MouseButton::Left));
+
+        // the release of a press that was hidden isn't reported either
+        manager.que.push_back(Event::new(left_up()));
+        assert!(manager.poll_events().is_some());
+        assert!(!manager.get_input().mouse_just_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn consumed_releases_still_let_go() {
+        let mut manager = EventManager::new();
+
+        manager.que.push_back(Event::new(left_down()));
+        assert!(manager.poll_events().is_some());
+        assert!(manager.get_input().is_mouse_down(MouseButton::Left));
+
+        manager.subscribe_all(0, |_| true);
+        manager.que.push_back(Event::new(left_up()));
+        assert!(manager.poll_events().is_none());
+        assert!(!manager.get_input().is_mouse_down(MouseButton::Left));
+        assert!(manager.get_input().mouse_just_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn unconsumed_events_reach_the_input_state() {
+        let mut manager = EventManager::new();
+        manager.subscribe(EventKind::KeyDown, 0, |_| false);
+
+        manager.que.push_back(Event::new(EventType::KeyDown {
+            key: Key::Space,
+            scancode: ScanCode::from_key(Key::Space).unwrap(),
+            modifiers: Modifiers::default(),
+            is_repeat: false,
+        }));
+
+        assert!(manager.poll_events().is_some());
+        assert!(manager.get_input().just_pressed(Key::Space));
+        assert!(manager.get_input().physical_just_pressed(Key::Space));
+    }
+
+    #[test]
+    fn captured_presses_are_hidden_from_the_input_state() {
+        let mut manager = EventManager::new();
+        manager.get_actions_mut().start_capture("fire", true);
+
+        manager.que.push_back(Event::new(left_down()));
+        assert!(manager.poll_events().is_none());
+        assert!(!manager.get_input().mouse_just_pressed(MouseButton::Left));
+        assert!(!manager.get_actions().just_pressed("fire", manager.get_input()));
+    }
+
+    fn in_window(e_type: EventType, id: u64) -> Event {
+        let mut event = Event::new(e_type);
+        event.window = Some(WindowId(id));
+        return event;
+    }
+
+    #[test]
+    fn focus_moving_between_windows_keeps_buttons_held() {
+        let mut manager = EventManager::new();
+        manager.que.push_back(in_window(EventType::WinFocusGained, 1));
+        manager.que.push_back(in_window(left_down(), 1));
+        while manager.poll_events().is_some() {}
+
+        manager.que.push_back(in_window(EventType::WinFocusLost, 1));
+        manager.que.push_back(in_window(EventType::WinFocusGained, 2));
+        while manager.poll_events().is_some() {}
+        assert!(manager.get_input().is_mouse_down(MouseButton::Left));
+
+        manager.que.push_back(in_window(EventType::WinFocusLost, 2));
+        while manager.poll_events().is_some() {}
+        assert!(!manager.get_input().is_mouse_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn mouse_delta_isnt_taken_across_windows() {
+        let mut manager = EventManager::new();
+        manager.que.push_back(in_window(EventType::MouseMove { x: 10, y: 10 }, 1));
+        manager.que.push_back(in_window(EventType::MouseMove { x: 500, y: 300 }, 2));
+        manager.que.push_back(in_window(EventType::MouseMove { x: 505, y: 302 }, 2));
+        while manager.poll_events().is_some() {}
+
+        assert_eq!(manager.get_input().get_mouse_delta(), (5, 2));
     }
 }
@@ -0,0 +1,1027 @@
+// only Linux has a backend so far, elsewhere the mapping is never applied
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::fs;
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::event::{Event, EventDeque, EventType};
+
+#[cfg(target_os = "linux")]
+mod evdev;
+
+/// Identifies a connected gamepad, a gamepad that's unplugged and plugged back in gets a new id
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GamepadId(pub(crate) u32);
+
+/// The buttons of a standard gamepad, named by where they are on an Xbox controller
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GamepadButton {
+    /// The bottom face button
+    A,
+    /// The right face button
+    B,
+    /// The left face button
+    X,
+    /// The top face button
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// The axes of a standard gamepad
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GamepadAxis {
+    /// -1 is left and 1 is right
+    LeftX,
+    /// -1 is up and 1 is down
+    LeftY,
+    RightX,
+    RightY,
+    /// 0 is released and 1 is fully pressed
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadButton {
+    /// Every button, in the order they're declared so a button's index in it is `button as usize`
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::A, GamepadButton::B, GamepadButton::X, GamepadButton::Y, GamepadButton::Back,
+        GamepadButton::Guide, GamepadButton::Start, GamepadButton::LeftStick, GamepadButton::RightStick,
+        GamepadButton::LeftShoulder, GamepadButton::RightShoulder, GamepadButton::DPadUp,
+        GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight,
+    ];
+
+    /// Gets the button by its name in SDL mappings, e.g. "leftshoulder"
+    fn from_sdl_name(name: &str) -> Option<GamepadButton> {
+        let button = match name {
+            "a" => GamepadButton::A,
+            "b" => GamepadButton::B,
+            "x" => GamepadButton::X,
+            "y" => GamepadButton::Y,
+            "back" => GamepadButton::Back,
+            "guide" => GamepadButton::Guide,
+            "start" => GamepadButton::Start,
+            "leftstick" => GamepadButton::LeftStick,
+            "rightstick" => GamepadButton::RightStick,
+            "leftshoulder" => GamepadButton::LeftShoulder,
+            "rightshoulder" => GamepadButton::RightShoulder,
+            "dpup" => GamepadButton::DPadUp,
+            "dpdown" => GamepadButton::DPadDown,
+            "dpleft" => GamepadButton::DPadLeft,
+            "dpright" => GamepadButton::DPadRight,
+            _ => return None,
+        };
+
+        return Some(button);
+    }
+}
+
+impl GamepadAxis {
+    /// Every axis, in the order they're declared so an axis' index in it is `axis as usize`
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftX, GamepadAxis::LeftY, GamepadAxis::RightX, GamepadAxis::RightY,
+        GamepadAxis::LeftTrigger, GamepadAxis::RightTrigger,
+    ];
+
+    /// Gets the axis by its name in SDL mappings, e.g. "lefttrigger"
+    fn from_sdl_name(name: &str) -> Option<GamepadAxis> {
+        let axis = match name {
+            "leftx" => GamepadAxis::LeftX,
+            "lefty" => GamepadAxis::LeftY,
+            "rightx" => GamepadAxis::RightX,
+            "righty" => GamepadAxis::RightY,
+            "lefttrigger" => GamepadAxis::LeftTrigger,
+            "righttrigger" => GamepadAxis::RightTrigger,
+            _ => return None,
+        };
+
+        return Some(axis);
+    }
+
+    #[inline]
+    fn is_trigger(self) -> bool {
+        return matches!(self, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger);
+    }
+}
+
+/// The part of an axis a mapping uses, written as a + or - before it in SDL mappings
+#[derive(Clone, Copy, PartialEq)]
+enum AxisRange {
+    Full,
+    Positive,
+    Negative,
+}
+
+/// A device input as SDL mappings number them
+#[derive(Clone, Copy, PartialEq)]
+enum MappingInput {
+    Button(u32),
+    Axis { index: u32, range: AxisRange, invert: bool },
+    /// A hat and the direction bit, 1 up, 2 right, 4 down and 8 left
+    Hat { index: u32, mask: u8 },
+}
+
+/// What a gamepad input is mapped to
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    Button(GamepadButton),
+    Axis(GamepadAxis, AxisRange),
+}
+
+/// An entry of an SDL game controller mapping database
+struct Mapping {
+    // lowercase, with the name checksum SDL 2.26 added zeroed so older and newer entries match
+    guid: String,
+    name: String,
+    binds: Vec<(MappingInput, Output)>,
+}
+
+/// The platform name SDL mappings are given for
+#[cfg(target_os = "linux")]
+const MAPPING_PLATFORM: &str = "Linux";
+#[cfg(target_os = "windows")]
+const MAPPING_PLATFORM: &str = "Windows";
+
+/// Where the checksum and version are in a GUID string
+const GUID_CRC: std::ops::Range<usize> = 4..8;
+const GUID_VERSION: std::ops::Range<usize> = 24..28;
+
+/// Zeroes part of a GUID string
+fn zero_guid(guid: &str, range: std::ops::Range<usize>) -> String {
+    let mut guid = guid.to_string();
+    guid.replace_range(range.clone(), &"0".repeat(range.len()));
+    return guid;
+}
+
+impl Mapping {
+    /// Parses a line like `guid,name,a:b0,leftx:a0,dpup:h0.1,platform:Linux,`.
+    /// Lines for other platforms give None, as do ones that don't parse
+    fn parse(line: &str) -> Option<Mapping> {
+        let mut fields = line.split(',');
+
+        let guid = fields.next()?.trim().to_ascii_lowercase();
+        if guid.len() != 32 || !guid.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let name = fields.next()?.trim().to_string();
+        let mut binds = Vec::new();
+
+        for field in fields.map(str::trim).filter(|field| !field.is_empty()) {
+            let (output, input) = field.split_once(':')?;
+
+            if output == "platform" {
+                if input != MAPPING_PLATFORM {
+                    return None;
+                }
+                continue;
+            }
+
+            let (output_range, output) = split_range(output);
+
+            let output = match GamepadButton::from_sdl_name(output) {
+                Some(button) => Output::Button(button),
+                None => match GamepadAxis::from_sdl_name(output) {
+                    Some(axis) => Output::Axis(axis, output_range),
+                    // other fields, like paddles, crc and hints, aren't used
+                    None => continue,
+                },
+            };
+
+            let (input_range, input) = split_range(input);
+
+            let input = if let Some(index) = input.strip_prefix('b') {
+                MappingInput::Button(index.parse().ok()?)
+            } else if let Some(axis) = input.strip_prefix('a') {
+                let (index, invert) = match axis.strip_suffix('~') {
+                    Some(index) => (index, true),
+                    None => (axis, false),
+                };
+
+                MappingInput::Axis {
+                    index: index.parse().ok()?,
+                    range: input_range,
+                    invert,
+                }
+            } else if let Some(hat) = input.strip_prefix('h') {
+                let (index, mask) = hat.split_once('.')?;
+
+                MappingInput::Hat {
+                    index: index.parse().ok()?,
+                    mask: mask.parse().ok()?,
+                }
+            } else {
+                return None;
+            };
+
+            binds.push((input, output));
+        }
+
+        return Some(Mapping {
+            guid: zero_guid(&guid, GUID_CRC),
+            name,
+            binds,
+        });
+    }
+}
+
+/// Splits the + or - off the front of a mapping field
+fn split_range(field: &str) -> (AxisRange, &str) {
+    if let Some(field) = field.strip_prefix('+') {
+        return (AxisRange::Positive, field);
+    }
+
+    if let Some(field) = field.strip_prefix('-') {
+        return (AxisRange::Negative, field);
+    }
+
+    return (AxisRange::Full, field);
+}
+
+/// A device input by its platform code, resolved from a mapping for one device
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Button(u16),
+    Axis { code: u16, range: AxisRange, invert: bool },
+    /// A hat axis, pressed when it points in the positive or negative direction
+    Hat { code: u16, positive: bool },
+}
+
+impl Source {
+    /// How far through the source's range a change puts it, from 0 to 1, or None if the
+    /// change is to another input
+    fn amount(self, change: InputChange) -> Option<f32> {
+        return match (self, change) {
+            (Source::Button(code), InputChange::Button { code: changed, down }) if code == changed => {
+                Some(if down { 1.0 } else { 0.0 })
+            }
+            (Source::Axis { code, range, invert }, InputChange::Axis { code: changed, value }) if code == changed => {
+                Some(range_amount(value, range, invert))
+            }
+            (Source::Hat { code, positive }, InputChange::Axis { code: changed, value }) if code == changed => {
+                let pressed = if positive { value > 0.0 } else { value < 0.0 };
+                Some(if pressed { 1.0 } else { 0.0 })
+            }
+            _ => None,
+        };
+    }
+}
+
+/// A change to one of a device's inputs, with axes scaled to -1 to 1
+#[derive(Clone, Copy, PartialEq)]
+enum InputChange {
+    Button { code: u16, down: bool },
+    Axis { code: u16, value: f32 },
+}
+
+/// A connected gamepad and the state of its buttons and axes
+pub struct Gamepad {
+    id: GamepadId,
+    name: String,
+    guid: String,
+    binds: Vec<(Source, Output)>,
+    buttons: [bool; 15],
+    // the axes before and after the dead zones
+    raw_axes: [f32; 6],
+    axes: [f32; 6],
+    #[cfg(target_os = "linux")]
+    device: evdev::Device,
+}
+
+impl Gamepad {
+    #[inline]
+    pub fn get_id(&self) -> GamepadId {
+        return self.id;
+    }
+
+    /// Gets the name from the mapping, or the one the device reports if it's unmapped
+    #[inline]
+    pub fn get_name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// Gets the GUID mappings for the gamepad are looked up with
+    #[inline]
+    pub fn get_guid(&self) -> &str {
+        return &self.guid;
+    }
+
+    #[inline]
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        return self.buttons[button as usize];
+    }
+
+    /// Gets the position of an axis, with the dead zone applied
+    #[inline]
+    pub fn get_axis(&self, axis: GamepadAxis) -> f32 {
+        return self.axes[axis as usize];
+    }
+
+    /// Updates the outputs mapped from a device input, pushing events for the ones that changed
+    fn handle_input(&mut self, change: InputChange, dead_zones: &[f32; 6], events: &mut Vec<EventType>) {
+        for index in 0..self.binds.len() {
+            let (source, output) = self.binds[index];
+
+            let amount = match source.amount(change) {
+                Some(amount) => amount,
+                None => continue,
+            };
+
+            match output {
+                Output::Button(button) => {
+                    let down = amount >= 0.5;
+
+                    if self.buttons[button as usize] != down {
+                        self.buttons[button as usize] = down;
+
+                        events.push(if down {
+                            EventType::GamepadButtonDown { id: self.id, button }
+                        } else {
+                            EventType::GamepadButtonUp { id: self.id, button }
+                        });
+                    }
+                }
+                Output::Axis(axis, range) => {
+                    let (min, max) = match range {
+                        AxisRange::Positive => (0.0, 1.0),
+                        AxisRange::Negative => (0.0, -1.0),
+                        AxisRange::Full if axis.is_trigger() => (0.0, 1.0),
+                        AxisRange::Full => (-1.0, 1.0),
+                    };
+
+                    let raw = &mut self.raw_axes[axis as usize];
+
+                    // half axes share an output, a half at rest mustn't undo the other half
+                    if range != AxisRange::Full && amount == 0.0 && *raw * max < 0.0 {
+                        continue;
+                    }
+
+                    *raw = min + amount * (max - min);
+
+                    let value = apply_dead_zone(*raw, dead_zones[axis as usize]);
+                    if self.axes[axis as usize] != value {
+                        self.axes[axis as usize] = value;
+                        events.push(EventType::GamepadAxisMotion { id: self.id, axis, value });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Zeroes values within the dead zone, scaling the rest so they still start from zero
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() <= dead_zone {
+        return 0.0;
+    }
+
+    return value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone);
+}
+
+/// How far a normalized axis value is through a range, from 0 to 1
+fn range_amount(value: f32, range: AxisRange, invert: bool) -> f32 {
+    let (mut min, mut max) = match range {
+        AxisRange::Full => (-1.0, 1.0),
+        AxisRange::Positive => (0.0, 1.0),
+        AxisRange::Negative => (0.0, -1.0),
+    };
+
+    if invert {
+        std::mem::swap(&mut min, &mut max);
+    }
+
+    return ((value - min) / (max - min)).clamp(0.0, 1.0);
+}
+
+/// The default dead zones, sticks rarely rest exactly in the middle
+const DEFAULT_STICK_DEAD_ZONE: f32 = 0.15;
+const DEFAULT_TRIGGER_DEAD_ZONE: f32 = 0.05;
+
+/// Finds gamepads, maps them to standard buttons and axes and delivers their events.
+/// Gamepads in SDL's game controller database are mapped by it, the database is read
+/// from SDL_GAMECONTROLLERCONFIG and added to with add_mappings. Unlisted gamepads using
+/// the kernel's standard gamepad layout are mapped by that, other joysticks are ignored.
+/// Only Linux evdev devices are supported so far
+pub struct GamepadManager {
+    mappings: Vec<Mapping>,
+    gamepads: Vec<Gamepad>,
+    dead_zones: [f32; 6],
+    next_id: u32,
+    // events from before the first update, like the gamepads connected at startup
+    pending: Vec<EventType>,
+    #[cfg(target_os = "linux")]
+    monitor: Option<evdev::Monitor>,
+}
+
+impl Default for GamepadManager {
+    fn default() -> Self {
+        GamepadManager::new()
+    }
+}
+
+impl GamepadManager {
+    /// Creates the manager, opening the gamepads already connected
+    pub fn new() -> GamepadManager {
+        let mut manager = GamepadManager::disabled();
+
+        #[cfg(target_os = "linux")]
+        {
+            manager.monitor = evdev::Monitor::new();
+        }
+
+        if let Ok(mappings) = std::env::var("SDL_GAMECONTROLLERCONFIG") {
+            manager.add_mappings(&mappings);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let mut events = Vec::new();
+
+            for path in evdev::scan() {
+                manager.open(&path, &mut events);
+            }
+
+            manager.pending = events;
+        }
+
+        return manager;
+    }
+
+    /// Creates a manager that never looks for gamepads, for headless apps, which shouldn't
+    /// pick up the devices of the machine they run on
+    pub(crate) fn disabled() -> GamepadManager {
+        return GamepadManager {
+            mappings: Vec::new(),
+            gamepads: Vec::new(),
+            dead_zones: [
+                DEFAULT_STICK_DEAD_ZONE,
+                DEFAULT_STICK_DEAD_ZONE,
+                DEFAULT_STICK_DEAD_ZONE,
+                DEFAULT_STICK_DEAD_ZONE,
+                DEFAULT_TRIGGER_DEAD_ZONE,
+                DEFAULT_TRIGGER_DEAD_ZONE,
+            ],
+            next_id: 0,
+            pending: Vec::new(),
+            #[cfg(target_os = "linux")]
+            monitor: None,
+        };
+    }
+
+    /// Adds mappings in SDL's gamecontrollerdb.txt format, one per line, returning how many
+    /// were added. They replace earlier mappings for the same GUID, and are applied to
+    /// gamepads already connected
+    pub fn add_mappings(&mut self, mappings: &str) -> usize {
+        let mut added = 0;
+
+        for line in mappings.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(mapping) = Mapping::parse(line) {
+                self.mappings.retain(|existing| existing.guid != mapping.guid);
+                self.mappings.push(mapping);
+                added += 1;
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        for index in 0..self.gamepads.len() {
+            let gamepad = &self.gamepads[index];
+
+            if let Some((name, binds)) = self.resolve(&gamepad.device) {
+                let gamepad = &mut self.gamepads[index];
+                gamepad.name = name;
+                gamepad.binds = binds;
+            }
+        }
+
+        return added;
+    }
+
+    /// Reads mappings from a file in SDL's gamecontrollerdb.txt format, see add_mappings
+    pub fn load_mappings<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let mappings = fs::read_to_string(path).map_err(Error::GamepadMappingsIo)?;
+        return Ok(self.add_mappings(&mappings));
+    }
+
+    /// Sets the dead zone of an axis, from 0 to 1. Positions within it read as 0 and the rest
+    /// are scaled, so the axis still moves smoothly from 0
+    pub fn set_dead_zone(&mut self, axis: GamepadAxis, dead_zone: f32) {
+        self.dead_zones[axis as usize] = dead_zone.clamp(0.0, 0.99);
+    }
+
+    #[inline]
+    pub fn get_dead_zone(&self, axis: GamepadAxis) -> f32 {
+        return self.dead_zones[axis as usize];
+    }
+
+    /// Gets the connected gamepads
+    #[inline]
+    pub fn get_gamepads(&self) -> &[Gamepad] {
+        return &self.gamepads;
+    }
+
+    #[inline]
+    pub fn get_gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        return self.gamepads.iter().find(|gamepad| gamepad.id == id);
+    }
+
+    /// Reads the gamepads' events into the event que, along with connections and disconnections
+    pub(crate) fn update(&mut self, que: &mut EventDeque) {
+        que.extend(self.pending.drain(..).map(Event::new));
+
+        #[cfg(target_os = "linux")]
+        self.update_devices(que);
+    }
+
+    /// Gets the file descriptors to wait on for gamepad events and hotplugging
+    #[cfg(target_os = "linux")]
+    pub(crate) fn get_fds(&self) -> Vec<libc::c_int> {
+        let mut fds: Vec<libc::c_int> = self.gamepads.iter().map(|gamepad| gamepad.device.get_fd()).collect();
+        fds.extend(self.monitor.as_ref().map(evdev::Monitor::get_fd));
+
+        return fds;
+    }
+
+    /// Whether there are events waiting for the next update, without reading any
+    #[inline]
+    pub(crate) fn has_pending(&self) -> bool {
+        return !self.pending.is_empty();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn update_devices(&mut self, que: &mut EventDeque) {
+        let mut events = Vec::new();
+
+        let added_or_removed = match self.monitor.as_ref() {
+            Some(monitor) => monitor.read_changes(),
+            None => Vec::new(),
+        };
+
+        for (path, removed) in added_or_removed {
+            let open = self.gamepads.iter().position(|gamepad| gamepad.device.get_path() == path);
+
+            match (open, removed) {
+                (Some(index), true) => self.close(index, &mut events),
+                // permissions are often only set after the node is created, so this is retried
+                (None, false) => self.open(&path, &mut events),
+                _ => {}
+            }
+        }
+
+        que.extend(events.drain(..).map(Event::new));
+
+        let dead_zones = self.dead_zones;
+        let mut index = 0;
+
+        while index < self.gamepads.len() {
+            let gamepad = &mut self.gamepads[index];
+            let (changes, connected) = gamepad.device.read();
+
+            // stamped with when the device reported them
+            for (change, time) in changes {
+                gamepad.handle_input(change, &dead_zones, &mut events);
+                que.extend(events.drain(..).map(|e_type| Event::at(e_type, time)));
+            }
+
+            if connected {
+                index += 1;
+            } else {
+                self.close(index, &mut events);
+                que.extend(events.drain(..).map(Event::new));
+            }
+        }
+    }
+
+    /// Opens a device if it's a gamepad that can be mapped
+    #[cfg(target_os = "linux")]
+    fn open(&mut self, path: &Path, events: &mut Vec<EventType>) {
+        let device = match evdev::Device::open(path) {
+            Some(device) => device,
+            None => return,
+        };
+
+        let (name, binds) = match self.resolve(&device) {
+            Some(resolved) => resolved,
+            None => {
+                log_info!(
+                    "Joystick \"{}\" ({}) has no gamepad mapping, so it's ignored",
+                    device.get_name(),
+                    device.get_guid()
+                );
+                return;
+            }
+        };
+
+        let id = GamepadId(self.next_id);
+        self.next_id += 1;
+
+        let mut gamepad = Gamepad {
+            id,
+            name,
+            guid: device.get_guid().to_string(),
+            binds,
+            buttons: [false; 15],
+            raw_axes: [0.0; 6],
+            axes: [0.0; 6],
+            device,
+        };
+
+        events.push(EventType::GamepadConnected { id });
+
+        // buttons already held and axes already moved, like triggers that rest at -1
+        for change in gamepad.device.current_state() {
+            gamepad.handle_input(change, &self.dead_zones, events);
+        }
+
+        self.gamepads.push(gamepad);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn close(&mut self, index: usize, events: &mut Vec<EventType>) {
+        let gamepad = self.gamepads.remove(index);
+        events.push(EventType::GamepadDisconnected { id: gamepad.id });
+    }
+
+    /// Finds the mapping for a device, falling back to the kernel's gamepad layout
+    #[cfg(target_os = "linux")]
+    fn resolve(&self, device: &evdev::Device) -> Option<(String, Vec<(Source, Output)>)> {
+        let guid = device.get_guid();
+        let versionless = zero_guid(guid, GUID_VERSION);
+
+        let mapping = self
+            .mappings
+            .iter()
+            .rev()
+            .find(|mapping| mapping.guid == guid)
+            .or_else(|| self.mappings.iter().rev().find(|mapping| mapping.guid == versionless));
+
+        return match mapping {
+            Some(mapping) => {
+                let binds = mapping
+                    .binds
+                    .iter()
+                    .filter_map(|(input, output)| Some((device.resolve(*input)?, *output)))
+                    .collect();
+
+                Some((mapping.name.clone(), binds))
+            }
+            None => {
+                let binds = device.default_binds()?;
+                Some((device.get_name().to_string(), binds))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Xbox 360 entry of SDL's gamecontrollerdb.txt
+    const X360_MAPPING: &str = "030000005e0400008e02000014010000,Xbox 360 Controller,a:b0,b:b1,back:b6,\
+        dpdown:h0.4,dpleft:h0.8,dpright:h0.2,dpup:h0.1,guide:b8,leftshoulder:b4,leftstick:b9,\
+        lefttrigger:a2,leftx:a0,lefty:a1,rightshoulder:b5,rightstick:b10,righttrigger:a5,rightx:a3,\
+        righty:a4,start:b7,x:b2,y:b3,platform:Linux,";
+
+    // the codes the xpad driver reports an Xbox 360 pad with, in the order SDL numbers them
+    #[cfg(target_os = "linux")]
+    const XPAD_BUTTONS: [u16; 11] = [0x130, 0x131, 0x133, 0x134, 0x136, 0x137, 0x13a, 0x13b, 0x13c, 0x13d, 0x13e];
+    const ABS_X: u16 = 0x00;
+    const ABS_Z: u16 = 0x02;
+    const ABS_HAT0X: u16 = 0x10;
+    const ABS_HAT0Y: u16 = 0x11;
+    const BTN_SOUTH: u16 = 0x130;
+
+    fn binds_of(mapping: &Mapping, input: MappingInput) -> Vec<Output> {
+        return mapping
+            .binds
+            .iter()
+            .filter(|(bound, _)| *bound == input)
+            .map(|(_, output)| *output)
+            .collect();
+    }
+
+    fn on_platform(line: &str) -> String {
+        return line.replace("platform:Linux", &format!("platform:{}", MAPPING_PLATFORM));
+    }
+
+    fn gamepad(binds: Vec<(Source, Output)>) -> Gamepad {
+        return Gamepad {
+            id: GamepadId(3),
+            name: String::from("Test"),
+            guid: "0".repeat(32),
+            binds,
+            buttons: [false; 15],
+            raw_axes: [0.0; 6],
+            axes: [0.0; 6],
+            #[cfg(target_os = "linux")]
+            device: evdev::Device::fake(Vec::new(), Vec::new(), Vec::new()),
+        };
+    }
+
+    /// Feeds changes through a gamepad with no dead zones, collecting the events
+    fn feed(gamepad: &mut Gamepad, changes: &[InputChange]) -> Vec<EventType> {
+        let mut events = Vec::new();
+
+        for change in changes {
+            gamepad.handle_input(*change, &[0.0; 6], &mut events);
+        }
+
+        return events;
+    }
+
+    fn axis(code: u16, value: f32) -> InputChange {
+        return InputChange::Axis { code, value };
+    }
+
+    fn button(code: u16, down: bool) -> InputChange {
+        return InputChange::Button { code, down };
+    }
+
+    fn button_event(button: GamepadButton, down: bool) -> EventType {
+        let id = GamepadId(3);
+
+        return if down {
+            EventType::GamepadButtonDown { id, button }
+        } else {
+            EventType::GamepadButtonUp { id, button }
+        };
+    }
+
+    fn axis_event(axis: GamepadAxis, value: f32) -> EventType {
+        return EventType::GamepadAxisMotion { id: GamepadId(3), axis, value };
+    }
+
+    #[test]
+    fn parses_a_database_entry() {
+        let mapping = Mapping::parse(&on_platform(X360_MAPPING)).unwrap();
+
+        assert_eq!(mapping.guid, "030000005e0400008e02000014010000");
+        assert_eq!(mapping.name, "Xbox 360 Controller");
+        assert_eq!(mapping.binds.len(), 21);
+
+        assert!(binds_of(&mapping, MappingInput::Button(0)) == [Output::Button(GamepadButton::A)]);
+        assert!(binds_of(&mapping, MappingInput::Hat { index: 0, mask: 4 }) == [Output::Button(GamepadButton::DPadDown)]);
+        assert!(
+            binds_of(&mapping, MappingInput::Axis { index: 2, range: AxisRange::Full, invert: false })
+                == [Output::Axis(GamepadAxis::LeftTrigger, AxisRange::Full)]
+        );
+    }
+
+    #[test]
+    fn ignores_the_name_checksum() {
+        let newer = on_platform(X360_MAPPING).replacen("03000000", "0300938D", 1);
+        assert_eq!(Mapping::parse(&newer).unwrap().guid, "030000005e0400008e02000014010000");
+    }
+
+    #[test]
+    fn parses_half_and_inverted_axes() {
+        let line = "03000000000000000000000000000000,Halves,+leftx:+a0,-leftx:-a0,+lefty:b12,-lefty:b11,\
+            righttrigger:a5~,paddle1:b20,crc:1234,";
+        let mapping = Mapping::parse(line).unwrap();
+
+        assert!(
+            binds_of(&mapping, MappingInput::Axis { index: 0, range: AxisRange::Positive, invert: false })
+                == [Output::Axis(GamepadAxis::LeftX, AxisRange::Positive)]
+        );
+        assert!(
+            binds_of(&mapping, MappingInput::Axis { index: 0, range: AxisRange::Negative, invert: false })
+                == [Output::Axis(GamepadAxis::LeftX, AxisRange::Negative)]
+        );
+        assert!(binds_of(&mapping, MappingInput::Button(11)) == [Output::Axis(GamepadAxis::LeftY, AxisRange::Negative)]);
+        assert!(
+            binds_of(&mapping, MappingInput::Axis { index: 5, range: AxisRange::Full, invert: true })
+                == [Output::Axis(GamepadAxis::RightTrigger, AxisRange::Full)]
+        );
+
+        // paddles and the checksum aren't mapped
+        assert_eq!(mapping.binds.len(), 5);
+    }
+
+    #[test]
+    fn filters_by_platform() {
+        assert!(Mapping::parse(&on_platform(X360_MAPPING)).is_some());
+        assert!(Mapping::parse(&X360_MAPPING.replace("platform:Linux", "platform:Mac OS X")).is_none());
+
+        // entries without a platform apply everywhere
+        assert!(Mapping::parse(&X360_MAPPING.replace("platform:Linux,", "")).is_some());
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(Mapping::parse("xyz,Short GUID,a:b0,").is_none());
+        assert!(Mapping::parse("030000005e0400008e02000014010000,Bad input,a:z0,").is_none());
+        assert!(Mapping::parse("030000005e0400008e02000014010000,Bad hat,dpup:h0,").is_none());
+        assert!(Mapping::parse("030000005e0400008e02000014010000,No colon,a,").is_none());
+    }
+
+    #[test]
+    fn newer_entries_replace_older_ones() {
+        let mut manager = GamepadManager::disabled();
+
+        let entry = on_platform(X360_MAPPING);
+        let renamed = entry.replace("Xbox 360 Controller", "Renamed");
+        let entries = format!("# comment\n\n{}\n{}\n", entry, renamed);
+
+        assert_eq!(manager.add_mappings(&entries), 2);
+        assert_eq!(manager.mappings.len(), 1);
+        assert_eq!(manager.mappings[0].name, "Renamed");
+    }
+
+    #[test]
+    fn buttons_and_hats_press_and_release() {
+        let mut pad = gamepad(vec![
+            (Source::Button(BTN_SOUTH), Output::Button(GamepadButton::A)),
+            (Source::Hat { code: ABS_HAT0Y, positive: false }, Output::Button(GamepadButton::DPadUp)),
+            (Source::Hat { code: ABS_HAT0Y, positive: true }, Output::Button(GamepadButton::DPadDown)),
+            (Source::Hat { code: ABS_HAT0X, positive: true }, Output::Button(GamepadButton::DPadRight)),
+        ]);
+
+        let events = feed(
+            &mut pad,
+            &[
+                button(BTN_SOUTH, true),
+                // a repeat of a held button changes nothing
+                button(BTN_SOUTH, true),
+                axis(ABS_HAT0Y, -1.0),
+                // rolling from up straight to down
+                axis(ABS_HAT0Y, 1.0),
+                axis(ABS_HAT0X, 1.0),
+                axis(ABS_HAT0Y, 0.0),
+                button(BTN_SOUTH, false),
+            ],
+        );
+
+        let expected = [
+            button_event(GamepadButton::A, true),
+            button_event(GamepadButton::DPadUp, true),
+            button_event(GamepadButton::DPadUp, false),
+            button_event(GamepadButton::DPadDown, true),
+            button_event(GamepadButton::DPadRight, true),
+            button_event(GamepadButton::DPadDown, false),
+            button_event(GamepadButton::A, false),
+        ];
+
+        assert!(events == expected);
+        assert!(pad.is_down(GamepadButton::DPadRight));
+        assert!(!pad.is_down(GamepadButton::A));
+    }
+
+    #[test]
+    fn half_axes_share_an_output() {
+        let mut pad = gamepad(vec![
+            (Source::Button(10), Output::Axis(GamepadAxis::LeftY, AxisRange::Negative)),
+            (Source::Button(11), Output::Axis(GamepadAxis::LeftY, AxisRange::Positive)),
+        ]);
+
+        let events = feed(
+            &mut pad,
+            &[
+                button(11, true),
+                button(10, true),
+                // the positive half letting go doesn't undo the negative half held
+                button(11, false),
+                button(10, false),
+            ],
+        );
+
+        let expected = [
+            axis_event(GamepadAxis::LeftY, 1.0),
+            axis_event(GamepadAxis::LeftY, -1.0),
+            axis_event(GamepadAxis::LeftY, 0.0),
+        ];
+
+        assert!(events == expected);
+    }
+
+    #[test]
+    fn axis_halves_and_inversion() {
+        let mut pad = gamepad(vec![
+            (
+                Source::Axis { code: ABS_Z, range: AxisRange::Positive, invert: false },
+                Output::Axis(GamepadAxis::RightTrigger, AxisRange::Full),
+            ),
+            (
+                Source::Axis { code: ABS_Z, range: AxisRange::Negative, invert: false },
+                Output::Axis(GamepadAxis::LeftTrigger, AxisRange::Full),
+            ),
+            (
+                Source::Axis { code: ABS_X, range: AxisRange::Full, invert: true },
+                Output::Axis(GamepadAxis::LeftX, AxisRange::Full),
+            ),
+        ]);
+
+        let events = feed(&mut pad, &[axis(ABS_Z, 0.5), axis(ABS_Z, -1.0), axis(ABS_X, 0.25)]);
+
+        let expected = [
+            axis_event(GamepadAxis::RightTrigger, 0.5),
+            axis_event(GamepadAxis::RightTrigger, 0.0),
+            axis_event(GamepadAxis::LeftTrigger, 1.0),
+            axis_event(GamepadAxis::LeftX, -0.25),
+        ];
+
+        assert!(events == expected);
+    }
+
+    #[test]
+    fn dead_zones_hold_sticks_at_rest() {
+        let mut pad = gamepad(vec![(
+            Source::Axis { code: ABS_X, range: AxisRange::Full, invert: false },
+            Output::Axis(GamepadAxis::LeftX, AxisRange::Full),
+        )]);
+        let dead_zones = [0.25; 6];
+        let mut events = Vec::new();
+
+        for value in [0.1, -0.25, 0.625, 0.2] {
+            pad.handle_input(axis(ABS_X, value), &dead_zones, &mut events);
+        }
+
+        assert!(events == [axis_event(GamepadAxis::LeftX, 0.5), axis_event(GamepadAxis::LeftX, 0.0)]);
+        assert_eq!(pad.get_axis(GamepadAxis::LeftX), 0.0);
+    }
+
+    #[test]
+    fn dead_zone_scaling() {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-0.2, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(0.625, 0.25), 0.5);
+        assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+        assert_eq!(apply_dead_zone(0.3, 0.0), 0.3);
+    }
+
+    #[test]
+    fn range_amounts() {
+        assert_eq!(range_amount(-1.0, AxisRange::Full, false), 0.0);
+        assert_eq!(range_amount(0.0, AxisRange::Full, false), 0.5);
+        assert_eq!(range_amount(-1.0, AxisRange::Full, true), 1.0);
+        assert_eq!(range_amount(0.5, AxisRange::Positive, false), 0.5);
+        assert_eq!(range_amount(-0.5, AxisRange::Positive, false), 0.0);
+        assert_eq!(range_amount(-0.5, AxisRange::Negative, false), 0.5);
+        assert_eq!(range_amount(0.5, AxisRange::Negative, false), 0.0);
+        assert_eq!(range_amount(0.25, AxisRange::Positive, true), 0.75);
+    }
+
+    /// A recorded xpad stream run through the database entry, as a connected pad would be
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn maps_a_recorded_xpad_stream() {
+        let device = evdev::Device::fake(XPAD_BUTTONS.to_vec(), vec![0, 1, 2, 3, 4, 5], vec![ABS_HAT0X]);
+        let mapping = Mapping::parse(X360_MAPPING).unwrap();
+
+        let mut pad = gamepad(
+            mapping
+                .binds
+                .iter()
+                .filter_map(|(input, output)| Some((device.resolve(*input)?, *output)))
+                .collect(),
+        );
+        assert_eq!(pad.binds.len(), 21);
+
+        let dead_zones = [0.15, 0.15, 0.15, 0.15, 0.05, 0.05];
+        let mut events = Vec::new();
+
+        // the left trigger rests at -1, is pulled and let go, while the stick drifts and
+        // Y and the d-pad's left are pressed
+        let stream = [
+            axis(ABS_Z, -1.0),
+            axis(ABS_X, 0.08),
+            axis(ABS_Z, 0.0),
+            axis(ABS_Z, 1.0),
+            button(0x134, true),
+            axis(ABS_HAT0X, -1.0),
+            axis(ABS_Z, -1.0),
+            axis(ABS_X, -0.1),
+            button(0x134, false),
+            axis(ABS_HAT0X, 0.0),
+        ];
+
+        for change in stream {
+            pad.handle_input(change, &dead_zones, &mut events);
+        }
+
+        let trigger_half = (0.5 - 0.05) / 0.95;
+        let expected = [
+            axis_event(GamepadAxis::LeftTrigger, trigger_half),
+            axis_event(GamepadAxis::LeftTrigger, 1.0),
+            button_event(GamepadButton::Y, true),
+            button_event(GamepadButton::DPadLeft, true),
+            axis_event(GamepadAxis::LeftTrigger, 0.0),
+            button_event(GamepadButton::Y, false),
+            button_event(GamepadButton::DPadLeft, false),
+        ];
+
+        assert!(events == expected);
+    }
+}
@@ -0,0 +1,677 @@
+//! Gamepads through the kernel's evdev interface, the /dev/input/event* devices
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use super::{AxisRange, GamepadAxis, GamepadButton, InputChange, MappingInput, Output, Source};
+
+/// Where the device nodes are, watched for hotplugging
+const INPUT_DIR: &str = "/dev/input";
+
+// event types and codes, see linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const EV_CNT: usize = 0x20;
+const SYN_REPORT: u16 = 0;
+const SYN_DROPPED: u16 = 3;
+
+const KEY_CNT: usize = 0x300;
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_GAMEPAD: u16 = 0x130;
+const BTN_DIGI: u16 = 0x140;
+
+const BTN_SOUTH: u16 = 0x130;
+const BTN_EAST: u16 = 0x131;
+// BTN_NORTH and BTN_WEST are the same codes as BTN_X and BTN_Y, which most drivers
+// report for the buttons in the X and Y positions of an Xbox controller
+const BTN_X: u16 = 0x133;
+const BTN_Y: u16 = 0x134;
+const BTN_TL: u16 = 0x136;
+const BTN_TR: u16 = 0x137;
+const BTN_TL2: u16 = 0x138;
+const BTN_TR2: u16 = 0x139;
+const BTN_SELECT: u16 = 0x13a;
+const BTN_START: u16 = 0x13b;
+const BTN_MODE: u16 = 0x13c;
+const BTN_THUMBL: u16 = 0x13d;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+
+const ABS_CNT: usize = 0x40;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+const ABS_HAT3Y: u16 = 0x17;
+
+/// An _IOC read request for the evdev ioctls, see linux/input.h
+const fn ioc_read(nr: u32, size: usize) -> libc::c_ulong {
+    return (2 << 30 | (size as u32) << 16 | (b'E' as u32) << 8 | nr) as libc::c_ulong;
+}
+
+const EVIOCGID: libc::c_ulong = ioc_read(0x02, std::mem::size_of::<libc::input_id>());
+const EVIOCGNAME_256: libc::c_ulong = ioc_read(0x06, 256);
+const EVIOCGKEY: libc::c_ulong = ioc_read(0x18, KEY_CNT / 8);
+// _IOW rather than _IOR
+const EVIOCSCLOCKID: libc::c_ulong = (1 << 30 | 4 << 16 | (b'E' as u32) << 8 | 0xa0) as libc::c_ulong;
+
+#[inline]
+const fn eviocgbit(ev: u16, len: usize) -> libc::c_ulong {
+    return ioc_read(0x20 + ev as u32, len);
+}
+
+#[inline]
+const fn eviocgabs(abs: u16) -> libc::c_ulong {
+    return ioc_read(0x40 + abs as u32, std::mem::size_of::<libc::input_absinfo>());
+}
+
+#[inline]
+fn test_bit(bits: &[u8], bit: u16) -> bool {
+    return bits[bit as usize / 8] & (1 << (bit % 8)) != 0;
+}
+
+/// Gets the device nodes there are now
+pub(super) fn scan() -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(INPUT_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_event_node(entry.file_name().as_bytes()))
+        .map(|entry| entry.path())
+        .collect();
+
+    // so gamepads connected at startup get ids in the order the kernel numbered them
+    paths.sort_by_key(|path| event_node_number(path));
+
+    return paths;
+}
+
+#[inline]
+fn is_event_node(name: &[u8]) -> bool {
+    return name.starts_with(b"event");
+}
+
+fn event_node_number(path: &Path) -> u32 {
+    return path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("event"))
+        .and_then(|number| number.parse().ok())
+        .unwrap_or(u32::MAX);
+}
+
+/// Watches the device nodes being added and removed with inotify
+pub(super) struct Monitor {
+    fd: libc::c_int,
+}
+
+impl Monitor {
+    pub(super) fn new() -> Option<Monitor> {
+        unsafe {
+            let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+            if fd < 0 {
+                log_warn!("Gamepad hotplugging isn't available, inotify couldn't be set up");
+                return None;
+            }
+
+            let dir = CString::new(INPUT_DIR).unwrap();
+
+            // udev sets a node's permissions after creating it, which only IN_ATTRIB reports
+            let mask = libc::IN_CREATE | libc::IN_ATTRIB | libc::IN_DELETE;
+
+            if libc::inotify_add_watch(fd, dir.as_ptr(), mask) < 0 {
+                log_info!("{} can't be watched, gamepads won't be hotplugged", INPUT_DIR);
+                libc::close(fd);
+                return None;
+            }
+
+            return Some(Monitor { fd });
+        }
+    }
+
+    #[inline]
+    pub(super) fn get_fd(&self) -> libc::c_int {
+        return self.fd;
+    }
+
+    /// Gets the device nodes that have changed since the last read, and whether they were removed
+    pub(super) fn read_changes(&self) -> Vec<(PathBuf, bool)> {
+        let mut changes = Vec::new();
+
+        // aligned for the inotify_event structs read into it
+        let mut buffer = [0u64; 512];
+        let header = std::mem::size_of::<libc::inotify_event>();
+
+        loop {
+            let len = unsafe { libc::read(self.fd, buffer.as_mut_ptr() as _, std::mem::size_of_val(&buffer)) };
+            if len <= 0 {
+                break;
+            }
+
+            let bytes = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, len as usize) };
+            let mut offset = 0;
+
+            while offset + header <= bytes.len() {
+                let event = unsafe { &*(bytes.as_ptr().add(offset) as *const libc::inotify_event) };
+                let name = &bytes[offset + header..offset + header + event.len as usize];
+                offset += header + event.len as usize;
+
+                // the name is padded with nuls
+                let name = &name[..name.iter().position(|byte| *byte == 0).unwrap_or(name.len())];
+
+                if is_event_node(name) {
+                    let path = Path::new(INPUT_DIR).join(std::ffi::OsStr::from_bytes(name));
+                    changes.push((path, event.mask & libc::IN_DELETE != 0));
+                }
+            }
+        }
+
+        return changes;
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// An open joystick device
+pub(super) struct Device {
+    fd: libc::c_int,
+    path: PathBuf,
+    name: String,
+    guid: String,
+    keys: [u8; KEY_CNT / 8],
+    abs: [u8; ABS_CNT / 8],
+    // the range of each axis the device has
+    abs_range: [(i32, i32); ABS_CNT],
+    // the codes of the buttons, axes and hats in the order SDL numbers them, which is how
+    // mappings refer to them
+    buttons: Vec<u16>,
+    axes: Vec<u16>,
+    hats: Vec<u16>,
+    // whether event times are on the monotonic clock, as Instant is
+    monotonic: bool,
+    // events were dropped, the rest up to the next report are skipped and the state re-read
+    dropped: bool,
+}
+
+impl Device {
+    /// Opens a device node, if it's a joystick that can be read
+    pub(super) fn open(path: &Path) -> Option<Device> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+        unsafe {
+            let fd = libc::open(c_path.as_ptr(), libc::O_RDONLY | libc::O_NONBLOCK | libc::O_CLOEXEC);
+            if fd < 0 {
+                // most input devices are only readable by root, those aren't joysticks
+                return None;
+            }
+
+            let mut device = Device {
+                fd,
+                path: path.to_path_buf(),
+                name: String::new(),
+                guid: String::new(),
+                keys: [0; KEY_CNT / 8],
+                abs: [0; ABS_CNT / 8],
+                abs_range: [(0, 0); ABS_CNT],
+                buttons: Vec::new(),
+                axes: Vec::new(),
+                hats: Vec::new(),
+                monotonic: false,
+                dropped: false,
+            };
+
+            // dropping the device closes fd from here on
+            if !device.query() {
+                return None;
+            }
+
+            return Some(device);
+        }
+    }
+
+    /// Reads what the device has, returning whether it's a joystick
+    unsafe fn query(&mut self) -> bool {
+        let mut ev = [0u8; EV_CNT / 8];
+
+        if libc::ioctl(self.fd, eviocgbit(0, ev.len()) as _, ev.as_mut_ptr()) < 0 || !test_bit(&ev, EV_KEY) {
+            return false;
+        }
+
+        libc::ioctl(self.fd, eviocgbit(EV_KEY, self.keys.len()) as _, self.keys.as_mut_ptr());
+
+        if test_bit(&ev, EV_ABS) {
+            libc::ioctl(self.fd, eviocgbit(EV_ABS, self.abs.len()) as _, self.abs.as_mut_ptr());
+        }
+
+        // the joystick and gamepad buttons, the ones after are tablets and touchpads
+        if !(BTN_JOYSTICK..BTN_DIGI).any(|code| test_bit(&self.keys, code)) {
+            return false;
+        }
+
+        let mut name = [0u8; 256];
+        if libc::ioctl(self.fd, EVIOCGNAME_256 as _, name.as_mut_ptr()) >= 0 {
+            let len = name.iter().position(|byte| *byte == 0).unwrap_or(name.len());
+            self.name = String::from_utf8_lossy(&name[..len]).into_owned();
+        }
+
+        let mut id: libc::input_id = std::mem::zeroed();
+        libc::ioctl(self.fd, EVIOCGID as _, std::ptr::addr_of_mut!(id));
+        self.guid = sdl_guid(&id, &self.name);
+
+        // SDL numbers the joystick buttons first, then the keys before them
+        let codes = (BTN_JOYSTICK..KEY_CNT as u16).chain(0..BTN_JOYSTICK);
+        self.buttons = codes.filter(|code| test_bit(&self.keys, *code)).collect();
+
+        for code in 0..ABS_CNT as u16 - 1 {
+            if !test_bit(&self.abs, code) {
+                continue;
+            }
+
+            if (ABS_HAT0X..=ABS_HAT3Y).contains(&code) {
+                // a hat is its pair of axes, numbered by the first
+                let x = code & !1;
+                if !self.hats.contains(&x) {
+                    self.hats.push(x);
+                }
+            } else {
+                self.axes.push(code);
+            }
+
+            let mut info: libc::input_absinfo = std::mem::zeroed();
+            if libc::ioctl(self.fd, eviocgabs(code) as _, std::ptr::addr_of_mut!(info)) >= 0 {
+                self.abs_range[code as usize] = (info.minimum, info.maximum);
+            }
+        }
+
+        let clock: libc::c_int = libc::CLOCK_MONOTONIC;
+        self.monotonic = libc::ioctl(self.fd, EVIOCSCLOCKID as _, std::ptr::addr_of!(clock)) >= 0;
+
+        return true;
+    }
+
+    #[inline]
+    pub(super) fn get_fd(&self) -> libc::c_int {
+        return self.fd;
+    }
+
+    #[inline]
+    pub(super) fn get_path(&self) -> &Path {
+        return &self.path;
+    }
+
+    #[inline]
+    pub(super) fn get_name(&self) -> &str {
+        return &self.name;
+    }
+
+    #[inline]
+    pub(super) fn get_guid(&self) -> &str {
+        return &self.guid;
+    }
+
+    /// Finds the code of an input numbered the way SDL mappings number them
+    pub(super) fn resolve(&self, input: MappingInput) -> Option<Source> {
+        let source = match input {
+            MappingInput::Button(index) => Source::Button(*self.buttons.get(index as usize)?),
+            MappingInput::Axis { index, range, invert } => Source::Axis {
+                code: *self.axes.get(index as usize)?,
+                range,
+                invert,
+            },
+            MappingInput::Hat { index, mask } => {
+                let x = *self.hats.get(index as usize)?;
+
+                match mask {
+                    1 => Source::Hat { code: x + 1, positive: false },
+                    2 => Source::Hat { code: x, positive: true },
+                    4 => Source::Hat { code: x + 1, positive: true },
+                    8 => Source::Hat { code: x, positive: false },
+                    _ => return None,
+                }
+            }
+        };
+
+        return Some(source);
+    }
+
+    /// The mapping of a device using the kernel's gamepad layout, None if it doesn't
+    pub(super) fn default_binds(&self) -> Option<Vec<(Source, Output)>> {
+        if !test_bit(&self.keys, BTN_GAMEPAD) {
+            return None;
+        }
+
+        let buttons = [
+            (BTN_SOUTH, GamepadButton::A),
+            (BTN_EAST, GamepadButton::B),
+            (BTN_X, GamepadButton::X),
+            (BTN_Y, GamepadButton::Y),
+            (BTN_TL, GamepadButton::LeftShoulder),
+            (BTN_TR, GamepadButton::RightShoulder),
+            (BTN_SELECT, GamepadButton::Back),
+            (BTN_START, GamepadButton::Start),
+            (BTN_MODE, GamepadButton::Guide),
+            (BTN_THUMBL, GamepadButton::LeftStick),
+            (BTN_THUMBR, GamepadButton::RightStick),
+            (BTN_DPAD_UP, GamepadButton::DPadUp),
+            (BTN_DPAD_DOWN, GamepadButton::DPadDown),
+            (BTN_DPAD_LEFT, GamepadButton::DPadLeft),
+            (BTN_DPAD_RIGHT, GamepadButton::DPadRight),
+        ];
+
+        let axes = [
+            (ABS_X, GamepadAxis::LeftX),
+            (ABS_Y, GamepadAxis::LeftY),
+            (ABS_RX, GamepadAxis::RightX),
+            (ABS_RY, GamepadAxis::RightY),
+            (ABS_Z, GamepadAxis::LeftTrigger),
+            (ABS_RZ, GamepadAxis::RightTrigger),
+        ];
+
+        let hat = [
+            (ABS_HAT0Y, false, GamepadButton::DPadUp),
+            (ABS_HAT0Y, true, GamepadButton::DPadDown),
+            (ABS_HAT0X, false, GamepadButton::DPadLeft),
+            (ABS_HAT0X, true, GamepadButton::DPadRight),
+        ];
+
+        // digital triggers, on pads without analog ones
+        let digital_triggers = [(BTN_TL2, ABS_Z, GamepadAxis::LeftTrigger), (BTN_TR2, ABS_RZ, GamepadAxis::RightTrigger)];
+
+        let mut binds = Vec::new();
+
+        for (code, button) in buttons {
+            if test_bit(&self.keys, code) {
+                binds.push((Source::Button(code), Output::Button(button)));
+            }
+        }
+
+        for (code, axis) in axes {
+            if test_bit(&self.abs, code) {
+                let source = Source::Axis {
+                    code,
+                    range: AxisRange::Full,
+                    invert: false,
+                };
+
+                binds.push((source, Output::Axis(axis, AxisRange::Full)));
+            }
+        }
+
+        for (code, positive, button) in hat {
+            if test_bit(&self.abs, code) {
+                binds.push((Source::Hat { code, positive }, Output::Button(button)));
+            }
+        }
+
+        for (code, analog, axis) in digital_triggers {
+            if test_bit(&self.keys, code) && !test_bit(&self.abs, analog) {
+                binds.push((Source::Button(code), Output::Axis(axis, AxisRange::Full)));
+            }
+        }
+
+        return Some(binds);
+    }
+
+    /// Scales an axis value to -1 to 1 by the axis' range
+    fn normalize(&self, code: u16, value: i32) -> f32 {
+        let (min, max) = self.abs_range[code as usize];
+        if max <= min {
+            return 0.0;
+        }
+
+        return ((value - min) as f32 / (max - min) as f32 * 2.0 - 1.0).clamp(-1.0, 1.0);
+    }
+
+    /// Gets the state of every button and axis, as changes from everything at rest
+    pub(super) fn current_state(&self) -> Vec<InputChange> {
+        let mut keys = [0u8; KEY_CNT / 8];
+        let mut changes = Vec::new();
+
+        unsafe {
+            if libc::ioctl(self.fd, EVIOCGKEY as _, keys.as_mut_ptr()) >= 0 {
+                for code in self.buttons.iter() {
+                    changes.push(InputChange::Button {
+                        code: *code,
+                        down: test_bit(&keys, *code),
+                    });
+                }
+            }
+
+            let hat_axes = self.hats.iter().flat_map(|x| [*x, x + 1]);
+
+            for code in self.axes.iter().copied().chain(hat_axes) {
+                let mut info: libc::input_absinfo = std::mem::zeroed();
+
+                if libc::ioctl(self.fd, eviocgabs(code) as _, std::ptr::addr_of_mut!(info)) >= 0 {
+                    changes.push(InputChange::Axis {
+                        code,
+                        value: self.normalize(code, info.value),
+                    });
+                }
+            }
+        }
+
+        return changes;
+    }
+
+    /// Reads the changes the device has reported, with when they happened.
+    /// Returns false with them if the device has been unplugged
+    pub(super) fn read(&mut self) -> (Vec<(InputChange, Instant)>, bool) {
+        let mut changes = Vec::new();
+        let mut events: [libc::input_event; 64] = unsafe { std::mem::zeroed() };
+
+        // Instant has no way in from a timestamp, so times are placed relative to now
+        let now = Instant::now();
+        let monotonic_now = monotonic_time();
+
+        loop {
+            let len = unsafe { libc::read(self.fd, events.as_mut_ptr() as _, std::mem::size_of_val(&events)) };
+
+            if len < 0 {
+                let err = std::io::Error::last_os_error();
+                // ENODEV once it's unplugged
+                return (changes, err.raw_os_error() == Some(libc::EAGAIN) || err.raw_os_error() == Some(libc::EINTR));
+            }
+
+            if len == 0 {
+                return (changes, false);
+            }
+
+            let count = len as usize / std::mem::size_of::<libc::input_event>();
+
+            for event in events[..count].iter() {
+                let time = if self.monotonic {
+                    let at = Duration::new(event.time.tv_sec as u64, event.time.tv_usec as u32 * 1000);
+                    now.checked_sub(monotonic_now.saturating_sub(at)).unwrap_or(now)
+                } else {
+                    now
+                };
+
+                match (event.type_, event.code) {
+                    (EV_SYN, SYN_DROPPED) => self.dropped = true,
+                    (EV_SYN, SYN_REPORT) if self.dropped => {
+                        self.dropped = false;
+                        changes.extend(self.current_state().into_iter().map(|change| (change, now)));
+                    }
+                    _ if self.dropped => {}
+                    (EV_KEY, code) => changes.push((
+                        InputChange::Button {
+                            code,
+                            // 2 is a key repeat
+                            down: event.value != 0,
+                        },
+                        time,
+                    )),
+                    (EV_ABS, code) if (code as usize) < ABS_CNT => changes.push((
+                        InputChange::Axis {
+                            code,
+                            value: self.normalize(code, event.value),
+                        },
+                        time,
+                    )),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Device {
+    /// A device with the given buttons, axes and hats in SDL's order and no node behind it
+    pub(super) fn fake(buttons: Vec<u16>, axes: Vec<u16>, hats: Vec<u16>) -> Device {
+        let mut device = Device {
+            fd: -1,
+            path: PathBuf::new(),
+            name: String::from("Fake"),
+            guid: "0".repeat(32),
+            keys: [0; KEY_CNT / 8],
+            abs: [0; ABS_CNT / 8],
+            abs_range: [(-1, 1); ABS_CNT],
+            buttons,
+            axes,
+            hats,
+            monotonic: false,
+            dropped: false,
+        };
+
+        for code in device.buttons.clone() {
+            device.keys[code as usize / 8] |= 1 << (code % 8);
+        }
+
+        for code in device.axes.iter().copied().chain(device.hats.iter().flat_map(|x| [*x, x + 1])) {
+            device.abs[code as usize / 8] |= 1 << (code % 8);
+        }
+
+        return device;
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// The time on the monotonic clock, which Instant uses on Linux
+fn monotonic_time() -> Duration {
+    let mut time: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, std::ptr::addr_of_mut!(time));
+    }
+
+    return Duration::new(time.tv_sec as u64, time.tv_nsec as u32);
+}
+
+/// Builds the GUID SDL identifies a device by, as the hex of its 16 bytes
+fn sdl_guid(id: &libc::input_id, name: &str) -> String {
+    let mut bytes = [0u8; 16];
+    bytes[0..2].copy_from_slice(&id.bustype.to_le_bytes());
+
+    // bytes 2 and 3 are a checksum of the name in newer versions of SDL, which matching ignores
+    if id.vendor != 0 && id.product != 0 {
+        bytes[4..6].copy_from_slice(&id.vendor.to_le_bytes());
+        bytes[8..10].copy_from_slice(&id.product.to_le_bytes());
+        bytes[12..14].copy_from_slice(&id.version.to_le_bytes());
+    } else {
+        let name = name.as_bytes();
+        let len = name.len().min(12);
+        bytes[4..4 + len].copy_from_slice(&name[..len]);
+    }
+
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hat_pad() -> Device {
+        return Device::fake(vec![BTN_SOUTH], vec![ABS_X], vec![ABS_HAT0X, ABS_HAT0X + 2]);
+    }
+
+    #[test]
+    fn hat_masks_pick_the_axis_and_direction() {
+        let device = hat_pad();
+        let hat = |index, mask| device.resolve(MappingInput::Hat { index, mask });
+
+        assert!(hat(0, 1) == Some(Source::Hat { code: ABS_HAT0Y, positive: false }));
+        assert!(hat(0, 2) == Some(Source::Hat { code: ABS_HAT0X, positive: true }));
+        assert!(hat(0, 4) == Some(Source::Hat { code: ABS_HAT0Y, positive: true }));
+        assert!(hat(0, 8) == Some(Source::Hat { code: ABS_HAT0X, positive: false }));
+        assert!(hat(1, 2) == Some(Source::Hat { code: ABS_HAT0X + 2, positive: true }));
+
+        // diagonals and hats the device doesn't have
+        assert!(hat(0, 3).is_none());
+        assert!(hat(2, 1).is_none());
+    }
+
+    #[test]
+    fn inputs_resolve_in_sdl_order() {
+        let device = hat_pad();
+
+        assert!(device.resolve(MappingInput::Button(0)) == Some(Source::Button(BTN_SOUTH)));
+        assert!(device.resolve(MappingInput::Button(1)).is_none());
+
+        let axis = MappingInput::Axis { index: 0, range: AxisRange::Negative, invert: true };
+        assert!(device.resolve(axis) == Some(Source::Axis { code: ABS_X, range: AxisRange::Negative, invert: true }));
+    }
+
+    #[test]
+    fn default_binds_map_the_hat_to_the_dpad() {
+        let binds = hat_pad().default_binds().unwrap();
+
+        assert!(binds.contains(&(Source::Button(BTN_SOUTH), Output::Button(GamepadButton::A))));
+        assert!(binds.contains(&(
+            Source::Hat { code: ABS_HAT0Y, positive: false },
+            Output::Button(GamepadButton::DPadUp)
+        )));
+        assert!(binds.contains(&(
+            Source::Hat { code: ABS_HAT0X, positive: true },
+            Output::Button(GamepadButton::DPadRight)
+        )));
+
+        // joysticks without the gamepad buttons aren't gamepads
+        assert!(Device::fake(vec![BTN_JOYSTICK], Vec::new(), Vec::new()).default_binds().is_none());
+    }
+
+    #[test]
+    fn axes_are_normalized_by_their_range() {
+        let mut device = hat_pad();
+        device.abs_range[ABS_X as usize] = (0, 255);
+
+        assert_eq!(device.normalize(ABS_X, 0), -1.0);
+        assert_eq!(device.normalize(ABS_X, 255), 1.0);
+        assert_eq!(device.normalize(ABS_X, 300), 1.0);
+        assert!(device.normalize(ABS_X, 128).abs() < 0.01);
+    }
+
+    #[test]
+    fn guids_use_the_ids_or_the_name() {
+        let id = libc::input_id { bustype: 3, vendor: 0x045e, product: 0x028e, version: 0x0114 };
+        assert_eq!(sdl_guid(&id, "Xbox 360 Controller"), "030000005e0400008e02000014010000");
+
+        let anonymous = libc::input_id { bustype: 5, vendor: 0, product: 0, version: 0 };
+        assert_eq!(sdl_guid(&anonymous, "Pad"), "05000000506164000000000000000000");
+    }
+}
@@ -1,41 +1,82 @@
-use crate::platform::Window;
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::platform::{Window, WindowId};
 
-use std::borrow::{Borrow, Cow};
-use std::path::Iter;
-use std::convert::AsRef;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::iter;
-use std::ops::Deref;
 
-use wgpu::util::DeviceExt;
-use wgpu::{Buffer, BufferUsages, Instance, TextureViewDimension, VertexState, VertexStepMode};
+use wgpu::Instance;
 
 
 pub struct Renderer {
     device_context: RenderDeviceContext,
+    surfaces: HashMap<WindowId, RenderSurface>,
     draw_context: RenderDrawContext
 }
 
 impl Renderer {
     #[inline ]
-    pub fn new(win: &Window) -> Option<Renderer> {
-        let device_context_op = futures::executor::block_on(RenderDeviceContext::new(win));
-        if device_context_op.is_none() {
-            return None;
+    pub fn new(win: &Window) -> Result<Renderer> {
+        if win.is_headless() {
+            log_error!("Can't create a renderer for a headless window");
+            return Err(Error::Headless);
         }
 
-        let device_context = device_context_op.unwrap();
+        let (device_context, surface) =
+            futures::executor::block_on(RenderDeviceContext::new(win))?;
+
+        let surface = RenderSurface::from_surface(&device_context, surface, win)?;
 
         let draw_context = RenderDrawContext::new(&device_context);
 
-        Some(Renderer { device_context, draw_context })
+        let mut surfaces = HashMap::new();
+        surfaces.insert(win.get_id(), surface);
+
+        Ok(Renderer { device_context, surfaces, draw_context })
     }
 
+    /// Creates a surface for another window, which is then rendered to along with the others
+    pub fn add_window(&mut self, win: &Window) -> Result<()> {
+        if win.is_headless() {
+            log_error!("Can't render to a headless window");
+            return Err(Error::Headless);
+        }
+
+        let surface = RenderSurface::new(&self.device_context, win)?;
+        self.surfaces.insert(win.get_id(), surface);
+
+        return Ok(());
+    }
+
+    /// Drops the surface of a window, this should be done before the window is destroyed
+    #[inline]
+    pub fn remove_window(&mut self, window: WindowId) {
+        self.surfaces.remove(&window);
+    }
 
     #[inline]
     pub fn render(&mut self) {
-        self.draw_context.render(&self.device_context);
+        for surface in self.surfaces.values() {
+            self.draw_context.render(&self.device_context, surface);
+        }
+    }
+
+    /// Reconfigures a window's surface to match its new size
+    #[inline]
+    pub fn resize(&mut self, window: WindowId, width: u16, height: u16) {
+        if let Some(surface) = self.surfaces.get_mut(&window) {
+            surface.resize(&self.device_context, width as u32, height as u32);
+        }
     }
 
+    /// Lets the renderer react to window events, such as a resize
+    #[inline]
+    pub fn handle_event(&mut self, event: &Event) {
+        if let (EventType::WinResize { width, height }, Some(window)) = (&event.e_type, event.window) {
+            self.resize(window, *width, *height);
+        }
+    }
 }
 
 struct RenderDrawContext {
@@ -77,24 +118,34 @@ impl RenderDrawContext {
         }
     }
 
-    fn render(&mut self, context: &RenderDeviceContext) {
+    fn render(&self, context: &RenderDeviceContext, surface: &RenderSurface) {
         let mut command_encoder = context.
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
-        let tex_res = context.surface.get_current_texture();
-
-        if tex_res.is_err() {
-            if tex_res.as_ref().err().is_some() {
-                log_error!("Failed to retrieve texture from surface: {} ", tex_res.as_ref().err().unwrap());
-            } else {
-                log_error!("Failed to retrieve texture from surface");
-            }
+        // Nothing can be presented to a minimised (zero sized) window
+        if !surface.is_configured() {
             return;
-        } else {
-            log_info!("Not error");
         }
 
-        let tex = tex_res.unwrap();
+        let tex_res = match surface.surface.get_current_texture() {
+            Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+                surface.configure(context);
+                surface.surface.get_current_texture()
+            }
+            res => res,
+        };
+
+        let tex = match tex_res {
+            Ok(tex) => tex,
+            Err(wgpu::SurfaceError::Timeout) => {
+                log_warn!("Timed out retrieving texture from surface, skipping frame");
+                return;
+            }
+            Err(err) => {
+                log_error!("Failed to retrieve texture from surface: {} ", err);
+                return;
+            }
+        };
         let view = tex.texture.create_view(&wgpu::TextureViewDescriptor {
             label: None,
             format: Some(wgpu::TextureFormat::Bgra8UnormSrgb),
@@ -130,18 +181,20 @@ impl RenderDrawContext {
         }
 
         context.queue.submit(iter::once(command_encoder.finish()));
+        tex.present();
     }
 }
 
 pub struct RenderDeviceContext {
-    pub surface: wgpu::Surface,
+    pub instance: wgpu::Instance,
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
 }
 
 impl RenderDeviceContext {
-    pub(crate) async fn new(win: &Window) -> Option<RenderDeviceContext> {
+    /// Creates the device, along with a surface for the window it has to be able to present to
+    pub(crate) async fn new(win: &Window) -> Result<(RenderDeviceContext, wgpu::Surface)> {
         let inst = Instance::new(wgpu::Backends::VULKAN);
 
         let surface = unsafe { inst.create_surface(win) };
@@ -154,12 +207,13 @@ impl RenderDeviceContext {
             })
             .await;
 
-        if adap_op.is_none() {
-            log_error!("Failed to create adapter for renderer");
-            return None;
-        }
-
-        let adapter = adap_op.unwrap();
+        let adapter = match adap_op {
+            Some(adapter) => adapter,
+            None => {
+                log_error!("Failed to create adapter for renderer");
+                return Err(Error::NoAdapter);
+            }
+        };
 
         let res = adapter
             .request_device(
@@ -172,27 +226,22 @@ impl RenderDeviceContext {
             )
             .await;
 
-        if res.is_err() {
-            log_error!("Failed to creat renderer Device and Queue");
-            return None;
-        }
-
-        let (device, queue) = res.unwrap();
-
-        surface.configure(&device, &wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            width: win.get_width() as u32,
-            height: win.get_height() as u32,
-            present_mode: wgpu::PresentMode::Fifo
-        });
+        let (device, queue) = match res {
+            Ok(device_queue) => device_queue,
+            Err(err) => {
+                log_error!("Failed to creat renderer Device and Queue");
+                return Err(Error::from(err));
+            }
+        };
 
-        return Some(RenderDeviceContext {
-            surface,
+        let context = RenderDeviceContext {
+            instance: inst,
             adapter,
             device,
             queue,
-        });
+        };
+
+        return Ok((context, surface));
     }
 
     #[inline]
@@ -205,3 +254,79 @@ impl RenderDeviceContext {
             });
     }
 }
+
+/// A window's surface and the configuration it's presented with
+pub struct RenderSurface {
+    pub surface: wgpu::Surface,
+    pub config: wgpu::SurfaceConfiguration,
+}
+
+impl RenderSurface {
+    pub(crate) fn new(context: &RenderDeviceContext, win: &Window) -> Result<RenderSurface> {
+        let surface = unsafe { context.instance.create_surface(win) };
+
+        return RenderSurface::from_surface(context, surface, win);
+    }
+
+    /// Checks the surface supports the format and present mode we use, then configures it
+    pub(crate) fn from_surface(
+        context: &RenderDeviceContext,
+        surface: wgpu::Surface,
+        win: &Window,
+    ) -> Result<RenderSurface> {
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: win.get_width() as u32,
+            height: win.get_height() as u32,
+            present_mode: wgpu::PresentMode::Fifo
+        };
+
+        if !surface.get_supported_formats(&context.adapter).contains(&config.format) {
+            log_error!("Surface doesn't support the {:?} format", config.format);
+            return Err(Error::SurfaceConfiguration(format!(
+                "the {:?} format isn't supported by the surface",
+                config.format
+            )));
+        }
+
+        if !surface.get_supported_modes(&context.adapter).contains(&config.present_mode) {
+            log_error!("Surface doesn't support the {:?} present mode", config.present_mode);
+            return Err(Error::SurfaceConfiguration(format!(
+                "the {:?} present mode isn't supported by the surface",
+                config.present_mode
+            )));
+        }
+
+        let render_surface = RenderSurface { surface, config };
+        render_surface.configure(context);
+
+        return Ok(render_surface);
+    }
+
+    /// Whether the surface has a size it can be configured with, a minimised
+    /// window reports a size of zero which wgpu will not accept
+    #[inline]
+    pub fn is_configured(&self) -> bool {
+        return self.config.width > 0 && self.config.height > 0;
+    }
+
+    /// (Re)configures the surface with the current configuration
+    #[inline]
+    pub fn configure(&self, context: &RenderDeviceContext) {
+        if self.is_configured() {
+            self.surface.configure(&context.device, &self.config);
+        }
+    }
+
+    /// Updates the surface size and reconfigures it, if the size has changed
+    pub fn resize(&mut self, context: &RenderDeviceContext, width: u32, height: u32) {
+        if self.config.width == width && self.config.height == height {
+            return;
+        }
+
+        self.config.width = width;
+        self.config.height = height;
+        self.configure(context);
+    }
+}
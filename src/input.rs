@@ -0,0 +1,284 @@
+use crate::event::{Event, EventType};
+use crate::keys::Key;
+use crate::platform::WindowId;
+
+const KEY_COUNT: usize = Key::ALL.len();
+
+/// The mouse buttons events are reported for
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+/// The keyboard and mouse as of the events polled so far, for games that would rather ask
+/// whether a key is held than track it from events. The "just" states and the mouse deltas
+/// cover the current frame, started with App::next_frame
+pub struct InputState {
+    keys_down: [bool; KEY_COUNT],
+    keys_pressed: [bool; KEY_COUNT],
+    keys_released: [bool; KEY_COUNT],
+    // by the key in the same place on a US keyboard, see ScanCode::get_key
+    physical_down: [bool; KEY_COUNT],
+    physical_pressed: [bool; KEY_COUNT],
+    physical_released: [bool; KEY_COUNT],
+    buttons_down: [bool; 3],
+    buttons_pressed: [bool; 3],
+    buttons_released: [bool; 3],
+    // None until the mouse has moved over a window
+    mouse_pos: Option<(i16, i16)>,
+    // the window mouse_pos is in, positions in different windows can't be compared
+    mouse_window: Option<WindowId>,
+    mouse_delta: (i32, i32),
+    raw_delta: (f32, f32),
+    wheel_delta: (f32, f32),
+    // the windows with focus, as their focus events reported it
+    focused: Vec<Option<WindowId>>,
+    // set when the last of them lost focus. Focus moving between windows arrives as a loss
+    // then a gain, so everything is only let go of if no gain follows in the events polled
+    losing_focus: bool,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState::new()
+    }
+}
+
+impl InputState {
+    #[inline]
+    pub fn new() -> InputState {
+        InputState {
+            keys_down: [false; KEY_COUNT],
+            keys_pressed: [false; KEY_COUNT],
+            keys_released: [false; KEY_COUNT],
+            physical_down: [false; KEY_COUNT],
+            physical_pressed: [false; KEY_COUNT],
+            physical_released: [false; KEY_COUNT],
+            buttons_down: [false; 3],
+            buttons_pressed: [false; 3],
+            buttons_released: [false; 3],
+            mouse_pos: None,
+            mouse_window: None,
+            mouse_delta: (0, 0),
+            raw_delta: (0.0, 0.0),
+            wheel_delta: (0.0, 0.0),
+            focused: Vec::new(),
+            losing_focus: false,
+        }
+    }
+
+    /// Whether a key is held down
+    #[inline]
+    pub fn is_down(&self, key: Key) -> bool {
+        return self.keys_down[key as usize];
+    }
+
+    /// Whether a key was pressed this frame, repeats from holding it don't count
+    #[inline]
+    pub fn just_pressed(&self, key: Key) -> bool {
+        return self.keys_pressed[key as usize];
+    }
+
+    /// Whether a key was released this frame
+    #[inline]
+    pub fn just_released(&self, key: Key) -> bool {
+        return self.keys_released[key as usize];
+    }
+
+    /// Whether the key where key is on a US keyboard is held down, whatever the layout
+    /// makes it. For controls that go by position, like WASD
+    #[inline]
+    pub fn is_physical_down(&self, key: Key) -> bool {
+        return self.physical_down[key as usize];
+    }
+
+    /// Whether the key where key is on a US keyboard was pressed this frame
+    #[inline]
+    pub fn physical_just_pressed(&self, key: Key) -> bool {
+        return self.physical_pressed[key as usize];
+    }
+
+    /// Whether the key where key is on a US keyboard was released this frame
+    #[inline]
+    pub fn physical_just_released(&self, key: Key) -> bool {
+        return self.physical_released[key as usize];
+    }
+
+    #[inline]
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        return self.buttons_down[button as usize];
+    }
+
+    /// Whether a mouse button was pressed this frame
+    #[inline]
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        return self.buttons_pressed[button as usize];
+    }
+
+    /// Whether a mouse button was released this frame
+    #[inline]
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        return self.buttons_released[button as usize];
+    }
+
+    /// Gets the last position the mouse moved to, in the coordinates MouseMove reports.
+    /// None until it has moved over one of the windows
+    #[inline]
+    pub fn get_mouse_pos(&self) -> Option<(i16, i16)> {
+        return self.mouse_pos;
+    }
+
+    /// Gets how far the mouse has moved this frame
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (i32, i32) {
+        return self.mouse_delta;
+    }
+
+    /// Gets how far the mouse itself has moved this frame, from MouseRawMotion. Unlike
+    /// get_mouse_delta it keeps counting when the cursor is held at the edge of the window
+    #[inline]
+    pub fn get_raw_mouse_delta(&self) -> (f32, f32) {
+        return self.raw_delta;
+    }
+
+    /// Gets how far the wheel has scrolled this frame, in the notches MouseWheel reports
+    #[inline]
+    pub fn get_wheel_delta(&self) -> (f32, f32) {
+        return self.wheel_delta;
+    }
+
+    /// Starts a new frame, clearing what happened during the last one
+    pub(crate) fn next_frame(&mut self) {
+        self.keys_pressed = [false; KEY_COUNT];
+        self.keys_released = [false; KEY_COUNT];
+        self.physical_pressed = [false; KEY_COUNT];
+        self.physical_released = [false; KEY_COUNT];
+        self.buttons_pressed = [false; 3];
+        self.buttons_released = [false; 3];
+        self.mouse_delta = (0, 0);
+        self.raw_delta = (0.0, 0.0);
+        self.wheel_delta = (0.0, 0.0);
+    }
+
+    /// Updates the state with an event as it's polled
+    pub(crate) fn handle_event(&mut self, event: &Event) {
+        match event.e_type {
+            EventType::KeyDown { key, scancode, is_repeat, .. } => {
+                if !is_repeat || !self.keys_down[key as usize] {
+                    self.keys_pressed[key as usize] = true;
+                }
+
+                self.keys_down[key as usize] = true;
+
+                let physical = scancode.get_key() as usize;
+
+                if !is_repeat || !self.physical_down[physical] {
+                    self.physical_pressed[physical] = true;
+                }
+
+                self.physical_down[physical] = true;
+            }
+            EventType::KeyUp { key, scancode, .. } => {
+                release_one(&mut self.keys_down, &mut self.keys_released, key as usize);
+
+                let physical = scancode.get_key() as usize;
+                release_one(&mut self.physical_down, &mut self.physical_released, physical);
+            }
+            EventType::MouseMove { x, y } => {
+                if self.mouse_window != event.window {
+                    self.mouse_window = event.window;
+                    self.mouse_pos = None;
+                }
+
+                if let Some((last_x, last_y)) = self.mouse_pos {
+                    self.mouse_delta.0 += x as i32 - last_x as i32;
+                    self.mouse_delta.1 += y as i32 - last_y as i32;
+                }
+
+                self.mouse_pos = Some((x, y));
+            }
+            EventType::MouseRawMotion { delta_x, delta_y } => {
+                self.raw_delta.0 += delta_x;
+                self.raw_delta.1 += delta_y;
+            }
+            EventType::MouseWheel { delta_x, delta_y } => {
+                self.wheel_delta.0 += delta_x;
+                self.wheel_delta.1 += delta_y;
+            }
+            EventType::MouseLeftBtnDown { .. } => self.press_button(MouseButton::Left),
+            EventType::MouseMidBtnDown { .. } => self.press_button(MouseButton::Middle),
+            EventType::MouseRightBtnDown { .. } => self.press_button(MouseButton::Right),
+            EventType::MouseLeftBtnUp { .. } => self.release_button(MouseButton::Left),
+            EventType::MouseMidBtnUp { .. } => self.release_button(MouseButton::Middle),
+            EventType::MouseRightBtnUp { .. } => self.release_button(MouseButton::Right),
+            EventType::WinFocusGained => {
+                if !self.focused.contains(&event.window) {
+                    self.focused.push(event.window);
+                }
+
+                self.losing_focus = false;
+            }
+            EventType::WinFocusLost => {
+                self.focused.retain(|window| *window != event.window);
+                self.losing_focus = self.focused.is_empty();
+            }
+            _ => {}
+        }
+    }
+
+    /// Called once the polled events run out. If the app lost focus, everything held is let
+    /// go of, as keys and buttons let go of while another app has focus are never reported
+    pub(crate) fn finish_events(&mut self) {
+        if self.losing_focus {
+            self.losing_focus = false;
+            self.release_all();
+        }
+    }
+
+    /// Updates the state with an event a handler consumed. Only releases and focus changes are
+    /// applied, so a handler taking one doesn't leave a key or button held down
+    pub(crate) fn handle_consumed_event(&mut self, event: &Event) {
+        match event.e_type {
+            EventType::KeyUp { .. }
+            | EventType::MouseLeftBtnUp { .. }
+            | EventType::MouseMidBtnUp { .. }
+            | EventType::MouseRightBtnUp { .. }
+            | EventType::WinFocusGained
+            | EventType::WinFocusLost => self.handle_event(event),
+            _ => {}
+        }
+    }
+
+    fn press_button(&mut self, button: MouseButton) {
+        self.buttons_down[button as usize] = true;
+        self.buttons_pressed[button as usize] = true;
+    }
+
+    fn release_button(&mut self, button: MouseButton) {
+        release_one(&mut self.buttons_down, &mut self.buttons_released, button as usize);
+    }
+
+    fn release_all(&mut self) {
+        release(&mut self.keys_down, &mut self.keys_released);
+        release(&mut self.physical_down, &mut self.physical_released);
+        release(&mut self.buttons_down, &mut self.buttons_released);
+    }
+}
+
+/// Lets go of one key or button, it's only marked as released if it was held, as its press
+/// may have been consumed before reaching the state
+#[inline]
+fn release_one(down: &mut [bool], released: &mut [bool], index: usize) {
+    released[index] |= down[index];
+    down[index] = false;
+}
+
+/// Lets go of everything held, marking it as released
+fn release(down: &mut [bool], released: &mut [bool]) {
+    for (down, released) in down.iter_mut().zip(released.iter_mut()) {
+        *released |= *down;
+        *down = false;
+    }
+}
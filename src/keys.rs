@@ -1,10 +1,75 @@
-#[derive(PartialEq)]
-pub enum Key {
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::error::{Error, Result};
+
+/// The modifier keys held, and lock keys active, when an event happened.
+/// On X11 this is the state just before the event, so pressing Shift
+/// reports a KeyDown without shift set
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub super_key: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+/// A key by where it is on the keyboard rather than what the layout makes it, so keys
+/// bound by position, like WASD, stay in place on an AZERTY keyboard. It's the evdev code
+/// on Linux and the set 1 scancode on Windows, with 0xE000 added for extended keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ScanCode(pub u32);
+
+impl ScanCode {
+    /// Gets the key in this position on a US QWERTY keyboard, whatever the layout in use
+    #[inline]
+    pub fn get_key(self) -> Key {
+        return Key::from(self);
+    }
+
+    /// Gets the position of a key on a US QWERTY keyboard, the reverse of get_key
+    pub fn from_key(key: Key) -> Option<ScanCode> {
+        if key == Key::None {
+            return None;
+        }
+
+        // evdev codes stay below 0x300, set 1 scancodes are a byte with the extended flag
+        return (0..0x300)
+            .chain(0xE000..0xE100)
+            .map(ScanCode)
+            .find(|scancode| scancode.get_key() == key);
+    }
+}
+
+/// Declares Key along with Key::ALL and the names Display writes, from one list so
+/// they can't fall out of order
+macro_rules! keys {
+    ($($(#[$meta:meta])* $key:ident,)*) => {
+        /// A key as the keyboard layout makes it. Keys are written by the names they're declared
+        /// with, e.g. "Escape" or "N1", which is what Display gives and FromStr takes
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        pub enum Key {
+            $($(#[$meta])* $key,)*
+        }
+
+        impl Key {
+            /// Every key, in the order they're declared so a key's index in it is `key as usize`
+            pub const ALL: [Key; [$(stringify!($key)),*].len()] = [$(Key::$key),*];
+        }
+
+        /// The names of the keys, in the same order as Key::ALL
+        const KEY_NAMES: [&str; Key::ALL.len()] = [$(stringify!($key)),*];
+    };
+}
+
+keys! {
     Backspace,
     Enter,
     Tab,
     Shift,
     Control,
+    Alt,
 
     Pause,
     Capital,
@@ -133,6 +198,87 @@ pub enum Key {
     Period,
     Slash,
     Grave,
+    LBracket,
+    RBracket,
+    Backslash,
+    Quote,
+    /// The extra key next to left shift on ISO keyboards
+    NonUsBackslash,
+    NumpadEnter,
+
+    Cancel,
+    Clear,
+    Kana,
+    Kanji,
+
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNext,
+    MediaPrev,
+    MediaStop,
+    MediaPlayPause,
+
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    LaunchMail,
+    LaunchMediaSelect,
+    LaunchApp1,
+    LaunchApp2,
 
     None,
 }
+
+impl Key {
+    /// Gets the name of the key as it's declared, e.g. "Escape" or "N1"
+    #[inline]
+    pub fn get_name(self) -> &'static str {
+        return KEY_NAMES[self as usize];
+    }
+
+    /// Gets the key with a name returned by get_name, ignoring case
+    pub fn from_name(name: &str) -> Option<Key> {
+        return KEY_NAMES
+            .iter()
+            .position(|key_name| key_name.eq_ignore_ascii_case(name))
+            .map(|index| Key::ALL[index]);
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+/// Takes the names Display writes, ignoring case
+impl FromStr for Key {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Key> {
+        return Key::from_name(s).ok_or_else(|| Error::UnknownKey(s.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_indexed_and_named_in_order() {
+        for (index, key) in Key::ALL.iter().enumerate() {
+            assert_eq!(*key as usize, index);
+            assert_eq!(Key::from_name(&key.to_string()), Some(*key));
+            assert_eq!(key.to_string().to_ascii_lowercase().parse::<Key>().ok(), Some(*key));
+        }
+
+        assert_eq!(Key::ALL.len(), KEY_NAMES.len());
+        assert_eq!(Key::N1.get_name(), "N1");
+        assert!(Key::from_name("NotAKey").is_none());
+    }
+}
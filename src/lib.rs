@@ -0,0 +1,20 @@
+#![allow(clippy::needless_return)]
+
+#[macro_use]
+extern crate rovella_logger;
+extern crate raw_window_handle;
+extern crate wgpu;
+
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+pub mod action;
+pub mod application;
+pub mod error;
+pub mod event;
+pub mod gamepad;
+pub mod graphics;
+pub mod input;
+pub mod keys;
+pub mod platform;
+pub mod replay;
@@ -1,54 +1,87 @@
-use event::*;
-use keys::Key;
-
-#[macro_use]
-extern crate rovella_logger;
-extern crate raw_window_handle;
-extern crate wgpu;
-
-#[cfg(target_os = "linux")]
-extern crate libc;
-
-pub mod application;
-pub mod event;
-pub mod graphics;
-pub mod keys;
-pub mod platform;
+use rovella::action::Binding;
+use rovella::application;
+use rovella::event::*;
+use rovella::graphics;
+use rovella::keys::Key;
+use rovella::platform::{Fullscreen, WindowBuilder};
 
 fn main() {
     let mut app: application::App =
         application::App::create("hello world", 15, 15, 1920, 1080).unwrap(); // Only if your lazy :)
 
-    let rend_op = graphics::Renderer::new(app.get_window_ref());
-
-    if rend_op.is_none() {
-        return;
-    }
+    let mut renderer = match graphics::Renderer::new(app.get_window_ref()) {
+        Ok(renderer) => renderer,
+        Err(err) => {
+            eprintln!("{}", err);
+            app.shutdown();
+            return;
+        }
+    };
 
-    let mut renderer = rend_op.unwrap();
+    let actions = app.get_actions_mut();
+    actions.bind("quit", Binding::Key(Key::Escape));
+    actions.bind("tools", Binding::Key(Key::F2));
 
     while app.is_running() {
-        renderer.render();
+        // nothing can be seen while minimized, so sleep until something happens instead
+        if !app.get_window_ref().is_visible() {
+            if let Some(event) = app.wait_events() {
+                handle_event(&mut app, &mut renderer, event);
+            }
 
-        let event_op = app.poll_events();
-        if event_op.is_none() {
             continue;
         }
 
-        let event = event_op.unwrap();
+        app.next_frame();
+
+        while let Some(event) = app.poll_events() {
+            handle_event(&mut app, &mut renderer, event);
+        }
 
-        match event.e_type {
-            EventType::WinClose => {
-                app.quit();
+        renderer.render();
+    }
+
+    app.shutdown();
+}
+
+fn handle_event(app: &mut application::App, renderer: &mut graphics::Renderer, event: Event) {
+    renderer.handle_event(&event);
+
+    match event.e_type {
+        EventType::WinClose => match event.window {
+            // closing a tool window only closes that window
+            Some(window) if window != app.get_main_window_id() => {
+                renderer.remove_window(window);
+                app.destroy_window(window);
             }
-            EventType::KeyDown => {
-                if event.get_key() == Key::Escape {
-                    app.quit();
+            _ => app.quit(),
+        },
+        _ if app.get_actions().is_pressed_by("quit", &event.e_type) => {
+            app.quit();
+        }
+        EventType::KeyDown { key: Key::Enter, modifiers, is_repeat: false, .. } if modifiers.alt => {
+            let window = app.get_window_mut();
+            if window.get_fullscreen().is_some() {
+                window.set_fullscreen(None);
+            } else {
+                window.set_fullscreen(Some(Fullscreen::Borderless));
+            }
+        }
+        _ if app.get_actions().is_pressed_by("tools", &event.e_type) => {
+            let tool_window = WindowBuilder::new()
+                .with_title("tools")
+                .with_size(400, 300);
+
+            match app.create_window(tool_window) {
+                Ok(window) => {
+                    if let Err(err) = renderer.add_window(app.get_window_by_id(window).unwrap()) {
+                        eprintln!("{}", err);
+                        app.destroy_window(window);
+                    }
                 }
+                Err(err) => eprintln!("{}", err),
             }
-            _ => {}
         }
+        _ => {}
     }
-
-    app.shutdown();
 }
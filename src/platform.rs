@@ -1,18 +1,26 @@
 #[cfg(target_os = "windows")]
 pub mod plat_libs {
-    pub use winapi::ctypes::c_int;
+    pub use winapi::ctypes::{c_int, c_long};
+    pub use winapi::shared::basetsd::LONG_PTR;
     pub use winapi::shared::minwindef::*;
+    pub use winapi::shared::ntdef::{HANDLE, LONG};
     pub use winapi::shared::windef::POINT;
     pub use winapi::shared::windef::*;
     pub use winapi::shared::windowsx::{GET_X_LPARAM, GET_Y_LPARAM};
+    pub use winapi::shared::winerror::ERROR_CLASS_ALREADY_EXISTS;
     pub use winapi::um::errhandlingapi::GetLastError;
+    pub use winapi::um::handleapi::CloseHandle;
+    pub use winapi::um::imm::{ImmGetContext, ImmReleaseContext, HIMC};
     pub use winapi::um::libloaderapi::*;
-    pub use winapi::um::synchapi::Sleep;
+    pub use winapi::um::synchapi::{CreateEventW, ResetEvent, SetEvent, Sleep};
+    pub use winapi::um::winbase::INFINITE;
     pub use winapi::um::winuser::*;
 
     pub use std::alloc::{alloc_zeroed, dealloc, Layout};
     pub use std::borrow::Borrow;
-    pub use std::ffi::CString;
+    pub use std::cell::{Cell, RefCell};
+    pub use std::ffi::OsStr;
+    pub use std::os::windows::ffi::OsStrExt;
     pub use std::ops::Deref;
     pub use std::ptr;
     pub use std::ptr::null_mut;
@@ -28,16 +36,18 @@ pub mod types {
 
 #[cfg(target_os = "linux")]
 pub mod plat_libs {
+    pub use std::cell::{Cell, RefCell};
+    pub use std::collections::HashMap;
+    pub use std::ffi::{CStr, CString};
     pub use std::ptr;
     pub use std::ptr::{null, null_mut};
+    pub use std::rc::Rc;
     pub use std::{thread, time};
     pub use x11::*;
     pub use xcb::ffi::xcb_connection_t;
     pub use xcb::ffi::xproto::*;
     pub use xcb::ffi::*;
     pub use xcb::ffi::{xcb_flush, xcb_generic_event_t, xcb_poll_for_event};
-    pub use xcb::*;
-    pub use xcb::{ConnResult, Connection};
 }
 
 #[cfg(target_os = "linux")]
@@ -48,12 +58,26 @@ pub mod types {
     pub type XcbConnection = xcb_connection_t;
 }
 
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle, Win32Handle, XcbHandle};
-use raw_window_handle::RawWindowHandle::{Win32, Xcb};
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+#[cfg(target_os = "windows")]
+use raw_window_handle::{Win32Handle, RawWindowHandle::Win32};
+#[cfg(target_os = "linux")]
+use raw_window_handle::{RawWindowHandle::Wayland, RawWindowHandle::Xcb, WaylandHandle, XcbHandle};
+
+#[cfg(target_os = "linux")]
+mod wayland_ffi;
+#[cfg(target_os = "linux")]
+use wayland_ffi as wl;
 
-use crate::event::{Event, EventData, EventDeque, EventType};
-use crate::keys::Key;
+use crate::error::{Error, Result};
+use crate::event::{Event, EventDeque, EventType};
+use crate::gamepad::GamepadManager;
+use crate::keys::{Key, Modifiers, ScanCode};
 use plat_libs::*;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Causes the current thread to sleep for a certain amount of milliseconds
 #[cfg(target_os = "windows")]
@@ -64,35 +88,212 @@ pub fn sleep(ms: u32) {
     }
 }
 
-/// A struct representative of the window
-#[allow(dead_code)]
-pub struct Window {
-    plat_win: PlatformWindow,
+/// Describes the window to create, build it with WindowBuilder::build
+#[derive(Clone)]
+pub struct WindowBuilder {
+    title: String,
     width: u16,
     height: u16,
     x: i16,
     y: i16,
+    resizable: bool,
+    decorations: bool,
+    min_size: Option<(u16, u16)>,
+    max_size: Option<(u16, u16)>,
+    maximized: bool,
+    wm_class: Option<(String, String)>,
+    headless: bool,
 }
 
-impl Window {
-    /// creates a value for all variables in a 'Window"
+impl Default for WindowBuilder {
+    fn default() -> Self {
+        WindowBuilder {
+            title: String::from("rovella"),
+            width: 800,
+            height: 600,
+            x: 0,
+            y: 0,
+            resizable: true,
+            decorations: true,
+            min_size: None,
+            max_size: None,
+            maximized: false,
+            wm_class: None,
+            headless: cfg!(feature = "headless"),
+        }
+    }
+}
+
+impl WindowBuilder {
     #[inline]
-    pub fn new(name: &'static str, width: u16, height: u16, x: i16, y: i16) -> Option<Window> {
-        let plat_win = PlatformWindow::new(name, width, height, x, y);
+    pub fn new() -> WindowBuilder {
+        WindowBuilder::default()
+    }
 
-        if plat_win.is_none() {
-            log_fatal!("Platform window couldn't be created");
-            return None;
+    #[inline]
+    pub fn with_title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    #[inline]
+    pub fn with_size(mut self, width: u16, height: u16) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    #[inline]
+    pub fn with_position(mut self, x: i16, y: i16) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// Whether the user can resize the window, a fixed size window still honours with_size
+    #[inline]
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Setting this to false creates a borderless window without a title bar
+    #[inline]
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    #[inline]
+    pub fn with_min_size(mut self, width: u16, height: u16) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    #[inline]
+    pub fn with_max_size(mut self, width: u16, height: u16) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    #[inline]
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.maximized = maximized;
+        self
+    }
+
+    /// Sets the instance and class names the window manager groups the window by
+    /// (WM_CLASS on X11, the window class name on Windows)
+    #[inline]
+    pub fn with_wm_class<T: Into<String>>(mut self, instance: T, class: T) -> Self {
+        self.wm_class = Some((instance.into(), class.into()));
+        self
+    }
+
+    /// Creates a headless window instead, which has no display connection and only a
+    /// virtual size, events are injected into it with Window::inject_event.
+    /// This defaults to true with the headless feature enabled
+    #[inline]
+    pub fn with_headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// The smallest size the window can be resized to, a fixed size window
+    /// is limited to its initial size
+    fn min_size_limit(&self) -> Option<(u16, u16)> {
+        if !self.resizable {
+            return Some((self.width, self.height));
+        }
+
+        return self.min_size;
+    }
+
+    /// The largest size the window can be resized to
+    fn max_size_limit(&self) -> Option<(u16, u16)> {
+        if !self.resizable {
+            return Some((self.width, self.height));
         }
 
-        return Some(Window {
-            plat_win: plat_win.unwrap(),
-            width,
-            height,
-            x,
-            y,
+        return self.max_size;
+    }
+
+    /// Creates the window
+    #[inline]
+    pub fn build(&self) -> Result<Window> {
+        return self.create(None);
+    }
+
+    /// Creates the window on the same display connection as another window, rather than
+    /// opening a new one
+    #[inline]
+    pub fn build_sharing(&self, window: &Window) -> Result<Window> {
+        return self.create(Some(&window.plat_win));
+    }
+
+    fn create(&self, sharing: Option<&WindowBackend>) -> Result<Window> {
+        let plat_win = match WindowBackend::new(self, sharing) {
+            Ok(plat_win) => plat_win,
+            Err(err) => {
+                log_fatal!("Platform window couldn't be created");
+                return Err(err);
+            }
+        };
+
+        return Ok(Window {
+            plat_win,
+            width: self.width,
+            height: self.height,
+            x: self.x,
+            y: self.y,
+            fullscreen: None,
+            // new windows are shown and normally given focus, the events confirm it later
+            focused: true,
+            visible: true,
+            cursor_grabbed: false,
+            cursor_visible: true,
         });
     }
+}
+
+/// Identifies a window, events are tagged with the id of the window they came from
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WindowId(pub(crate) u64);
+
+/// The ways a window can fill the screen
+#[derive(Clone, Copy, PartialEq)]
+pub enum Fullscreen {
+    /// Fullscreen that asks the compositor to step aside, for the lowest latency
+    Exclusive,
+    /// A borderless window covering the screen, quicker to switch in and out of
+    Borderless,
+}
+
+/// A struct representative of the window
+#[allow(dead_code)]
+pub struct Window {
+    plat_win: WindowBackend,
+    width: u16,
+    height: u16,
+    x: i16,
+    y: i16,
+    fullscreen: Option<Fullscreen>,
+    focused: bool,
+    visible: bool,
+    cursor_grabbed: bool,
+    cursor_visible: bool,
+}
+
+impl Window {
+    /// creates a value for all variables in a 'Window", see WindowBuilder for more options
+    #[inline]
+    pub fn new(name: &str, width: u16, height: u16, x: i16, y: i16) -> Result<Window> {
+        return WindowBuilder::new()
+            .with_title(name)
+            .with_size(width, height)
+            .with_position(x, y)
+            .build();
+    }
 
     #[inline]
     pub fn get_width(&self) -> u16 {
@@ -114,10 +315,160 @@ impl Window {
         return self.y;
     }
 
-    /// Gets events and helps to send them to the event manager
+    /// Switches the window into a fullscreen mode, or back to windowed with None.
+    /// The size change arrives as a WinResize event once the platform has applied it
+    #[inline]
+    pub fn set_fullscreen(&mut self, mode: Option<Fullscreen>) {
+        if self.fullscreen == mode {
+            return;
+        }
+
+        self.plat_win.set_fullscreen(mode);
+        self.fullscreen = mode;
+    }
+
+    #[inline]
+    pub fn get_fullscreen(&self) -> Option<Fullscreen> {
+        return self.fullscreen;
+    }
+
+    /// Whether the window has keyboard focus, as of the last update
+    #[inline]
+    pub fn has_focus(&self) -> bool {
+        return self.focused;
+    }
+
+    /// Whether the window is shown rather than minimized or hidden, as of the last update
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        return self.visible;
+    }
+
+    /// Keeps the cursor within the window, for camera controls along with hiding the cursor
+    /// and MouseRawMotion. The grab is let go of while the window is unfocused and taken
+    /// again when focus returns
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        if self.cursor_grabbed == grab {
+            return;
+        }
+
+        self.cursor_grabbed = grab;
+
+        if self.focused || !grab {
+            self.plat_win.set_cursor_grab(grab);
+        }
+    }
+
+    #[inline]
+    pub fn is_cursor_grabbed(&self) -> bool {
+        return self.cursor_grabbed;
+    }
+
+    /// Hides or shows the cursor while it's over the window
+    #[inline]
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if self.cursor_visible == visible {
+            return;
+        }
+
+        self.plat_win.set_cursor_visible(visible);
+        self.cursor_visible = visible;
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        return self.cursor_visible;
+    }
+
+    /// Gets the modifier keys held and lock keys active right now
+    #[inline]
+    pub fn get_modifiers(&self) -> Modifiers {
+        return self.plat_win.get_modifiers();
+    }
+
+    /// Gets what's printed on a key under the current layout, for on screen prompts like
+    /// "Press Z to jump". Printable keys give the character they type, uppercased, and
+    /// other keys their Key's name. Headless windows have no layout and label keys as
+    /// they are on a US keyboard
+    #[inline]
+    pub fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        return self.plat_win.get_key_label(scancode);
+    }
+
+    /// Whether this is a headless window, see WindowBuilder::with_headless
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        return matches!(self.plat_win, WindowBackend::Headless(_));
+    }
+
+    /// Queues an event as if it came from the platform, it's delivered by the next update.
+    /// Only headless windows accept injected events
+    #[inline]
+    pub fn inject_event(&mut self, e_type: EventType) {
+        self.inject_event_at(e_type, Instant::now());
+    }
+
+    /// Queues an event that happened at time, see inject_event
+    pub fn inject_event_at(&mut self, e_type: EventType, time: Instant) {
+        match &mut self.plat_win {
+            WindowBackend::Headless(headless) => headless.injected.push_back(Event::at(e_type, time)),
+            _ => {
+                log_warn!("Events can only be injected into headless windows");
+            }
+        }
+    }
+
+    /// Gets the id events from this window are tagged with
     #[inline]
-    pub fn update(&self, ev_que: &mut EventDeque) {
+    pub fn get_id(&self) -> WindowId {
+        return self.plat_win.get_id();
+    }
+
+    /// Gets events and helps to send them to the event manager, tagging them with the
+    /// window's id and keeping the cached size, position, focus and visibility in sync.
+    /// A cursor grab is let go of and taken again as focus changes
+    #[inline]
+    pub fn update(&mut self, ev_que: &mut EventDeque) {
+        let first_new = ev_que.len();
         self.plat_win.update(ev_que);
+
+        let id = self.get_id();
+
+        for event in ev_que.range_mut(first_new..) {
+            event.window = Some(id);
+
+            match event.e_type {
+                EventType::WinResize { width, height } => {
+                    self.width = width;
+                    self.height = height;
+                }
+                EventType::WinMove { x, y } => {
+                    self.x = x;
+                    self.y = y;
+                }
+                EventType::WinShow => self.visible = true,
+                EventType::WinHide => self.visible = false,
+                EventType::WinFocusGained => self.focused = true,
+                EventType::WinFocusLost => self.focused = false,
+                _ => {}
+            }
+
+            if !self.cursor_grabbed {
+                continue;
+            }
+
+            // the grab is taken again when the window moves too, as on Windows the area
+            // the cursor is clipped to doesn't follow the window
+            match event.e_type {
+                EventType::WinFocusGained | EventType::WinResize { .. } | EventType::WinMove { .. }
+                    if self.focused =>
+                {
+                    self.plat_win.set_cursor_grab(true);
+                }
+                EventType::WinFocusLost => self.plat_win.set_cursor_grab(false),
+                _ => {}
+            }
+        }
     }
 
     /// Frees up memory and calls shutdown functions
@@ -127,391 +478,1360 @@ impl Window {
     }
 }
 
+/// A headless window gives an empty handle, which nothing can be rendered to
 unsafe impl HasRawWindowHandle for Window {
     #[cfg(target_os = "windows")]
     fn raw_window_handle(&self) -> RawWindowHandle {
         let mut handle = Win32Handle::empty();
-        handle.hwnd = self.plat_win.hwnd as _;
-        handle.hinstance = self.plat_win.hinst as _;
+
+        if let WindowBackend::Native(plat_win) = &self.plat_win {
+            handle.hwnd = plat_win.hwnd as _;
+            handle.hinstance = plat_win.hinst as _;
+        }
 
         return Win32(handle);
     }
 
     #[cfg(target_os = "linux")]
     fn raw_window_handle(&self) -> RawWindowHandle {
+        if let WindowBackend::Wayland(wl_win) = &self.plat_win {
+            let mut handle = WaylandHandle::empty();
+            handle.surface = wl_win.surface as _;
+            handle.display = wl_win.shared.display as _;
+
+            return Wayland(handle);
+        }
+
         let mut handle = XcbHandle::empty();
-        handle.window = self.plat_win.window;
+
+        if let WindowBackend::Native(plat_win) = &self.plat_win {
+            handle.window = plat_win.window;
+        }
         handle.visual_id = 0;
 
         return Xcb(handle);
     }
 }
 
-/// A struct for platform related aspects of a window
-#[cfg(target_os = "windows")]
-struct PlatformWindow {
-    pub(crate) hinst: types::Hinstance,
-    pub(crate) hwnd: *mut types::Hwnd,
-}
-
-trait TPlatformWindow {
-    fn new(name: &'static str, width: u16, height: u16, x: i16, y: i16) -> Option<PlatformWindow>;
-    fn update(&self, ev_que: &mut EventDeque);
-    fn destroy(&self);
+/// What a Window is backed by, chosen by the WindowBuilder
+enum WindowBackend {
+    // boxed as the platform windows are far bigger than the headless one
+    Native(Box<PlatformWindow>),
+    #[cfg(target_os = "linux")]
+    Wayland(Box<WaylandWindow>),
+    Headless(HeadlessWindow),
 }
 
-#[cfg(target_os = "windows")]
-impl TPlatformWindow for PlatformWindow {
-    /// creates the window
-    fn new(name: &'static str, width: u16, height: u16, x: i16, y: i16) -> Option<PlatformWindow> {
-        let mut win = PlatformWindow {
-            hinst: null_mut(),
-            hwnd: null_mut(),
-        };
-
-        let class_name = CString::new("rovella_window_class").expect("CString ERROR");
-        let window_name = CString::new(name).expect("CString ERROR");
-
-        unsafe {
-            win.hinst = GetModuleHandleA(0 as *const i8);
-            let icon = LoadIconA(win.hinst, IDI_APPLICATION as *const i8);
-            let cursor = LoadCursorA(win.hinst, IDC_ARROW as *const i8);
-
-            let wc = WNDCLASSA {
-                style: CS_DBLCLKS,
-                lpfnWndProc: Some(window_proc),
-                cbClsExtra: 0,
-                cbWndExtra: 1,
-                hInstance: win.hinst,
-                hIcon: icon,
-                hCursor: cursor,
-                hbrBackground: null_mut(),
-                lpszMenuName: null_mut(),
-                lpszClassName: class_name.deref().as_ptr(),
-            };
-
-            if RegisterClassA(&wc) == 0 {
-                log_fatal!("failed to register window class");
-                return None;
+impl WindowBackend {
+    /// Creates a window on a new display connection. On Linux that's the Wayland compositor
+    /// when WAYLAND_DISPLAY is set, falling back to X11 when it isn't or can't be used
+    fn new_native(config: &WindowBuilder) -> Result<WindowBackend> {
+        #[cfg(target_os = "linux")]
+        {
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                match WaylandWindow::new(config, None) {
+                    Ok(wl_win) => return Ok(WindowBackend::Wayland(Box::new(wl_win))),
+                    Err(err) => log_warn!("Falling back to X11, {}", err),
+                }
             }
+        }
 
-            let window_style = WS_OVERLAPPED | WS_SYSMENU;
-            let window_ex_style = WS_EX_APPWINDOW | WS_MAXIMIZEBOX | WS_MINIMIZEBOX;
+        return Ok(WindowBackend::Native(Box::new(PlatformWindow::new(config, None)?)));
+    }
+}
 
-            // Todo: Memory allocation is likely unecessary, investigate
-            let layout = Layout::new::<RECT>();
-            let border_rect: *mut u8 = alloc_zeroed(layout);
+impl TPlatformWindow for WindowBackend {
+    fn new(config: &WindowBuilder, sharing: Option<&WindowBackend>) -> Result<WindowBackend> {
+        if config.headless {
+            return Ok(WindowBackend::Headless(HeadlessWindow::new(config, None)?));
+        }
 
-            AdjustWindowRectEx(border_rect as *mut RECT, window_style,
-                               0, window_ex_style);
+        // a window can only share the connection of a window of the same kind
+        return match sharing {
+            Some(WindowBackend::Native(plat_win)) => {
+                Ok(WindowBackend::Native(Box::new(PlatformWindow::new(config, Some(plat_win))?)))
+            }
+            #[cfg(target_os = "linux")]
+            Some(WindowBackend::Wayland(wl_win)) => {
+                Ok(WindowBackend::Wayland(Box::new(WaylandWindow::new(config, Some(wl_win))?)))
+            }
+            _ => WindowBackend::new_native(config),
+        };
+    }
 
-            dealloc(border_rect, layout);
+    #[inline]
+    fn get_id(&self) -> WindowId {
+        return match self {
+            WindowBackend::Native(plat_win) => plat_win.get_id(),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.get_id(),
+            WindowBackend::Headless(headless) => headless.get_id(),
+        };
+    }
 
-            win.hwnd = CreateWindowExA(
-                window_ex_style,
-                class_name.deref().as_ptr(),
-                window_name.deref().as_ptr(),
-                window_style,
-                x as c_int,
-                y as c_int,
-                width as c_int,
-                height as c_int,
-                null_mut(),
-                null_mut(),
-                win.hinst,
-                null_mut(),
-            ) as _;
+    #[inline]
+    fn update(&mut self, ev_que: &mut EventDeque) {
+        match self {
+            WindowBackend::Native(plat_win) => plat_win.update(ev_que),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.update(ev_que),
+            WindowBackend::Headless(headless) => headless.update(ev_que),
         }
+    }
 
-        if win.hwnd.is_null() {
-            log_fatal!("Failed to create window {}", name);
-            return None;
+    #[inline]
+    fn set_fullscreen(&mut self, mode: Option<Fullscreen>) {
+        match self {
+            WindowBackend::Native(plat_win) => plat_win.set_fullscreen(mode),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.set_fullscreen(mode),
+            WindowBackend::Headless(headless) => headless.set_fullscreen(mode),
         }
+    }
 
-        unsafe {
-            ShowWindow(win.hwnd as _, SW_SHOW);
-        }
+    #[inline]
+    fn get_modifiers(&self) -> Modifiers {
+        return match self {
+            WindowBackend::Native(plat_win) => plat_win.get_modifiers(),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.get_modifiers(),
+            WindowBackend::Headless(headless) => headless.get_modifiers(),
+        };
+    }
 
-        return Some(win);
+    #[inline]
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        return match self {
+            WindowBackend::Native(plat_win) => plat_win.get_key_label(scancode),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.get_key_label(scancode),
+            WindowBackend::Headless(headless) => headless.get_key_label(scancode),
+        };
     }
 
     #[inline]
-    fn update(&self, ev_que: &mut EventDeque) {
-        unsafe {
-            SetWindowLongPtrA(self.hwnd as _, GWLP_USERDATA, ptr::addr_of_mut!(*ev_que) as _);
+    fn set_cursor_grab(&mut self, grab: bool) {
+        match self {
+            WindowBackend::Native(plat_win) => plat_win.set_cursor_grab(grab),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.set_cursor_grab(grab),
+            WindowBackend::Headless(headless) => headless.set_cursor_grab(grab),
         }
+    }
 
-        let mut message: MSG = MSG {
-            hwnd: null_mut(),
-            message: 0,
-            wParam: 0,
-            lParam: 0,
-            time: 0,
-            pt: POINT { x: 0, y: 0 },
-        };
-
-        unsafe {
-            while PeekMessageA(ptr::addr_of_mut!(message), null_mut(), 0, 0, PM_REMOVE) != 0 {
-                TranslateMessage(ptr::addr_of_mut!(message));
-                DispatchMessageA(ptr::addr_of_mut!(message));
-            }
+    #[inline]
+    fn set_cursor_visible(&mut self, visible: bool) {
+        match self {
+            WindowBackend::Native(plat_win) => plat_win.set_cursor_visible(visible),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.set_cursor_visible(visible),
+            WindowBackend::Headless(headless) => headless.set_cursor_visible(visible),
         }
     }
 
-    /// destroys the window
+    #[inline]
     fn destroy(&self) {
-        if !self.hwnd.is_null() {
-            unsafe {
-                DestroyWindow(self.hwnd as _);
-            }
-        } else {
-            log_warn!("Attempted to close HWND with null value");
+        match self {
+            WindowBackend::Native(plat_win) => plat_win.destroy(),
+            #[cfg(target_os = "linux")]
+            WindowBackend::Wayland(wl_win) => wl_win.destroy(),
+            WindowBackend::Headless(headless) => headless.destroy(),
         }
     }
 }
 
-#[cfg(target_os = "windows")]
-impl From<u32> for EventType {
-    /// converts a u32 to EventType and vice versa
-    fn from(msg: u32) -> Self {
-        match msg {
-            WM_CLOSE => EventType::WinClose,
-            WM_SHOWWINDOW => EventType::WinShow,
-            WM_SIZE => EventType::WinResize,
-            WM_KEYDOWN => EventType::KeyDown,
-            WM_SYSKEYDOWN => EventType::KeyDown,
-            WM_KEYUP => EventType::KeyUp,
-            WM_SYSKEYUP => EventType::KeyUp,
-            WM_MOUSEMOVE => EventType::MouseMove,
-            WM_MOUSEWHEEL => EventType::MouseWheel,
-            WM_LBUTTONDOWN => EventType::MouseLeftBtnDown,
-            WM_MBUTTONDOWN => EventType::MouseMidBtnDown,
-            WM_RBUTTONDOWN => EventType::MouseRightBtnDown,
-            WM_LBUTTONUP => EventType::MouseLeftBtnUp,
-            WM_MBUTTONUP => EventType::MouseMidBtnUp,
-            WM_RBUTTONUP => EventType::MouseRightBtnUp,
-            _ => EventType::None,
-        }
-    }
+/// Headless window ids have the top bit set so they can't clash with platform window ids
+const HEADLESS_ID_BIT: u64 = 1 << 63;
+static NEXT_HEADLESS_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A window that only exists in memory, nothing is shown and the only events it sends
+/// are the ones injected into it
+struct HeadlessWindow {
+    id: WindowId,
+    injected: VecDeque<Event>,
+    // the modifiers of the last key or button event, as there's no keyboard to query
+    modifiers: Modifiers,
 }
 
-#[cfg(target_os = "windows")]
-unsafe fn add_event_to_que(event: Event, hwnd: *mut HWND__) {
-    let ev_que: *mut EventDeque = GetWindowLongPtrA(hwnd, GWLP_USERDATA) as _;
+impl TPlatformWindow for HeadlessWindow {
+    fn new(_config: &WindowBuilder, _sharing: Option<&HeadlessWindow>) -> Result<HeadlessWindow> {
+        let id = NEXT_HEADLESS_ID.fetch_add(1, Ordering::Relaxed) | HEADLESS_ID_BIT;
 
-    if !(ev_que.is_null()) {
-        (*ev_que).push_back(event);
-    } else {
-        let err = GetLastError();
-        if err != 0 {
-            log_error!(
-                "window proc couldn't retrieve event queue with error code, {} ",
-                err
-            );
+        return Ok(HeadlessWindow {
+            id: WindowId(id),
+            injected: VecDeque::new(),
+            modifiers: Modifiers::default(),
+        });
+    }
+
+    #[inline]
+    fn get_id(&self) -> WindowId {
+        return self.id;
+    }
+
+    fn update(&mut self, ev_que: &mut EventDeque) {
+        for event in self.injected.drain(..) {
+            match event.e_type {
+                EventType::KeyDown { modifiers, .. }
+                | EventType::KeyUp { modifiers, .. }
+                | EventType::MouseLeftBtnDown { modifiers }
+                | EventType::MouseLeftBtnUp { modifiers }
+                | EventType::MouseMidBtnDown { modifiers }
+                | EventType::MouseMidBtnUp { modifiers }
+                | EventType::MouseRightBtnDown { modifiers }
+                | EventType::MouseRightBtnUp { modifiers } => self.modifiers = modifiers,
+                _ => {}
+            }
+
+            ev_que.push_back(event);
         }
     }
+
+    /// There's no screen to fill, Window still records the mode
+    #[inline]
+    fn set_fullscreen(&mut self, _mode: Option<Fullscreen>) {}
+
+    #[inline]
+    fn get_modifiers(&self) -> Modifiers {
+        return self.modifiers;
+    }
+
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        return match scancode.get_key() {
+            Key::None => None,
+            key => Some(key.get_name().to_string()),
+        };
+    }
+
+    /// There's no cursor, Window still records the grab and visibility
+    #[inline]
+    fn set_cursor_grab(&mut self, _grab: bool) {}
+
+    #[inline]
+    fn set_cursor_visible(&mut self, _visible: bool) {}
+
+    #[inline]
+    fn destroy(&self) {}
 }
 
-/// the callback for window event management used in the win32 api
+/// Turns the millisecond timestamps the platforms put on input events into Instants.
+/// The first time seen is taken as now, later ones are placed relative to it
+#[derive(Default)]
+struct EventClock {
+    base: Option<(u32, Instant)>,
+}
+
+impl EventClock {
+    fn instant_of(&mut self, time: u32) -> Instant {
+        let now = Instant::now();
+
+        let (base_time, base) = match self.base {
+            Some(base) => base,
+            None => {
+                self.base = Some((time, now));
+                return now;
+            }
+        };
+
+        // the timestamps wrap around after ~49 days, so the difference is taken signed
+        let offset = time.wrapping_sub(base_time) as i32;
+        let instant = if offset >= 0 {
+            base + Duration::from_millis(offset as u64)
+        } else {
+            base.checked_sub(Duration::from_millis(offset.unsigned_abs() as u64)).unwrap_or(base)
+        };
+
+        // a time past now means the base was taken late, so it's moved to this event
+        if instant > now {
+            self.base = Some((time, now));
+            return now;
+        }
+
+        return instant;
+    }
+}
+
+/// Wakes an App blocked in wait_events from another thread, get one with App::get_waker
+#[derive(Clone)]
+pub struct EventLoopWaker {
+    handle: Arc<WakeHandle>,
+}
+
+/// The pipe a wake is written to, which waits poll alongside the display connections
+#[cfg(target_os = "linux")]
+struct WakeHandle {
+    read_fd: libc::c_int,
+    write_fd: libc::c_int,
+}
+
+/// The auto-reset event waits are signalled with
 #[cfg(target_os = "windows")]
-unsafe extern "system" fn window_proc(
-    hwnd: *mut HWND__,
-    msg: u32,
-    wparam: usize,
-    lparam: isize,
-) -> LRESULT {
-    if msg == WM_CREATE {
-        return DefWindowProcA(hwnd, msg, wparam, lparam);
+struct WakeHandle {
+    event: HANDLE,
+}
+
+// an event handle can be signalled from any thread
+#[cfg(target_os = "windows")]
+unsafe impl Send for WakeHandle {}
+#[cfg(target_os = "windows")]
+unsafe impl Sync for WakeHandle {}
+
+impl EventLoopWaker {
+    #[cfg(target_os = "linux")]
+    pub(crate) fn new() -> Result<EventLoopWaker> {
+        let mut fds: [libc::c_int; 2] = [0; 2];
+
+        // non-blocking, so waking with the pipe already full doesn't block the waking thread
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+            return Err(Error::EventLoopWaker(std::io::Error::last_os_error()));
+        }
+
+        return Ok(EventLoopWaker {
+            handle: Arc::new(WakeHandle {
+                read_fd: fds[0],
+                write_fd: fds[1],
+            }),
+        });
     }
 
-    match msg {
-        WM_ERASEBKGND => {
-            return 1;
+    #[cfg(target_os = "windows")]
+    pub(crate) fn new() -> Result<EventLoopWaker> {
+        let event = unsafe { CreateEventW(null_mut(), FALSE, FALSE, null_mut()) };
+
+        if event.is_null() {
+            return Err(Error::EventLoopWaker(std::io::Error::last_os_error()));
         }
-        WM_CLOSE => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::WinClose,
-                    data: EventData::default()
-                },
-                hwnd,
-            );
-            return 0;
+
+        return Ok(EventLoopWaker {
+            handle: Arc::new(WakeHandle { event }),
+        });
+    }
+
+    /// Makes a blocked wait_events check for events again, like those sent through an
+    /// EventLoopProxy
+    #[cfg(target_os = "linux")]
+    pub fn wake(&self) {
+        let byte = 1u8;
+        unsafe {
+            libc::write(self.handle.write_fd, ptr::addr_of!(byte) as _, 1);
         }
-        WM_DESTROY => {
-            PostQuitMessage(0);
-            return 0;
+    }
+
+    /// Makes a blocked wait_events check for events again, like those sent through an
+    /// EventLoopProxy
+    #[cfg(target_os = "windows")]
+    pub fn wake(&self) {
+        unsafe {
+            SetEvent(self.handle.event);
         }
-        WM_KEYDOWN | WM_SYSKEYDOWN => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::KeyDown,
-                    data: EventData {
-                        m_u32: wparam as u32,
-                    }
-                },
-                hwnd,
-            );
+    }
+
+    /// Forgets any wake not yet waited on
+    #[cfg(target_os = "linux")]
+    pub(crate) fn clear(&self) {
+        let mut buffer = [0u8; 64];
+        unsafe { while libc::read(self.handle.read_fd, buffer.as_mut_ptr() as _, buffer.len()) > 0 {} }
+    }
+
+    /// Forgets any wake not yet waited on
+    #[cfg(target_os = "windows")]
+    pub(crate) fn clear(&self) {
+        unsafe {
+            ResetEvent(self.handle.event);
         }
-        WM_KEYUP | WM_SYSKEYUP => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::KeyUp,
-                    data: EventData {
-                        m_u32: wparam as u32,
-                    }
-                },
-                hwnd,
-            );
+    }
+}
+
+impl Drop for WakeHandle {
+    #[cfg(target_os = "linux")]
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
         }
-        WM_MOUSEMOVE => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseMove,
-                    data: EventData {
-                        m_arr2_i16: [
-                            GET_X_LPARAM(lparam) as i16,
-                            GET_Y_LPARAM(lparam) as i16
-                        ]
-                    }
-                },
-                hwnd,
-            );
+    }
+
+    #[cfg(target_os = "windows")]
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.event);
         }
-        WM_MOUSEWHEEL => {
-            let z_delta = GET_WHEEL_DELTA_WPARAM(wparam);
-            if z_delta != 0 {
-                if z_delta < 0 {
-                    add_event_to_que(
-                        Event {
-                            e_type: EventType::MouseWheel,
-                            data: EventData { m_i32: -1 as i32 },
-                        },
-                        hwnd,
-                    );
-                } else {
-                    add_event_to_que(
-                        Event {
-                            e_type: EventType::MouseWheel,
-                            data: EventData { m_i32: 1 as i32 },
-                        },
-                        hwnd,
-                    );
+    }
+}
+
+/// Blocks until one of the windows or gamepads has events to read, the waker is woken or
+/// the timeout elapses
+#[cfg(target_os = "linux")]
+pub(crate) fn wait_for_events(
+    windows: &[Window],
+    gamepads: &GamepadManager,
+    waker: &EventLoopWaker,
+    timeout: Option<Duration>,
+) {
+    let mut x_connections: Vec<&Rc<XConnection>> = Vec::new();
+    let mut wl_connections: Vec<&Rc<WlConnection>> = Vec::new();
+
+    // windows sharing a connection are waited on once
+    for window in windows {
+        match &window.plat_win {
+            WindowBackend::Native(plat_win) => {
+                if !x_connections.iter().any(|shared| Rc::ptr_eq(shared, &plat_win.shared)) {
+                    x_connections.push(&plat_win.shared);
+                }
+            }
+            WindowBackend::Wayland(wl_win) => {
+                if !wl_connections.iter().any(|shared| Rc::ptr_eq(shared, &wl_win.shared)) {
+                    wl_connections.push(&wl_win.shared);
+                }
+            }
+            WindowBackend::Headless(headless) => {
+                if !headless.injected.is_empty() {
+                    return;
                 }
             }
         }
-        WM_LBUTTONDOWN => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseLeftBtnDown,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
-        }
-        WM_MBUTTONDOWN => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseMidBtnDown,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
+    }
+
+    if gamepads.has_pending() {
+        return;
+    }
+
+    let mut poll_fds = vec![libc::pollfd {
+        fd: waker.handle.read_fd,
+        events: libc::POLLIN,
+        revents: 0,
+    }];
+
+    for fd in gamepads.get_fds() {
+        poll_fds.push(libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+    }
+
+    let mut timeout = timeout;
+
+    for shared in x_connections.iter() {
+        unsafe {
+            // events read while waiting for replies sit in xcb's queue, where polling can't see them
+            shared.pump();
+            if shared.has_pending() {
+                return;
+            }
+
+            xcb_flush(shared.connection);
+
+            poll_fds.push(libc::pollfd {
+                fd: xcb_get_file_descriptor(shared.connection),
+                events: libc::POLLIN,
+                revents: 0,
+            });
         }
-        WM_RBUTTONDOWN => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseRightBtnDown,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
+    }
+
+    // connections with a read prepared, and where their fd is in poll_fds
+    let mut prepared: Vec<(&Rc<WlConnection>, usize)> = Vec::new();
+    let mut ready = false;
+
+    for shared in wl_connections.iter() {
+        // held keys are repeated by the client, which has to wake up for them
+        if let Some(due) = shared.next_repeat() {
+            let until_due = due.saturating_duration_since(Instant::now());
+            timeout = Some(timeout.map_or(until_due, |timeout| timeout.min(until_due)));
         }
-        WM_LBUTTONUP => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseLeftBtnUp,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
+
+        match shared.prepare_wait() {
+            WlWait::Fd(fd) => {
+                prepared.push((shared, poll_fds.len()));
+                poll_fds.push(libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+            WlWait::Ready => {
+                ready = true;
+                break;
+            }
+            WlWait::Lost => {}
         }
-        WM_MBUTTONUP => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseMidBtnUp,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
+    }
+
+    if !ready {
+        // rounded up, so a wait doesn't end just short of the timeout and spin
+        let timeout_ms = match timeout {
+            Some(timeout) => (timeout.as_micros() as u64).div_ceil(1000).min(libc::c_int::MAX as u64) as libc::c_int,
+            None => -1,
+        };
+
+        unsafe {
+            libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, timeout_ms);
         }
-        WM_RBUTTONUP => {
-            add_event_to_que(
-                Event {
-                    e_type: EventType::MouseRightBtnUp,
-                    data: EventData::default(),
-                },
-                hwnd,
-            );
+    }
+
+    for (shared, index) in prepared {
+        shared.finish_wait(poll_fds[index].revents & libc::POLLIN != 0);
+    }
+
+}
+
+/// Blocks until a message arrives for the thread, the waker is woken or the timeout
+/// elapses, gamepads aren't supported on windows yet
+#[cfg(target_os = "windows")]
+pub(crate) fn wait_for_events(
+    windows: &[Window],
+    gamepads: &GamepadManager,
+    waker: &EventLoopWaker,
+    timeout: Option<Duration>,
+) {
+    if gamepads.has_pending() {
+        return;
+    }
+
+    for window in windows {
+        if let WindowBackend::Headless(headless) = &window.plat_win {
+            if !headless.injected.is_empty() {
+                return;
+            }
         }
-        _ => {}
     }
 
-    return DefWindowProcA(hwnd, msg, wparam, lparam);
+    // rounded up, so a wait doesn't end just short of the timeout and spin
+    let timeout_ms = match timeout {
+        Some(timeout) => (timeout.as_micros() as u64).div_ceil(1000).min(INFINITE as u64 - 1) as DWORD,
+        None => INFINITE,
+    };
+
+    unsafe {
+        MsgWaitForMultipleObjects(1, ptr::addr_of!(waker.handle.event), FALSE, timeout_ms, QS_ALLINPUT);
+    }
 }
 
+/// A struct for platform related aspects of a window
 #[cfg(target_os = "windows")]
-impl From<u32> for Key {
-    fn from(val: u32) -> Self {
-        return match val as u16 {
-            0x08 => Key::Backspace,
-            0x0D => Key::Enter,
-            0x09 => Key::Tab,
-            0x10 => Key::Shift,
-            0x11 => Key::Control,
-            0x13 => Key::Pause,
-            0x14 => Key::Capital,
-            0x1B => Key::Escape,
-            0x1C => Key::Convert,
-            0x1D => Key::NonConvert,
-            0x1E => Key::Accept,
-            0x1F => Key::ModeChange,
-            0x20 => Key::Space,
-            0x21 => Key::Prior,
-            0x22 => Key::Next,
-            0x23 => Key::End,
-            0x24 => Key::Home,
-            0x25 => Key::Left,
-            0x26 => Key::Up,
-            0x27 => Key::Right,
-            0x28 => Key::Down,
-            0x29 => Key::Select,
-            0x2A => Key::Print,
-            0x2B => Key::Execute,
-            0x2C => Key::Snapshot,
-            0x2D => Key::Insert,
-            0x2E => Key::Delete,
-            0x2F => Key::Help,
-            0x41 => Key::A,
-            0x42 => Key::B,
-            0x43 => Key::C,
-            0x44 => Key::D,
-            0x45 => Key::E,
-            0x46 => Key::F,
-            0x47 => Key::G,
-            0x48 => Key::H,
-            0x49 => Key::I,
-            0x4A => Key::J,
-            0x4B => Key::K,
-            0x4C => Key::L,
-            0x4D => Key::M,
-            0x4E => Key::N,
-            0x4F => Key::O,
-            0x50 => Key::P,
-            0x51 => Key::Q,
-            0x52 => Key::R,
-            0x53 => Key::S,
-            0x54 => Key::T,
+struct PlatformWindow {
+    pub(crate) hinst: types::Hinstance,
+    pub(crate) hwnd: *mut types::Hwnd,
+    // the style and placement to return to when leaving fullscreen
+    windowed_placement: Option<(LONG_PTR, RECT)>,
+    // the events window_proc made for this window, held until its update. Boxed so the
+    // pointer the window keeps to it stays valid when this moves
+    pending: Box<EventDeque>,
+}
+
+trait TPlatformWindow {
+    fn new(config: &WindowBuilder, sharing: Option<&Self>) -> Result<Self>
+    where
+        Self: Sized;
+    fn get_id(&self) -> WindowId;
+    fn update(&mut self, ev_que: &mut EventDeque);
+    fn set_fullscreen(&mut self, mode: Option<Fullscreen>);
+    fn get_modifiers(&self) -> Modifiers;
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String>;
+    fn set_cursor_grab(&mut self, grab: bool);
+    fn set_cursor_visible(&mut self, visible: bool);
+    fn destroy(&self);
+}
+
+#[cfg(target_os = "windows")]
+impl TPlatformWindow for PlatformWindow {
+    /// creates the window, there's no connection to share on windows
+    fn new(config: &WindowBuilder, _sharing: Option<&PlatformWindow>) -> Result<PlatformWindow> {
+        let mut win = PlatformWindow {
+            hinst: null_mut(),
+            hwnd: null_mut(),
+            windowed_placement: None,
+            pending: Box::new(EventDeque::new()),
+        };
+
+        // the wide versions of the api are used so WM_CHAR carries UTF-16 rather than the ANSI code page
+        let class_name = match &config.wm_class {
+            Some((_, class)) => to_wide(class),
+            None => to_wide("rovella_window_class"),
+        };
+        let window_name = to_wide(&config.title);
+
+        unsafe {
+            win.hinst = GetModuleHandleA(0 as *const i8);
+            let icon = LoadIconW(win.hinst, IDI_APPLICATION as _);
+            let cursor = LoadCursorW(win.hinst, IDC_ARROW as _);
+
+            let wc = WNDCLASSW {
+                style: CS_DBLCLKS,
+                lpfnWndProc: Some(window_proc),
+                cbClsExtra: 0,
+                cbWndExtra: 1,
+                hInstance: win.hinst,
+                hIcon: icon,
+                hCursor: cursor,
+                hbrBackground: null_mut(),
+                lpszMenuName: null_mut(),
+                lpszClassName: class_name.as_ptr(),
+            };
+
+            // the class is registered once and reused by later windows
+            if RegisterClassW(&wc) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+                let err = GetLastError();
+                log_fatal!("failed to register window class");
+                return Err(Error::WindowCreation(format!(
+                    "failed to register window class, error code {}",
+                    err
+                )));
+            }
+
+            let mut window_style = WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX;
+            let window_ex_style = WS_EX_APPWINDOW;
+
+            if config.resizable {
+                window_style |= WS_THICKFRAME | WS_MAXIMIZEBOX;
+            }
+
+            if !config.decorations {
+                window_style = WS_POPUP;
+            }
+
+            // Todo: Memory allocation is likely unecessary, investigate
+            let layout = Layout::new::<RECT>();
+            let border_rect: *mut u8 = alloc_zeroed(layout);
+
+            AdjustWindowRectEx(border_rect as *mut RECT, window_style,
+                               0, window_ex_style);
+
+            dealloc(border_rect, layout);
+
+            win.hwnd = CreateWindowExW(
+                window_ex_style,
+                class_name.as_ptr(),
+                window_name.as_ptr(),
+                window_style,
+                config.x as c_int,
+                config.y as c_int,
+                config.width as c_int,
+                config.height as c_int,
+                null_mut(),
+                null_mut(),
+                win.hinst,
+                null_mut(),
+            ) as _;
+        }
+
+        if win.hwnd.is_null() {
+            let err = unsafe { GetLastError() };
+            log_fatal!("Failed to create window {}", config.title);
+            return Err(Error::WindowCreation(format!(
+                "CreateWindowExW failed with error code {}",
+                err
+            )));
+        }
+
+        unsafe {
+            SetWindowLongPtrA(win.hwnd as _, GWLP_USERDATA, ptr::addr_of_mut!(*win.pending) as _);
+
+            // the size limits are read back by window_proc when handling WM_GETMINMAXINFO
+            if let Some((width, height)) = config.min_size_limit() {
+                SetPropA(win.hwnd as _, MIN_SIZE_PROP.as_ptr() as _, pack_size(width, height));
+            }
+
+            if let Some((width, height)) = config.max_size_limit() {
+                SetPropA(win.hwnd as _, MAX_SIZE_PROP.as_ptr() as _, pack_size(width, height));
+            }
+
+            // raw mouse input is sent as WM_INPUT to whichever window has focus
+            let mouse = RAWINPUTDEVICE {
+                usUsagePage: HID_USAGE_PAGE_GENERIC,
+                usUsage: HID_USAGE_GENERIC_MOUSE,
+                dwFlags: 0,
+                hwndTarget: null_mut(),
+            };
+
+            if RegisterRawInputDevices(&mouse, 1, std::mem::size_of::<RAWINPUTDEVICE>() as UINT) == 0 {
+                log_warn!("Failed to register for raw mouse input, there won't be raw mouse motion");
+            }
+
+            if config.maximized {
+                ShowWindow(win.hwnd as _, SW_SHOWMAXIMIZED);
+            } else {
+                ShowWindow(win.hwnd as _, SW_SHOW);
+            }
+        }
+
+        return Ok(win);
+    }
+
+    #[inline]
+    fn get_id(&self) -> WindowId {
+        return WindowId(self.hwnd as u64);
+    }
+
+    #[inline]
+    fn update(&mut self, ev_que: &mut EventDeque) {
+        let mut message: MSG = MSG {
+            hwnd: null_mut(),
+            message: 0,
+            wParam: 0,
+            lParam: 0,
+            time: 0,
+            pt: POINT { x: 0, y: 0 },
+        };
+
+        unsafe {
+            // every message for the thread, window_proc holds the events each one makes for
+            // its window's own update, so the first window to update pumps for the others
+            while PeekMessageW(ptr::addr_of_mut!(message), null_mut(), 0, 0, PM_REMOVE) != 0 {
+                TranslateMessage(ptr::addr_of_mut!(message));
+                DispatchMessageW(ptr::addr_of_mut!(message));
+            }
+        }
+
+        ev_que.append(&mut self.pending);
+    }
+
+    fn set_fullscreen(&mut self, mode: Option<Fullscreen>) {
+        let hwnd = self.hwnd as HWND;
+
+        unsafe {
+            match mode {
+                Some(mode) => {
+                    // remember the windowed placement so it can be restored, unless
+                    // we are only switching between fullscreen modes
+                    if self.windowed_placement.is_none() {
+                        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+                        GetWindowRect(hwnd, ptr::addr_of_mut!(rect));
+                        self.windowed_placement = Some((GetWindowLongPtrA(hwnd, GWL_STYLE), rect));
+                    }
+
+                    let mut monitor_info: MONITORINFO = std::mem::zeroed();
+                    monitor_info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+                    GetMonitorInfoA(
+                        MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST),
+                        ptr::addr_of_mut!(monitor_info),
+                    );
+
+                    let rect = monitor_info.rcMonitor;
+                    let insert_after = match mode {
+                        Fullscreen::Exclusive => HWND_TOPMOST,
+                        Fullscreen::Borderless => HWND_NOTOPMOST,
+                    };
+
+                    SetWindowLongPtrA(hwnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE) as LONG_PTR);
+                    SetWindowPos(
+                        hwnd,
+                        insert_after,
+                        rect.left,
+                        rect.top,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_FRAMECHANGED | SWP_SHOWWINDOW,
+                    );
+                }
+                None => {
+                    let (style, rect) = match self.windowed_placement.take() {
+                        Some(placement) => placement,
+                        None => return,
+                    };
+
+                    SetWindowLongPtrA(hwnd, GWL_STYLE, style);
+                    SetWindowPos(
+                        hwnd,
+                        HWND_NOTOPMOST,
+                        rect.left,
+                        rect.top,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_FRAMECHANGED | SWP_SHOWWINDOW,
+                    );
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn get_modifiers(&self) -> Modifiers {
+        return unsafe { current_modifiers() };
+    }
+
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        // the scancode and extended flag go where a key message's lparam has them
+        let extended = if scancode.0 & 0xFF00 == 0xE000 { 1 << 24 } else { 0 };
+        let lparam = ((scancode.0 & 0xFF) << 16) | extended;
+
+        let mut buffer = [0u16; 64];
+        let len = unsafe { GetKeyNameTextW(lparam as LONG, buffer.as_mut_ptr(), buffer.len() as c_int) };
+
+        if len <= 0 {
+            return None;
+        }
+
+        return Some(String::from_utf16_lossy(&buffer[..len as usize]));
+    }
+
+    /// The cursor is clipped to the client area as it is now, Window clips it again when
+    /// the window moves or is resized
+    fn set_cursor_grab(&mut self, grab: bool) {
+        unsafe {
+            if !grab {
+                ClipCursor(ptr::null());
+                return;
+            }
+
+            let mut rect: RECT = std::mem::zeroed();
+            GetClientRect(self.hwnd as _, ptr::addr_of_mut!(rect));
+
+            // the clip rectangle is in screen coordinates, the corners are mapped as two points
+            MapWindowPoints(self.hwnd as _, null_mut(), ptr::addr_of_mut!(rect) as *mut POINT, 2);
+
+            if ClipCursor(ptr::addr_of!(rect)) == 0 {
+                log_warn!("Failed to clip the cursor to the window");
+            }
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        unsafe {
+            if visible {
+                RemovePropA(self.hwnd as _, CURSOR_HIDDEN_PROP.as_ptr() as _);
+            } else {
+                SetPropA(self.hwnd as _, CURSOR_HIDDEN_PROP.as_ptr() as _, 1 as HANDLE);
+            }
+
+            // the cursor is only set as it moves, so it's refreshed now if it's over the window
+            let mut pos = POINT { x: 0, y: 0 };
+            GetCursorPos(ptr::addr_of_mut!(pos));
+
+            if WindowFromPoint(pos) == self.hwnd as HWND {
+                SendMessageW(
+                    self.hwnd as _,
+                    WM_SETCURSOR,
+                    self.hwnd as WPARAM,
+                    // the hit test goes in the low word and the triggering message in the high word
+                    (HTCLIENT as u32 | (WM_MOUSEMOVE << 16)) as LPARAM,
+                );
+            }
+        }
+    }
+
+    /// destroys the window
+    fn destroy(&self) {
+        if !self.hwnd.is_null() {
+            unsafe {
+                RemovePropA(self.hwnd as _, MIN_SIZE_PROP.as_ptr() as _);
+                RemovePropA(self.hwnd as _, MAX_SIZE_PROP.as_ptr() as _);
+                RemovePropA(self.hwnd as _, MINIMIZED_PROP.as_ptr() as _);
+                RemovePropA(self.hwnd as _, MOUSE_TRACKED_PROP.as_ptr() as _);
+                RemovePropA(self.hwnd as _, CURSOR_HIDDEN_PROP.as_ptr() as _);
+                DestroyWindow(self.hwnd as _);
+            }
+        } else {
+            log_warn!("Attempted to close HWND with null value");
+        }
+    }
+}
+
+/// Window property names used to store the min and max window size
+#[cfg(target_os = "windows")]
+const MIN_SIZE_PROP: &[u8] = b"rovella_min_size\0";
+#[cfg(target_os = "windows")]
+const MAX_SIZE_PROP: &[u8] = b"rovella_max_size\0";
+/// Window property names used as flags for the minimized state, mouse leave tracking and
+/// the hidden cursor
+#[cfg(target_os = "windows")]
+const MINIMIZED_PROP: &[u8] = b"rovella_minimized\0";
+#[cfg(target_os = "windows")]
+const MOUSE_TRACKED_PROP: &[u8] = b"rovella_mouse_tracked\0";
+#[cfg(target_os = "windows")]
+const CURSOR_HIDDEN_PROP: &[u8] = b"rovella_cursor_hidden\0";
+
+/// The HID usage of a mouse, for registering for its raw input
+#[cfg(target_os = "windows")]
+const HID_USAGE_PAGE_GENERIC: USHORT = 0x01;
+#[cfg(target_os = "windows")]
+const HID_USAGE_GENERIC_MOUSE: USHORT = 0x02;
+
+/// Packs a size into a window property value
+#[cfg(target_os = "windows")]
+fn pack_size(width: u16, height: u16) -> HANDLE {
+    return ((width as usize) | ((height as usize) << 16)) as HANDLE;
+}
+
+/// Unpacks a size stored with pack_size, None if the property isn't set
+#[cfg(target_os = "windows")]
+unsafe fn get_size_prop(hwnd: *mut HWND__, prop: &[u8]) -> Option<POINT> {
+    let val = GetPropA(hwnd, prop.as_ptr() as _) as usize;
+
+    if val == 0 {
+        return None;
+    }
+
+    return Some(POINT {
+        x: (val & 0xffff) as i32,
+        y: (val >> 16) as i32,
+    });
+}
+
+/// Converts a string to a nul terminated UTF-16 string for the wide win32 functions
+#[cfg(target_os = "windows")]
+fn to_wide(text: &str) -> Vec<u16> {
+    return OsStr::new(text).encode_wide().chain(Some(0)).collect();
+}
+
+/// ImmGetCompositionStringW indexes, see imm.h
+#[cfg(target_os = "windows")]
+const GCS_COMPSTR: DWORD = 0x0008;
+#[cfg(target_os = "windows")]
+const GCS_CURSORPOS: DWORD = 0x0080;
+
+// winapi 0.3 does not bind ImmGetCompositionStringW, so it is declared here
+#[cfg(target_os = "windows")]
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmGetCompositionStringW(himc: HIMC, index: DWORD, buffer: LPVOID, len: DWORD) -> c_long;
+}
+
+#[cfg(target_os = "windows")]
+thread_local! {
+    // characters outside the BMP arrive as two WM_CHAR messages, this holds the first half
+    static HIGH_SURROGATE: Cell<Option<u16>> = const { Cell::new(None) };
+    // maps the times of the window messages to Instants
+    static MESSAGE_CLOCK: RefCell<EventClock> = RefCell::new(EventClock::default());
+}
+
+/// Creates an event stamped with the time of the input message being handled,
+/// only meaningful for posted messages as sent ones don't change the message time
+#[cfg(target_os = "windows")]
+unsafe fn input_event(e_type: EventType) -> Event {
+    let time = MESSAGE_CLOCK.with(|clock| clock.borrow_mut().instant_of(GetMessageTime() as u32));
+    return Event::at(e_type, time);
+}
+
+/// Gets the key of a key message, keypad enter is only told apart by its extended flag
+#[cfg(target_os = "windows")]
+fn message_key(wparam: WPARAM, lparam: LPARAM) -> Key {
+    return match Key::from(wparam as u32) {
+        Key::Enter if lparam & (1 << 24) != 0 => Key::NumpadEnter,
+        key => key,
+    };
+}
+
+/// Gets the scancode from bits 16 to 24 of a key message's lparam, bit 24 is the extended flag
+#[cfg(target_os = "windows")]
+fn message_scancode(lparam: LPARAM) -> ScanCode {
+    let extended = if lparam & (1 << 24) != 0 { 0xE000 } else { 0 };
+    return ScanCode(((lparam >> 16) & 0xFF) as u32 | extended);
+}
+
+/// Reads the text currently being composed by the input method and the cursor position in it
+#[cfg(target_os = "windows")]
+unsafe fn composition_string(hwnd: *mut HWND__) -> Option<(String, usize)> {
+    let imc = ImmGetContext(hwnd);
+
+    if imc.is_null() {
+        return None;
+    }
+
+    // the length is returned in bytes
+    let len = ImmGetCompositionStringW(imc, GCS_COMPSTR, null_mut(), 0);
+    let mut buffer = vec![0u16; len.max(0) as usize / 2];
+
+    if !buffer.is_empty() {
+        ImmGetCompositionStringW(imc, GCS_COMPSTR, buffer.as_mut_ptr() as _, len as DWORD);
+    }
+
+    // the cursor is counted in UTF-16 units, which has to become a count of chars
+    let cursor_units = ImmGetCompositionStringW(imc, GCS_CURSORPOS, null_mut(), 0).max(0) as usize;
+    ImmReleaseContext(hwnd, imc);
+
+    let text = String::from_utf16_lossy(&buffer);
+    let cursor = char::decode_utf16(buffer.iter().copied().take(cursor_units)).count();
+
+    return Some((text, cursor));
+}
+
+/// Reads the modifier state for the message currently being processed
+#[cfg(target_os = "windows")]
+unsafe fn current_modifiers() -> Modifiers {
+    // the high bit is set while a key is held, the low bit while a lock key is toggled on
+    let held = |vk: c_int| GetKeyState(vk) as u16 & 0x8000 != 0;
+    let toggled = |vk: c_int| GetKeyState(vk) & 1 != 0;
+
+    return Modifiers {
+        shift: held(VK_SHIFT),
+        ctrl: held(VK_CONTROL),
+        alt: held(VK_MENU),
+        super_key: held(VK_LWIN) || held(VK_RWIN),
+        caps_lock: toggled(VK_CAPITAL),
+        num_lock: toggled(VK_NUMLOCK),
+    };
+}
+
+/// Holds an event for the update of the window it's for
+#[cfg(target_os = "windows")]
+unsafe fn add_event_to_que(event: Event, hwnd: *mut HWND__) {
+    let ev_que: *mut EventDeque = GetWindowLongPtrA(hwnd, GWLP_USERDATA) as _;
+
+    if !(ev_que.is_null()) {
+        (*ev_que).push_back(event);
+    } else {
+        let err = GetLastError();
+        if err != 0 {
+            log_error!(
+                "window proc couldn't retrieve event queue with error code, {} ",
+                err
+            );
+        }
+    }
+}
+
+/// the callback for window event management used in the win32 api
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn window_proc(
+    hwnd: *mut HWND__,
+    msg: u32,
+    wparam: usize,
+    lparam: isize,
+) -> LRESULT {
+    if msg == WM_CREATE {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    match msg {
+        WM_ERASEBKGND => {
+            return 1;
+        }
+        WM_CLOSE => {
+            add_event_to_que(
+                Event::new(EventType::WinClose),
+                hwnd,
+            );
+            return 0;
+        }
+        WM_GETMINMAXINFO => {
+            let info = lparam as *mut MINMAXINFO;
+
+            if let Some(min) = get_size_prop(hwnd, MIN_SIZE_PROP) {
+                (*info).ptMinTrackSize = min;
+            }
+
+            if let Some(max) = get_size_prop(hwnd, MAX_SIZE_PROP) {
+                (*info).ptMaxTrackSize = max;
+            }
+
+            return 0;
+        }
+        WM_SIZE => {
+            // minimizing doesn't unmap the window like on X11, so it's reported as hiding it
+            let was_minimized = !GetPropA(hwnd, MINIMIZED_PROP.as_ptr() as _).is_null();
+
+            if wparam == SIZE_MINIMIZED {
+                if !was_minimized {
+                    SetPropA(hwnd, MINIMIZED_PROP.as_ptr() as _, 1 as HANDLE);
+                    add_event_to_que(Event::new(EventType::WinHide), hwnd);
+                }
+            } else {
+                if was_minimized {
+                    RemovePropA(hwnd, MINIMIZED_PROP.as_ptr() as _);
+                    add_event_to_que(Event::new(EventType::WinShow), hwnd);
+                }
+
+                add_event_to_que(
+                    Event::new(EventType::WinResize {
+                        width: LOWORD(lparam as u32),
+                        height: HIWORD(lparam as u32),
+                    }),
+                    hwnd,
+                );
+            }
+        }
+        WM_SHOWWINDOW => {
+            if wparam != 0 {
+                add_event_to_que(Event::new(EventType::WinShow), hwnd);
+            } else {
+                add_event_to_que(Event::new(EventType::WinHide), hwnd);
+            }
+        }
+        WM_PAINT => {
+            // DefWindowProc validates the window so this isn't sent again
+            add_event_to_que(Event::new(EventType::WinExpose), hwnd);
+        }
+        WM_SETFOCUS => {
+            add_event_to_que(Event::new(EventType::WinFocusGained), hwnd);
+        }
+        WM_KILLFOCUS => {
+            add_event_to_que(Event::new(EventType::WinFocusLost), hwnd);
+        }
+        WM_MOUSELEAVE => {
+            RemovePropA(hwnd, MOUSE_TRACKED_PROP.as_ptr() as _);
+            add_event_to_que(Event::new(EventType::MouseLeave), hwnd);
+        }
+        WM_MOVE => {
+            add_event_to_que(
+                Event::new(EventType::WinMove {
+                    x: GET_X_LPARAM(lparam) as i16,
+                    y: GET_Y_LPARAM(lparam) as i16,
+                }),
+                hwnd,
+            );
+        }
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            add_event_to_que(
+                input_event(EventType::KeyDown {
+                    key: message_key(wparam, lparam),
+                    scancode: message_scancode(lparam),
+                    modifiers: current_modifiers(),
+                    // bit 30 is the previous key state, set when this is an auto repeat
+                    is_repeat: lparam & (1 << 30) != 0,
+                }),
+                hwnd,
+            );
+        }
+        WM_KEYUP | WM_SYSKEYUP => {
+            add_event_to_que(
+                input_event(EventType::KeyUp {
+                    key: message_key(wparam, lparam),
+                    scancode: message_scancode(lparam),
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_CHAR => {
+            let unit = wparam as u16;
+
+            let text = if (0xD800..0xDC00).contains(&unit) {
+                HIGH_SURROGATE.with(|high| high.set(Some(unit)));
+                None
+            } else {
+                let high = HIGH_SURROGATE.with(|high| high.take());
+                let units: Vec<u16> = high.into_iter().chain(Some(unit)).collect();
+
+                // keys like backspace and enter type control characters, those are left to KeyDown
+                let text: String = char::decode_utf16(units)
+                    .filter_map(|c| c.ok())
+                    .filter(|c| !c.is_control())
+                    .collect();
+
+                if text.is_empty() {
+                    None
+                } else {
+                    Some(text)
+                }
+            };
+
+            if let Some(text) = text {
+                add_event_to_que(input_event(EventType::TextInput { text }), hwnd);
+            }
+
+            return 0;
+        }
+        WM_IME_STARTCOMPOSITION => {
+            add_event_to_que(Event::new(EventType::TextCompositionStart), hwnd);
+        }
+        // the result string is left to DefWindowProc, which sends it on as WM_CHAR messages
+        WM_IME_COMPOSITION if lparam as DWORD & GCS_COMPSTR != 0 => {
+            if let Some((text, cursor)) = composition_string(hwnd) {
+                add_event_to_que(
+                    Event::new(EventType::TextComposition { text, cursor }),
+                    hwnd,
+                );
+            }
+        }
+        WM_IME_ENDCOMPOSITION => {
+            add_event_to_que(Event::new(EventType::TextCompositionEnd), hwnd);
+        }
+        WM_MOUSEMOVE => {
+            // windows only reports the cursor leaving when asked to, once per entry
+            if GetPropA(hwnd, MOUSE_TRACKED_PROP.as_ptr() as _).is_null() {
+                let mut track = TRACKMOUSEEVENT {
+                    cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                    dwFlags: TME_LEAVE,
+                    hwndTrack: hwnd,
+                    dwHoverTime: 0,
+                };
+
+                TrackMouseEvent(ptr::addr_of_mut!(track));
+                SetPropA(hwnd, MOUSE_TRACKED_PROP.as_ptr() as _, 1 as HANDLE);
+                add_event_to_que(input_event(EventType::MouseEnter), hwnd);
+            }
+
+            add_event_to_que(
+                input_event(EventType::MouseMove {
+                    x: GET_X_LPARAM(lparam) as i16,
+                    y: GET_Y_LPARAM(lparam) as i16,
+                }),
+                hwnd,
+            );
+        }
+        WM_INPUT => {
+            let mut input: RAWINPUT = std::mem::zeroed();
+            let mut size = std::mem::size_of::<RAWINPUT>() as UINT;
+
+            let read = GetRawInputData(
+                lparam as HRAWINPUT,
+                RID_INPUT,
+                ptr::addr_of_mut!(input) as _,
+                ptr::addr_of_mut!(size),
+                std::mem::size_of::<RAWINPUTHEADER>() as UINT,
+            );
+
+            // tablets and remote desktop report absolute positions, which aren't motion
+            if read != UINT::MAX && input.header.dwType == RIM_TYPEMOUSE {
+                let mouse = input.data.mouse();
+
+                if mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0 && (mouse.lLastX != 0 || mouse.lLastY != 0) {
+                    add_event_to_que(
+                        input_event(EventType::MouseRawMotion {
+                            delta_x: mouse.lLastX as f32,
+                            delta_y: mouse.lLastY as f32,
+                        }),
+                        hwnd,
+                    );
+                }
+            }
+        }
+        // only the client area's cursor is hidden, the borders keep their resize cursors
+        WM_SETCURSOR
+            if LOWORD(lparam as DWORD) as LRESULT == HTCLIENT
+                && !GetPropA(hwnd, CURSOR_HIDDEN_PROP.as_ptr() as _).is_null() =>
+        {
+            SetCursor(null_mut());
+            return TRUE as LRESULT;
+        }
+        WM_MOUSEWHEEL => {
+            // high resolution wheels report fractions of WHEEL_DELTA
+            let z_delta = GET_WHEEL_DELTA_WPARAM(wparam);
+            if z_delta != 0 {
+                add_event_to_que(
+                    input_event(EventType::MouseWheel {
+                        delta_x: 0.0,
+                        delta_y: z_delta as f32 / WHEEL_DELTA as f32,
+                    }),
+                    hwnd,
+                );
+            }
+        }
+        WM_MOUSEHWHEEL => {
+            let z_delta = GET_WHEEL_DELTA_WPARAM(wparam);
+            if z_delta != 0 {
+                add_event_to_que(
+                    input_event(EventType::MouseWheel {
+                        delta_x: z_delta as f32 / WHEEL_DELTA as f32,
+                        delta_y: 0.0,
+                    }),
+                    hwnd,
+                );
+            }
+        }
+        WM_LBUTTONDOWN => {
+            add_event_to_que(
+                input_event(EventType::MouseLeftBtnDown {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_MBUTTONDOWN => {
+            add_event_to_que(
+                input_event(EventType::MouseMidBtnDown {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_RBUTTONDOWN => {
+            add_event_to_que(
+                input_event(EventType::MouseRightBtnDown {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_LBUTTONUP => {
+            add_event_to_que(
+                input_event(EventType::MouseLeftBtnUp {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_MBUTTONUP => {
+            add_event_to_que(
+                input_event(EventType::MouseMidBtnUp {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        WM_RBUTTONUP => {
+            add_event_to_que(
+                input_event(EventType::MouseRightBtnUp {
+                    modifiers: current_modifiers(),
+                }),
+                hwnd,
+            );
+        }
+        _ => {}
+    }
+
+    return DefWindowProcW(hwnd, msg, wparam, lparam);
+}
+
+#[cfg(target_os = "windows")]
+impl From<u32> for Key {
+    fn from(val: u32) -> Self {
+        return match val as u16 {
+            0x03 => Key::Cancel,
+            0x08 => Key::Backspace,
+            0x0C => Key::Clear,
+            0x0D => Key::Enter,
+            0x09 => Key::Tab,
+            0x10 => Key::Shift,
+            0x11 => Key::Control,
+            0x12 => Key::Alt,
+            0x13 => Key::Pause,
+            0x14 => Key::Capital,
+            0x15 => Key::Kana,
+            0x19 => Key::Kanji,
+            0x1B => Key::Escape,
+            0x1C => Key::Convert,
+            0x1D => Key::NonConvert,
+            0x1E => Key::Accept,
+            0x1F => Key::ModeChange,
+            0x20 => Key::Space,
+            0x21 => Key::Prior,
+            0x22 => Key::Next,
+            0x23 => Key::End,
+            0x24 => Key::Home,
+            0x25 => Key::Left,
+            0x26 => Key::Up,
+            0x27 => Key::Right,
+            0x28 => Key::Down,
+            0x29 => Key::Select,
+            0x2A => Key::Print,
+            0x2B => Key::Execute,
+            0x2C => Key::Snapshot,
+            0x2D => Key::Insert,
+            0x2E => Key::Delete,
+            0x2F => Key::Help,
+            0x41 => Key::A,
+            0x42 => Key::B,
+            0x43 => Key::C,
+            0x44 => Key::D,
+            0x45 => Key::E,
+            0x46 => Key::F,
+            0x47 => Key::G,
+            0x48 => Key::H,
+            0x49 => Key::I,
+            0x4A => Key::J,
+            0x4B => Key::K,
+            0x4C => Key::L,
+            0x4D => Key::M,
+            0x4E => Key::N,
+            0x4F => Key::O,
+            0x50 => Key::P,
+            0x51 => Key::Q,
+            0x52 => Key::R,
+            0x53 => Key::S,
+            0x54 => Key::T,
             0x55 => Key::U,
             0x56 => Key::V,
             0x57 => Key::W,
@@ -581,6 +1901,24 @@ impl From<u32> for Key {
             0xA3 => Key::RControl,
             0xA4 => Key::LAlt,
             0xA5 => Key::RAlt,
+            0xA6 => Key::BrowserBack,
+            0xA7 => Key::BrowserForward,
+            0xA8 => Key::BrowserRefresh,
+            0xA9 => Key::BrowserStop,
+            0xAA => Key::BrowserSearch,
+            0xAB => Key::BrowserFavorites,
+            0xAC => Key::BrowserHome,
+            0xAD => Key::VolumeMute,
+            0xAE => Key::VolumeDown,
+            0xAF => Key::VolumeUp,
+            0xB0 => Key::MediaNext,
+            0xB1 => Key::MediaPrev,
+            0xB2 => Key::MediaStop,
+            0xB3 => Key::MediaPlayPause,
+            0xB4 => Key::LaunchMail,
+            0xB5 => Key::LaunchMediaSelect,
+            0xB6 => Key::LaunchApp1,
+            0xB7 => Key::LaunchApp2,
             0xBA => Key::Semicolon,
             0xBB => Key::Plus,
             0xBC => Key::Comma,
@@ -588,383 +1926,3524 @@ impl From<u32> for Key {
             0xBE => Key::Period,
             0xBF => Key::Slash,
             0xC0 => Key::Grave,
+            0xDB => Key::LBracket,
+            0xDC => Key::Backslash,
+            0xDD => Key::RBracket,
+            0xDE => Key::Quote,
+            0xE2 => Key::NonUsBackslash,
+            _ => Key::None,
+        };
+    }
+}
+
+/// The keys by keysym, the keypad's are also matched by the keysyms they have with num
+/// lock off as the unshifted keysym is reported, see keysymdef.h and XF86keysym.h
+#[cfg(target_os = "linux")]
+impl From<u32> for Key {
+    fn from(val: u32) -> Self {
+        return match val {
+            x11::keysym::XK_BackSpace => Key::Backspace,
+            x11::keysym::XK_Return => Key::Enter,
+            x11::keysym::XK_Tab | x11::keysym::XK_ISO_Left_Tab => Key::Tab,
+            x11::keysym::XK_Pause | x11::keysym::XK_Break => Key::Pause,
+            x11::keysym::XK_Caps_Lock => Key::Capital,
+            x11::keysym::XK_Escape => Key::Escape,
+            x11::keysym::XK_Henkan => Key::Convert,
+            x11::keysym::XK_Muhenkan => Key::NonConvert,
+            x11::keysym::XK_Mode_switch => Key::ModeChange,
+            x11::keysym::XK_space => Key::Space,
+            x11::keysym::XK_Prior => Key::Prior,
+            x11::keysym::XK_Next => Key::Next,
+            x11::keysym::XK_End => Key::End,
+            x11::keysym::XK_Home => Key::Home,
+            x11::keysym::XK_Left => Key::Left,
+            x11::keysym::XK_Up => Key::Up,
+            x11::keysym::XK_Right => Key::Right,
+            x11::keysym::XK_Down => Key::Down,
+            x11::keysym::XK_Select => Key::Select,
+            x11::keysym::XK_Execute => Key::Execute,
+            x11::keysym::XK_Print | x11::keysym::XK_Sys_Req => Key::Snapshot,
+            x11::keysym::XK_Insert => Key::Insert,
+            x11::keysym::XK_Delete => Key::Delete,
+            x11::keysym::XK_Help => Key::Help,
+            x11::keysym::XK_Cancel => Key::Cancel,
+            x11::keysym::XK_Clear => Key::Clear,
+            x11::keysym::XK_Hiragana_Katakana => Key::Kana,
+            x11::keysym::XK_Kanji => Key::Kanji,
+            x11::keysym::XK_0 => Key::N0,
+            x11::keysym::XK_1 => Key::N1,
+            x11::keysym::XK_2 => Key::N2,
+            x11::keysym::XK_3 => Key::N3,
+            x11::keysym::XK_4 => Key::N4,
+            x11::keysym::XK_5 => Key::N5,
+            x11::keysym::XK_6 => Key::N6,
+            x11::keysym::XK_7 => Key::N7,
+            x11::keysym::XK_8 => Key::N8,
+            x11::keysym::XK_9 => Key::N9,
+            x11::keysym::XK_Super_L | x11::keysym::XK_Meta_L => Key::Lwin,
+            x11::keysym::XK_Super_R | x11::keysym::XK_Meta_R => Key::Rwin,
+            x11::keysym::XK_Menu => Key::Apps,
+            x11::keysym::XK_KP_0 | x11::keysym::XK_KP_Insert => Key::Numpad0,
+            x11::keysym::XK_KP_1 | x11::keysym::XK_KP_End => Key::Numpad1,
+            x11::keysym::XK_KP_2 | x11::keysym::XK_KP_Down => Key::Numpad2,
+            x11::keysym::XK_KP_3 | x11::keysym::XK_KP_Next => Key::Numpad3,
+            x11::keysym::XK_KP_4 | x11::keysym::XK_KP_Left => Key::Numpad4,
+            x11::keysym::XK_KP_5 | x11::keysym::XK_KP_Begin => Key::Numpad5,
+            x11::keysym::XK_KP_6 | x11::keysym::XK_KP_Right => Key::Numpad6,
+            x11::keysym::XK_KP_7 | x11::keysym::XK_KP_Home => Key::Numpad7,
+            x11::keysym::XK_KP_8 | x11::keysym::XK_KP_Up => Key::Numpad8,
+            x11::keysym::XK_KP_9 | x11::keysym::XK_KP_Prior => Key::Numpad9,
+            x11::keysym::XK_KP_Multiply => Key::Multiply,
+            x11::keysym::XK_KP_Add => Key::Add,
+            x11::keysym::XK_KP_Separator => Key::Separator,
+            x11::keysym::XK_KP_Subtract => Key::Subtract,
+            x11::keysym::XK_KP_Decimal | x11::keysym::XK_KP_Delete => Key::Decimal,
+            x11::keysym::XK_KP_Divide => Key::Divide,
+            x11::keysym::XK_KP_Enter => Key::NumpadEnter,
+            x11::keysym::XK_KP_Equal => Key::NumpadEqual,
+            x11::keysym::XK_F1 => Key::F1,
+            x11::keysym::XK_F2 => Key::F2,
+            x11::keysym::XK_F3 => Key::F3,
+            x11::keysym::XK_F4 => Key::F4,
+            x11::keysym::XK_F5 => Key::F5,
+            x11::keysym::XK_F6 => Key::F6,
+            x11::keysym::XK_F7 => Key::F7,
+            x11::keysym::XK_F8 => Key::F8,
+            x11::keysym::XK_F9 => Key::F9,
+            x11::keysym::XK_F10 => Key::F10,
+            x11::keysym::XK_F11 => Key::F11,
+            x11::keysym::XK_F12 => Key::F12,
+            x11::keysym::XK_F13 => Key::F13,
+            x11::keysym::XK_F14 => Key::F14,
+            x11::keysym::XK_F15 => Key::F15,
+            x11::keysym::XK_F16 => Key::F16,
+            x11::keysym::XK_F17 => Key::F17,
+            x11::keysym::XK_F18 => Key::F18,
+            x11::keysym::XK_F19 => Key::F19,
+            x11::keysym::XK_F20 => Key::F20,
+            x11::keysym::XK_F21 => Key::F21,
+            x11::keysym::XK_F22 => Key::F22,
+            x11::keysym::XK_F23 => Key::F23,
+            x11::keysym::XK_F24 => Key::F24,
+            x11::keysym::XK_Num_Lock => Key::Numlock,
+            x11::keysym::XK_Scroll_Lock => Key::ScrollLock,
+            x11::keysym::XK_Shift_L => Key::LShift,
+            x11::keysym::XK_Shift_R => Key::RShift,
+            x11::keysym::XK_Control_L => Key::LControl,
+            x11::keysym::XK_Control_R => Key::RControl,
+            x11::keysym::XK_Alt_L => Key::LAlt,
+            x11::keysym::XK_Alt_R | x11::keysym::XK_ISO_Level3_Shift => Key::RAlt,
+            x11::keysym::XK_semicolon => Key::Semicolon,
+            x11::keysym::XK_plus | x11::keysym::XK_equal => Key::Plus,
+            x11::keysym::XK_comma => Key::Comma,
+            x11::keysym::XK_minus => Key::Minus,
+            x11::keysym::XK_period => Key::Period,
+            x11::keysym::XK_slash => Key::Slash,
+            x11::keysym::XK_grave => Key::Grave,
+            x11::keysym::XK_bracketleft => Key::LBracket,
+            x11::keysym::XK_bracketright => Key::RBracket,
+            x11::keysym::XK_backslash => Key::Backslash,
+            x11::keysym::XK_apostrophe => Key::Quote,
+            x11::keysym::XK_less => Key::NonUsBackslash,
+            x11::keysym::XF86XK_Sleep => Key::Sleep,
+            x11::keysym::XF86XK_AudioMute => Key::VolumeMute,
+            x11::keysym::XF86XK_AudioLowerVolume => Key::VolumeDown,
+            x11::keysym::XF86XK_AudioRaiseVolume => Key::VolumeUp,
+            x11::keysym::XF86XK_AudioNext => Key::MediaNext,
+            x11::keysym::XF86XK_AudioPrev => Key::MediaPrev,
+            x11::keysym::XF86XK_AudioStop => Key::MediaStop,
+            x11::keysym::XF86XK_AudioPlay | x11::keysym::XF86XK_AudioPause => Key::MediaPlayPause,
+            x11::keysym::XF86XK_Back => Key::BrowserBack,
+            x11::keysym::XF86XK_Forward => Key::BrowserForward,
+            x11::keysym::XF86XK_Reload => Key::BrowserRefresh,
+            x11::keysym::XF86XK_Stop => Key::BrowserStop,
+            x11::keysym::XF86XK_Search => Key::BrowserSearch,
+            x11::keysym::XF86XK_Favorites => Key::BrowserFavorites,
+            x11::keysym::XF86XK_HomePage => Key::BrowserHome,
+            x11::keysym::XF86XK_Mail => Key::LaunchMail,
+            x11::keysym::XF86XK_AudioMedia => Key::LaunchMediaSelect,
+            x11::keysym::XF86XK_MyComputer => Key::LaunchApp1,
+            x11::keysym::XF86XK_Calculator => Key::LaunchApp2,
+            x11::keysym::XK_a | x11::keysym::XK_A => Key::A,
+            x11::keysym::XK_b | x11::keysym::XK_B => Key::B,
+            x11::keysym::XK_c | x11::keysym::XK_C => Key::C,
+            x11::keysym::XK_d | x11::keysym::XK_D => Key::D,
+            x11::keysym::XK_e | x11::keysym::XK_E => Key::E,
+            x11::keysym::XK_f | x11::keysym::XK_F => Key::F,
+            x11::keysym::XK_g | x11::keysym::XK_G => Key::G,
+            x11::keysym::XK_h | x11::keysym::XK_H => Key::H,
+            x11::keysym::XK_i | x11::keysym::XK_I => Key::I,
+            x11::keysym::XK_j | x11::keysym::XK_J => Key::J,
+            x11::keysym::XK_k | x11::keysym::XK_K => Key::K,
+            x11::keysym::XK_l | x11::keysym::XK_L => Key::L,
+            x11::keysym::XK_m | x11::keysym::XK_M => Key::M,
+            x11::keysym::XK_n | x11::keysym::XK_N => Key::N,
+            x11::keysym::XK_o | x11::keysym::XK_O => Key::O,
+            x11::keysym::XK_p | x11::keysym::XK_P => Key::P,
+            x11::keysym::XK_q | x11::keysym::XK_Q => Key::Q,
+            x11::keysym::XK_r | x11::keysym::XK_R => Key::R,
+            x11::keysym::XK_s | x11::keysym::XK_S => Key::S,
+            x11::keysym::XK_t | x11::keysym::XK_T => Key::T,
+            x11::keysym::XK_u | x11::keysym::XK_U => Key::U,
+            x11::keysym::XK_v | x11::keysym::XK_V => Key::V,
+            x11::keysym::XK_w | x11::keysym::XK_W => Key::W,
+            x11::keysym::XK_x | x11::keysym::XK_X => Key::X,
+            x11::keysym::XK_y | x11::keysym::XK_Y => Key::Y,
+            x11::keysym::XK_z | x11::keysym::XK_Z => Key::Z,
             _ => Key::None,
         };
     }
 }
 
+/// The keys of a US keyboard by set 1 scancode, extended keys have 0xE000 added
+#[cfg(target_os = "windows")]
+impl From<ScanCode> for Key {
+    fn from(scancode: ScanCode) -> Self {
+        return match scancode.0 {
+            0x01 => Key::Escape,
+            0x02 => Key::N1,
+            0x03 => Key::N2,
+            0x04 => Key::N3,
+            0x05 => Key::N4,
+            0x06 => Key::N5,
+            0x07 => Key::N6,
+            0x08 => Key::N7,
+            0x09 => Key::N8,
+            0x0A => Key::N9,
+            0x0B => Key::N0,
+            0x0C => Key::Minus,
+            0x0D => Key::Plus,
+            0x0E => Key::Backspace,
+            0x0F => Key::Tab,
+            0x10 => Key::Q,
+            0x11 => Key::W,
+            0x12 => Key::E,
+            0x13 => Key::R,
+            0x14 => Key::T,
+            0x15 => Key::Y,
+            0x16 => Key::U,
+            0x17 => Key::I,
+            0x18 => Key::O,
+            0x19 => Key::P,
+            0x1A => Key::LBracket,
+            0x1B => Key::RBracket,
+            0x1C => Key::Enter,
+            0x1D => Key::LControl,
+            0x1E => Key::A,
+            0x1F => Key::S,
+            0x20 => Key::D,
+            0x21 => Key::F,
+            0x22 => Key::G,
+            0x23 => Key::H,
+            0x24 => Key::J,
+            0x25 => Key::K,
+            0x26 => Key::L,
+            0x27 => Key::Semicolon,
+            0x28 => Key::Quote,
+            0x29 => Key::Grave,
+            0x2A => Key::LShift,
+            0x2B => Key::Backslash,
+            0x2C => Key::Z,
+            0x2D => Key::X,
+            0x2E => Key::C,
+            0x2F => Key::V,
+            0x30 => Key::B,
+            0x31 => Key::N,
+            0x32 => Key::M,
+            0x33 => Key::Comma,
+            0x34 => Key::Period,
+            0x35 => Key::Slash,
+            0x36 => Key::RShift,
+            0x37 => Key::Multiply,
+            0x38 => Key::LAlt,
+            0x39 => Key::Space,
+            0x3A => Key::Capital,
+            0x3B => Key::F1,
+            0x3C => Key::F2,
+            0x3D => Key::F3,
+            0x3E => Key::F4,
+            0x3F => Key::F5,
+            0x40 => Key::F6,
+            0x41 => Key::F7,
+            0x42 => Key::F8,
+            0x43 => Key::F9,
+            0x44 => Key::F10,
+            // pause sends num lock's scancode without the extended flag
+            0x45 => Key::Pause,
+            0x46 => Key::ScrollLock,
+            0x47 => Key::Numpad7,
+            0x48 => Key::Numpad8,
+            0x49 => Key::Numpad9,
+            0x4A => Key::Subtract,
+            0x4B => Key::Numpad4,
+            0x4C => Key::Numpad5,
+            0x4D => Key::Numpad6,
+            0x4E => Key::Add,
+            0x4F => Key::Numpad1,
+            0x50 => Key::Numpad2,
+            0x51 => Key::Numpad3,
+            0x52 => Key::Numpad0,
+            0x53 => Key::Decimal,
+            0x56 => Key::NonUsBackslash,
+            0x57 => Key::F11,
+            0x58 => Key::F12,
+            0x59 => Key::NumpadEqual,
+            0x64 => Key::F13,
+            0x65 => Key::F14,
+            0x66 => Key::F15,
+            0x67 => Key::F16,
+            0x68 => Key::F17,
+            0x69 => Key::F18,
+            0x6A => Key::F19,
+            0x6B => Key::F20,
+            0x6C => Key::F21,
+            0x6D => Key::F22,
+            0x6E => Key::F23,
+            0x70 => Key::Kana,
+            0x76 => Key::F24,
+            0x79 => Key::Convert,
+            0x7B => Key::NonConvert,
+            0x7E => Key::Separator,
+            0xE010 => Key::MediaPrev,
+            0xE019 => Key::MediaNext,
+            0xE01C => Key::NumpadEnter,
+            0xE01D => Key::RControl,
+            0xE020 => Key::VolumeMute,
+            0xE021 => Key::LaunchApp2,
+            0xE022 => Key::MediaPlayPause,
+            0xE024 => Key::MediaStop,
+            0xE02E => Key::VolumeDown,
+            0xE030 => Key::VolumeUp,
+            0xE032 => Key::BrowserHome,
+            0xE035 => Key::Divide,
+            0xE037 => Key::Snapshot,
+            0xE038 => Key::RAlt,
+            0xE045 => Key::Numlock,
+            0xE047 => Key::Home,
+            0xE048 => Key::Up,
+            0xE049 => Key::Prior,
+            0xE04B => Key::Left,
+            0xE04D => Key::Right,
+            0xE04F => Key::End,
+            0xE050 => Key::Down,
+            0xE051 => Key::Next,
+            0xE052 => Key::Insert,
+            0xE053 => Key::Delete,
+            0xE05B => Key::Lwin,
+            0xE05C => Key::Rwin,
+            0xE05D => Key::Apps,
+            0xE05F => Key::Sleep,
+            0xE065 => Key::BrowserSearch,
+            0xE066 => Key::BrowserFavorites,
+            0xE067 => Key::BrowserRefresh,
+            0xE068 => Key::BrowserStop,
+            0xE069 => Key::BrowserForward,
+            0xE06A => Key::BrowserBack,
+            0xE06B => Key::LaunchApp1,
+            0xE06C => Key::LaunchMail,
+            0xE06D => Key::LaunchMediaSelect,
+            _ => Key::None,
+        };
+    }
+}
+
+/// The keys of a US keyboard by evdev code, see linux/input-event-codes.h
+#[cfg(target_os = "linux")]
+impl From<ScanCode> for Key {
+    fn from(scancode: ScanCode) -> Self {
+        return match scancode.0 {
+            1 => Key::Escape,
+            2 => Key::N1,
+            3 => Key::N2,
+            4 => Key::N3,
+            5 => Key::N4,
+            6 => Key::N5,
+            7 => Key::N6,
+            8 => Key::N7,
+            9 => Key::N8,
+            10 => Key::N9,
+            11 => Key::N0,
+            12 => Key::Minus,
+            13 => Key::Plus,
+            14 => Key::Backspace,
+            15 => Key::Tab,
+            16 => Key::Q,
+            17 => Key::W,
+            18 => Key::E,
+            19 => Key::R,
+            20 => Key::T,
+            21 => Key::Y,
+            22 => Key::U,
+            23 => Key::I,
+            24 => Key::O,
+            25 => Key::P,
+            26 => Key::LBracket,
+            27 => Key::RBracket,
+            28 => Key::Enter,
+            29 => Key::LControl,
+            30 => Key::A,
+            31 => Key::S,
+            32 => Key::D,
+            33 => Key::F,
+            34 => Key::G,
+            35 => Key::H,
+            36 => Key::J,
+            37 => Key::K,
+            38 => Key::L,
+            39 => Key::Semicolon,
+            40 => Key::Quote,
+            41 => Key::Grave,
+            42 => Key::LShift,
+            43 => Key::Backslash,
+            44 => Key::Z,
+            45 => Key::X,
+            46 => Key::C,
+            47 => Key::V,
+            48 => Key::B,
+            49 => Key::N,
+            50 => Key::M,
+            51 => Key::Comma,
+            52 => Key::Period,
+            53 => Key::Slash,
+            54 => Key::RShift,
+            55 => Key::Multiply,
+            56 => Key::LAlt,
+            57 => Key::Space,
+            58 => Key::Capital,
+            59 => Key::F1,
+            60 => Key::F2,
+            61 => Key::F3,
+            62 => Key::F4,
+            63 => Key::F5,
+            64 => Key::F6,
+            65 => Key::F7,
+            66 => Key::F8,
+            67 => Key::F9,
+            68 => Key::F10,
+            69 => Key::Numlock,
+            70 => Key::ScrollLock,
+            71 => Key::Numpad7,
+            72 => Key::Numpad8,
+            73 => Key::Numpad9,
+            74 => Key::Subtract,
+            75 => Key::Numpad4,
+            76 => Key::Numpad5,
+            77 => Key::Numpad6,
+            78 => Key::Add,
+            79 => Key::Numpad1,
+            80 => Key::Numpad2,
+            81 => Key::Numpad3,
+            82 => Key::Numpad0,
+            83 => Key::Decimal,
+            86 => Key::NonUsBackslash,
+            87 => Key::F11,
+            88 => Key::F12,
+            92 => Key::Convert,
+            93 => Key::Kana,
+            94 => Key::NonConvert,
+            96 => Key::NumpadEnter,
+            97 => Key::RControl,
+            98 => Key::Divide,
+            99 => Key::Snapshot,
+            100 => Key::RAlt,
+            102 => Key::Home,
+            103 => Key::Up,
+            104 => Key::Prior,
+            105 => Key::Left,
+            106 => Key::Right,
+            107 => Key::End,
+            108 => Key::Down,
+            109 => Key::Next,
+            110 => Key::Insert,
+            111 => Key::Delete,
+            113 => Key::VolumeMute,
+            114 => Key::VolumeDown,
+            115 => Key::VolumeUp,
+            117 => Key::NumpadEqual,
+            119 => Key::Pause,
+            121 => Key::Separator,
+            122 => Key::Kana,
+            123 => Key::Kanji,
+            125 => Key::Lwin,
+            126 => Key::Rwin,
+            127 => Key::Apps,
+            128 => Key::BrowserStop,
+            138 => Key::Help,
+            140 => Key::LaunchApp2,
+            142 => Key::Sleep,
+            155 => Key::LaunchMail,
+            156 => Key::BrowserFavorites,
+            157 => Key::LaunchApp1,
+            158 => Key::BrowserBack,
+            159 => Key::BrowserForward,
+            163 => Key::MediaNext,
+            164 => Key::MediaPlayPause,
+            165 => Key::MediaPrev,
+            166 => Key::MediaStop,
+            172 => Key::BrowserHome,
+            173 => Key::BrowserRefresh,
+            183 => Key::F13,
+            184 => Key::F14,
+            185 => Key::F15,
+            186 => Key::F16,
+            187 => Key::F17,
+            188 => Key::F18,
+            189 => Key::F19,
+            190 => Key::F20,
+            191 => Key::F21,
+            192 => Key::F22,
+            193 => Key::F23,
+            194 => Key::F24,
+            217 => Key::BrowserSearch,
+            226 => Key::LaunchMediaSelect,
+            _ => Key::None,
+        };
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn sleep(ms: u32) {
+    thread::sleep(time::Duration::from_millis(ms as u64));
+}
+
+/// An event read from the X connection that's waiting for the update of its window
+#[cfg(target_os = "linux")]
+enum PendingEvent {
+    Xcb(*mut xcb_generic_event_t),
+    // the key presses Xlib makes to deliver text committed by the input method
+    Xlib(xlib::XEvent),
+}
+
+/// The display connection and what comes with it, shared by every window created from it
+/// and closed when the last of them is dropped
+#[cfg(target_os = "linux")]
+struct XConnection {
+    display: *mut xlib::Display,
+    connection: *mut xcb_connection_t,
+    screen: *mut xcb_screen_t,
+    wm_protocols: xcb_atom_t,
+    wm_delete_win: xcb_atom_t,
+    net_wm_state: xcb_atom_t,
+    net_wm_state_fullscreen: xcb_atom_t,
+    net_wm_bypass_compositor: xcb_atom_t,
+    // the input method used for text input, null if none could be opened
+    im: xlib::XIM,
+    // the XInput extension's opcode, None if raw motion couldn't be selected
+    xi_opcode: Option<u8>,
+    // which of the windows has focus, 0 for none, raw motion is held for it
+    focus: Cell<u32>,
+    // an empty cursor for hiding it, 0 until a window first hides it
+    blank_cursor: Cell<u32>,
+    // events are read for every window at once, and held here by window until its update
+    pending: RefCell<HashMap<u32, VecDeque<PendingEvent>>>,
+    // maps the server's event times to Instants
+    clock: RefCell<EventClock>,
+}
+
+#[cfg(target_os = "linux")]
+impl XConnection {
+    /// Opens the display and sets up what all of its windows need
+    fn open() -> Result<XConnection> {
+        unsafe {
+            let display = xlib::XOpenDisplay(null());
+
+            if display.is_null() {
+                log_fatal!("Could not get display");
+                return Err(Error::NoDisplay);
+            }
+
+            // key repeats then arrive as presses without releases in between, rather than
+            // release and press pairs, so they can be told apart from new presses
+            let mut detectable_repeat: xlib::Bool = 0;
+            xlib::XkbSetDetectableAutoRepeat(display, 1, ptr::addr_of_mut!(detectable_repeat));
+
+            if detectable_repeat == 0 {
+                log_warn!("Detectable key auto repeat isn't supported, repeats will be reported as new presses");
+            }
+
+            let connection: *mut xcb_connection_t =
+                x11::xlib_xcb::XGetXCBConnection(display) as *mut xcb_connection_t;
+
+            let conn_err = xcb_connection_has_error(connection);
+            if conn_err != 0 {
+                log_fatal!("Unable to connect to X server, have you set one up?");
+                xlib::XCloseDisplay(display);
+                return Err(Error::XcbConnection(conn_err));
+            }
+
+            let setup = xcb_get_setup(connection);
+            let screen: *mut xcb_screen_t;
+
+            {
+                let iterator = xcb_setup_roots_iterator(setup);
+                screen = iterator.data;
+            }
+
+            let del_str = b"WM_DELETE_WINDOW";
+
+            let wm_delete_cookie =
+                xcb_intern_atom(connection, 0, del_str.len() as u16, del_str.as_ptr() as _);
+
+            let proto_str = b"WM_PROTOCOLS";
+
+            let wm_protocols_cookie = xcb_intern_atom(
+                connection,
+                0,
+                proto_str.len() as u16,
+                proto_str.as_ptr() as _,
+            );
+
+            let wm_delete_reply = xcb_intern_atom_reply(connection, wm_delete_cookie, null_mut());
+
+            let wm_proto_reply = xcb_intern_atom_reply(connection, wm_protocols_cookie, null_mut());
+
+            if wm_delete_reply.is_null() || wm_proto_reply.is_null() {
+                log_fatal!("Failed to intern the WM_PROTOCOLS and WM_DELETE_WINDOW atoms");
+                libc::free(wm_delete_reply as _);
+                libc::free(wm_proto_reply as _);
+                xlib::XCloseDisplay(display);
+                return Err(Error::WindowCreation(
+                    "failed to intern the WM_PROTOCOLS and WM_DELETE_WINDOW atoms".to_string(),
+                ));
+            }
+
+            let wm_protocols = (*wm_proto_reply).atom;
+            let wm_delete_win = (*wm_delete_reply).atom;
+            libc::free(wm_proto_reply as _);
+            libc::free(wm_delete_reply as _);
+
+            return Ok(XConnection {
+                display,
+                connection,
+                screen,
+                wm_protocols,
+                wm_delete_win,
+                net_wm_state: intern_atom(connection, b"_NET_WM_STATE"),
+                net_wm_state_fullscreen: intern_atom(connection, b"_NET_WM_STATE_FULLSCREEN"),
+                net_wm_bypass_compositor: intern_atom(connection, b"_NET_WM_BYPASS_COMPOSITOR"),
+                im: open_input_method(display),
+                xi_opcode: select_raw_motion(connection, (*screen).root),
+                focus: Cell::new(0),
+                blank_cursor: Cell::new(0),
+                pending: RefCell::new(HashMap::new()),
+                clock: RefCell::new(EventClock::default()),
+            });
+        }
+    }
+
+    /// Starts holding events for a window
+    fn register(&self, window: u32) {
+        self.pending.borrow_mut().insert(window, VecDeque::new());
+    }
+
+    /// Stops holding events for a window, dropping any it hasn't taken
+    fn unregister(&self, window: u32) {
+        if let Some(events) = self.pending.borrow_mut().remove(&window) {
+            free_pending(events);
+        }
+    }
+
+    /// Takes the next event held for a window
+    fn take_pending(&self, window: u32) -> Option<PendingEvent> {
+        return self
+            .pending
+            .borrow_mut()
+            .get_mut(&window)
+            .and_then(|events| events.pop_front());
+    }
+
+    /// Whether any window has events held for it
+    fn has_pending(&self) -> bool {
+        return self.pending.borrow().values().any(|events| !events.is_empty());
+    }
+
+    /// Reads every event waiting on the connection and holds them for their windows
+    unsafe fn pump(&self) {
+        loop {
+            let event = xcb_poll_for_event(self.connection);
+            if event.is_null() {
+                break;
+            }
+
+            let window = match (*event).response_type & 0x7f {
+                // raw motion is sent from the root window, it's for whichever of ours has focus
+                XCB_GE_GENERIC if self.is_raw_motion(event) => Some(self.focus.get()),
+                _ => event_window(event),
+            };
+
+            if self.hold(event, window) {
+                continue;
+            }
+
+            // not one of our windows, but it could be the one Xlib made for the input method
+            self.filter_foreign(event);
+            libc::free(event as _);
+        }
+    }
+
+    /// Holds an event for the window it's for, returning false if that isn't one of ours
+    unsafe fn hold(&self, event: *mut xcb_generic_event_t, window: Option<u32>) -> bool {
+        let window = match window {
+            Some(window) => window,
+            None => return false,
+        };
+
+        // focus is followed as the events are read, so raw motion read along with a
+        // focus change is held for the right window
+        self.track_focus(event, window);
+
+        return match self.pending.borrow_mut().get_mut(&window) {
+            Some(events) => {
+                events.push_back(PendingEvent::Xcb(event));
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// Whether a generic event is XInput 2 raw motion
+    unsafe fn is_raw_motion(&self, event: *const xcb_generic_event_t) -> bool {
+        let generic = event as *const xcb_ge_generic_event_t;
+
+        return self.xi_opcode == Some((*generic).extension) && (*generic).event_type == XI_RAW_MOTION;
+    }
+
+    /// Follows which of the windows has focus from their focus events
+    unsafe fn track_focus(&self, event: *const xcb_generic_event_t, window: u32) {
+        match (*event).response_type & 0x7f {
+            XCB_FOCUS_IN if is_focus_change(event as _) => self.focus.set(window),
+            XCB_FOCUS_OUT if is_focus_change(event as _) && self.focus.get() == window => self.focus.set(0),
+            _ => {}
+        }
+    }
+
+    /// Gets a cursor with nothing drawn, made the first time it's needed
+    unsafe fn blank_cursor(&self) -> u32 {
+        if self.blank_cursor.get() == 0 {
+            let root = (*self.screen).root as xlib::Window;
+
+            // a pixmap from Xlib rather than xcb, as it starts out cleared
+            let data = [0 as libc::c_char; 8];
+            let bitmap = xlib::XCreateBitmapFromData(self.display, root, data.as_ptr(), 8, 8);
+            let mut black: xlib::XColor = std::mem::zeroed();
+
+            let cursor = xlib::XCreatePixmapCursor(
+                self.display,
+                bitmap,
+                bitmap,
+                ptr::addr_of_mut!(black),
+                ptr::addr_of_mut!(black),
+                0,
+                0,
+            );
+            xlib::XFreePixmap(self.display, bitmap);
+
+            // Xlib buffers its requests separately, they have to be sent before xcb uses the cursor
+            xlib::XFlush(self.display);
+            self.blank_cursor.set(cursor as u32);
+        }
+
+        return self.blank_cursor.get();
+    }
+
+    /// Passes input method traffic through Xlib, which has to see it for the input method to work
+    unsafe fn filter_foreign(&self, event: *mut xcb_generic_event_t) {
+        if self.im.is_null() {
+            return;
+        }
+
+        let mut xevent = match (*event).response_type & 0x7f {
+            XCB_CLIENT_MESSAGE => {
+                let cm = event as *const xcb_client_message_event_t;
+
+                let data = match (*cm).format {
+                    32 => {
+                        let mut longs = [0 as libc::c_long; 5];
+                        for (long, val) in longs.iter_mut().zip((*cm).data.data32()) {
+                            *long = *val as libc::c_long;
+                        }
+                        xlib::ClientMessageData::from(longs)
+                    }
+                    _ => {
+                        let mut bytes = [0 as libc::c_char; 20];
+                        for (byte, val) in bytes.iter_mut().zip((*cm).data.data8()) {
+                            *byte = *val as libc::c_char;
+                        }
+                        xlib::ClientMessageData::from(bytes)
+                    }
+                };
+
+                xlib::XEvent {
+                    client_message: xlib::XClientMessageEvent {
+                        type_: xlib::ClientMessage,
+                        serial: (*cm).sequence as libc::c_ulong,
+                        send_event: ((*cm).response_type & 0x80 != 0) as xlib::Bool,
+                        display: self.display,
+                        window: (*cm).window as xlib::Window,
+                        message_type: (*cm).type_ as xlib::Atom,
+                        format: (*cm).format as libc::c_int,
+                        data,
+                    },
+                }
+            }
+            XCB_PROPERTY_NOTIFY => {
+                // larger input method messages are passed through properties
+                let property = event as *const xcb_property_notify_event_t;
+
+                xlib::XEvent {
+                    property: xlib::XPropertyEvent {
+                        type_: xlib::PropertyNotify,
+                        serial: (*property).sequence as libc::c_ulong,
+                        send_event: ((*property).response_type & 0x80 != 0) as xlib::Bool,
+                        display: self.display,
+                        window: (*property).window as xlib::Window,
+                        atom: (*property).atom as xlib::Atom,
+                        time: (*property).time as xlib::Time,
+                        state: (*property).state as libc::c_int,
+                    },
+                }
+            }
+            _ => return,
+        };
+
+        xlib::XFilterEvent(ptr::addr_of_mut!(xevent), 0);
+        self.drain_xlib_queue();
+    }
+
+    /// Text committed by the input method is put back on Xlib's own queue as key presses,
+    /// which are held for the window they're for. Xlib also reads any events that arrive while
+    /// it waits on the input method, those are turned back into xcb events and held the same way
+    unsafe fn drain_xlib_queue(&self) {
+        while xlib::XEventsQueued(self.display, QUEUED_ALREADY) > 0 {
+            let mut queued: xlib::XEvent = std::mem::zeroed();
+            xlib::XNextEvent(self.display, ptr::addr_of_mut!(queued));
+
+            if xlib::XFilterEvent(ptr::addr_of_mut!(queued), 0) != 0 {
+                continue;
+            }
+
+            // key presses on the queue are ones the input method sent back after seeing them,
+            // they were reported as KeyDown when xcb first read them, so only their text is left
+            if queued.get_type() == xlib::KeyPress {
+                if let Some(events) = self.pending.borrow_mut().get_mut(&(queued.key.window as u32)) {
+                    events.push_back(PendingEvent::Xlib(queued));
+                }
+                continue;
+            }
+
+            let event = to_xcb_event(&queued);
+            if !event.is_null() && !self.hold(event, event_window(event)) {
+                libc::free(event as _);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for XConnection {
+    fn drop(&mut self) {
+        for (_, events) in self.pending.get_mut().drain() {
+            free_pending(events);
+        }
+
+        unsafe {
+            if !self.im.is_null() {
+                xlib::XCloseIM(self.im);
+            }
+
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Frees the xcb events among events that were never handled
+#[cfg(target_os = "linux")]
+fn free_pending(events: VecDeque<PendingEvent>) {
+    for event in events {
+        if let PendingEvent::Xcb(event) = event {
+            unsafe {
+                libc::free(event as _);
+            }
+        }
+    }
+}
+
+/// Whether a focus event is a real change, focus moving in and out for keyboard grabs, like
+/// a WM's alt-tab switcher, and focus moving to our own child windows isn't
+#[cfg(target_os = "linux")]
+unsafe fn is_focus_change(focus: *const xcb_focus_in_event_t) -> bool {
+    return (*focus).mode as u32 != XCB_NOTIFY_MODE_GRAB
+        && (*focus).mode as u32 != XCB_NOTIFY_MODE_UNGRAB
+        && (*focus).detail as u32 != XCB_NOTIFY_DETAIL_INFERIOR
+        && (*focus).detail as u32 != XCB_NOTIFY_DETAIL_POINTER;
+}
+
+/// Gets the window an event is about, for the events we select on our windows
+#[cfg(target_os = "linux")]
+unsafe fn event_window(event: *const xcb_generic_event_t) -> Option<u32> {
+    let window = match (*event).response_type & 0x7f {
+        // key, button, motion and crossing events share a layout up to the window
+        XCB_KEY_PRESS | XCB_KEY_RELEASE | XCB_BUTTON_PRESS | XCB_BUTTON_RELEASE
+        | XCB_MOTION_NOTIFY | XCB_ENTER_NOTIFY | XCB_LEAVE_NOTIFY => {
+            (*(event as *const xcb_key_press_event_t)).event
+        }
+        XCB_FOCUS_IN | XCB_FOCUS_OUT => (*(event as *const xcb_focus_in_event_t)).event,
+        XCB_EXPOSE => (*(event as *const xcb_expose_event_t)).window,
+        XCB_MAP_NOTIFY => (*(event as *const xcb_map_notify_event_t)).window,
+        XCB_UNMAP_NOTIFY => (*(event as *const xcb_unmap_notify_event_t)).window,
+        XCB_CONFIGURE_NOTIFY => (*(event as *const xcb_configure_notify_event_t)).window,
+        XCB_CLIENT_MESSAGE => (*(event as *const xcb_client_message_event_t)).window,
+        XCB_PROPERTY_NOTIFY => (*(event as *const xcb_property_notify_event_t)).window,
+        _ => return None,
+    };
+
+    return Some(window);
+}
+
+#[cfg(target_os = "linux")]
+pub struct PlatformWindow {
+    shared: Rc<XConnection>,
+    pub window: u32,
+    width: u16,
+    height: u16,
+    x: i16,
+    y: i16,
+    // the input context used for text input, null if none could be created
+    ic: xlib::XIC,
+    // boxed so the input method callbacks keep a stable pointer to it
+    preedit: Box<PreeditState>,
+    // which keycodes are held, to tell auto repeated presses apart
+    keys_down: [bool; 256],
+}
+
+#[cfg(target_os = "linux")]
+impl PlatformWindow {
+    /// Gets the position of the window's top left corner relative to the root window
+    fn root_position(&self) -> (i16, i16) {
+        unsafe {
+            let cookie = xcb_translate_coordinates(
+                self.shared.connection,
+                self.window,
+                (*self.shared.screen).root,
+                0,
+                0,
+            );
+
+            let reply = xcb_translate_coordinates_reply(self.shared.connection, cookie, null_mut());
+
+            if reply.is_null() {
+                log_error!("Failed to translate window coordinates to the root window");
+                return (self.x, self.y);
+            }
+
+            let pos = ((*reply).dst_x, (*reply).dst_y);
+            libc::free(reply as _);
+
+            return pos;
+        }
+    }
+
+    /// Passes a key press through the input method, returning true if it was consumed.
+    /// Any composition events it produced are added to the que
+    unsafe fn filter_key_event(&mut self, xevent: &mut xlib::XEvent, ev_que: &mut EventDeque) -> bool {
+        if self.ic.is_null() {
+            return false;
+        }
+
+        let filtered = xlib::XFilterEvent(xevent, 0) != 0;
+
+        ev_que.extend(self.preedit.events.drain(..).map(Event::new));
+        self.shared.drain_xlib_queue();
+
+        return filtered;
+    }
+
+    /// Sends a client message about this window to the root window, which is how
+    /// requests are made to the window manager
+    fn send_root_message(&self, message_type: xcb_atom_t, values: [u32; 5]) {
+        let mut data = xcb_client_message_data_t { data: [0; 20] };
+
+        for (i, val) in values.iter().enumerate() {
+            data.data[i * 4..i * 4 + 4].copy_from_slice(&val.to_ne_bytes());
+        }
+
+        let message = xcb_client_message_event_t {
+            response_type: XCB_CLIENT_MESSAGE,
+            format: 32,
+            sequence: 0,
+            window: self.window,
+            type_: message_type,
+            data,
+        };
+
+        unsafe {
+            xcb_send_event(
+                self.shared.connection,
+                0,
+                (*self.shared.screen).root,
+                XCB_EVENT_MASK_SUBSTRUCTURE_REDIRECT | XCB_EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+                ptr::addr_of!(message) as _,
+            );
+        }
+    }
+
+    /// Translates an event for this window
+    unsafe fn handle_event(&mut self, event: *mut xcb_generic_event_t, ev_que: &mut EventDeque) {
+        let event_enum: u8 = (*event).response_type & 0x7f;
+
+        match event_enum {
+            XCB_KEY_PRESS => {
+                let kb_event = event as *const xcb_key_press_event_t;
+
+                // index 0 is the unshifted keysym, shift is reported through the modifiers
+                let key = xlib::XKeycodeToKeysym(self.shared.display, (*kb_event).detail, 0);
+                let keycode = (*kb_event).detail as usize;
+
+                ev_que.push_back(Event::new(EventType::KeyDown {
+                    key: Key::from(key as u32),
+                    scancode: x_scancode((*kb_event).detail),
+                    modifiers: modifiers_from_state((*kb_event).state),
+                    is_repeat: self.keys_down[keycode],
+                }));
+
+                self.keys_down[keycode] = true;
+
+                // keys used for composing are consumed by the input method
+                let mut xevent = to_xkey_event(self.shared.display, kb_event);
+                if !self.filter_key_event(&mut xevent, ev_que) {
+                    if let Some(text) = lookup_text(self.ic, ptr::addr_of_mut!(xevent.key)) {
+                        ev_que.push_back(Event::new(EventType::TextInput { text }));
+                    }
+                }
+            }
+            XCB_KEY_RELEASE => {
+                let kb_event = event as *const xcb_key_release_event_t;
+
+                let key = xlib::XKeycodeToKeysym(self.shared.display, (*kb_event).detail, 0);
+                self.keys_down[(*kb_event).detail as usize] = false;
+
+                ev_que.push_back(Event::new(EventType::KeyUp {
+                    key: Key::from(key as u32),
+                    scancode: x_scancode((*kb_event).detail),
+                    modifiers: modifiers_from_state((*kb_event).state),
+                }));
+            }
+            XCB_MOTION_NOTIFY => {
+                let motion = event as *const xcb_motion_notify_event_t;
+                ev_que.push_back(Event::new(EventType::MouseMove {
+                    x: (*motion).event_x,
+                    y: (*motion).event_y,
+                }));
+            }
+            // only raw motion is held for windows, see XConnection::pump
+            XCB_GE_GENERIC => {
+                let (delta_x, delta_y) = raw_motion_delta(event as *const XiRawEvent);
+
+                if delta_x != 0.0 || delta_y != 0.0 {
+                    ev_que.push_back(Event::new(EventType::MouseRawMotion { delta_x, delta_y }));
+                }
+            }
+            XCB_BUTTON_PRESS => {
+                let button_event = event as *mut xcb_button_press_event_t;
+                let modifiers = modifiers_from_state((*button_event).state);
+
+                match (*button_event).detail as u32 {
+                    XCB_BUTTON_INDEX_1 => {
+                        ev_que.push_back(Event::new(EventType::MouseLeftBtnDown { modifiers }));
+                    }
+                    XCB_BUTTON_INDEX_2 => {
+                        ev_que.push_back(Event::new(EventType::MouseMidBtnDown { modifiers }));
+                    }
+                    XCB_BUTTON_INDEX_3 => {
+                        ev_que.push_back(Event::new(EventType::MouseRightBtnDown { modifiers }));
+                    }
+                    // the wheel is reported as presses of buttons 4 to 7,
+                    // each press is one notch and the releases are ignored.
+                    // XI2's scroll valuators aren't read, so there's no smooth scrolling on X11
+                    XCB_BUTTON_INDEX_4 => {
+                        ev_que.push_back(Event::new(EventType::MouseWheel {
+                            delta_x: 0.0,
+                            delta_y: 1.0,
+                        }));
+                    }
+                    XCB_BUTTON_INDEX_5 => {
+                        ev_que.push_back(Event::new(EventType::MouseWheel {
+                            delta_x: 0.0,
+                            delta_y: -1.0,
+                        }));
+                    }
+                    BUTTON_INDEX_SCROLL_LEFT => {
+                        ev_que.push_back(Event::new(EventType::MouseWheel {
+                            delta_x: -1.0,
+                            delta_y: 0.0,
+                        }));
+                    }
+                    BUTTON_INDEX_SCROLL_RIGHT => {
+                        ev_que.push_back(Event::new(EventType::MouseWheel {
+                            delta_x: 1.0,
+                            delta_y: 0.0,
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+            XCB_BUTTON_RELEASE => {
+                let button_event = event as *mut xcb_button_release_event_t;
+                let modifiers = modifiers_from_state((*button_event).state);
+
+                match (*button_event).detail as u32 {
+                    XCB_BUTTON_INDEX_1 => {
+                        ev_que.push_back(Event::new(EventType::MouseLeftBtnUp { modifiers }));
+                    }
+                    XCB_BUTTON_INDEX_2 => {
+                        ev_que.push_back(Event::new(EventType::MouseMidBtnUp { modifiers }));
+                    }
+                    XCB_BUTTON_INDEX_3 => {
+                        ev_que.push_back(Event::new(EventType::MouseRightBtnUp { modifiers }));
+                    }
+                    _ => {}
+                }
+            }
+            XCB_FOCUS_IN | XCB_FOCUS_OUT
+                if is_focus_change(event as *const xcb_focus_in_event_t) =>
+            {
+                if event_enum == XCB_FOCUS_IN {
+                    if !self.ic.is_null() {
+                        xlib::XSetICFocus(self.ic);
+                    }
+                    ev_que.push_back(Event::new(EventType::WinFocusGained));
+                } else {
+                    if !self.ic.is_null() {
+                        xlib::XUnsetICFocus(self.ic);
+                    }
+
+                    // releases while another window has focus are never seen
+                    self.keys_down = [false; 256];
+                    ev_que.push_back(Event::new(EventType::WinFocusLost));
+                }
+            }
+            XCB_ENTER_NOTIFY | XCB_LEAVE_NOTIFY => {
+                let crossing = event as *const xcb_enter_notify_event_t;
+
+                // the cursor moving onto or off a child window stays within ours
+                if (*crossing).detail as u32 != XCB_NOTIFY_DETAIL_INFERIOR {
+                    if event_enum == XCB_ENTER_NOTIFY {
+                        ev_que.push_back(Event::new(EventType::MouseEnter));
+                    } else {
+                        ev_que.push_back(Event::new(EventType::MouseLeave));
+                    }
+                }
+            }
+            XCB_MAP_NOTIFY => {
+                ev_que.push_back(Event::new(EventType::WinShow));
+            }
+            XCB_UNMAP_NOTIFY => {
+                // also sent when the window is minimized
+                ev_que.push_back(Event::new(EventType::WinHide));
+            }
+            XCB_EXPOSE => {
+                let expose = event as *const xcb_expose_event_t;
+
+                // a single expose is made of several events, count is how many are left
+                if (*expose).count == 0 {
+                    ev_que.push_back(Event::new(EventType::WinExpose));
+                }
+            }
+            XCB_CONFIGURE_NOTIFY => {
+                let configure = event as *const xcb_configure_notify_event_t;
+
+                if (*configure).width != self.width || (*configure).height != self.height {
+                    self.width = (*configure).width;
+                    self.height = (*configure).height;
+
+                    ev_que.push_back(Event::new(EventType::WinResize {
+                        width: self.width,
+                        height: self.height,
+                    }));
+                }
+
+                // Synthetic events sent by the window manager are in root coordinates,
+                // real ones are relative to the parent, which is usually a WM frame
+                let (x, y) = if (*configure).response_type & 0x80 != 0 {
+                    ((*configure).x, (*configure).y)
+                } else {
+                    self.root_position()
+                };
+
+                if x != self.x || y != self.y {
+                    self.x = x;
+                    self.y = y;
+
+                    ev_que.push_back(Event::new(EventType::WinMove {
+                        x: self.x,
+                        y: self.y,
+                    }));
+                }
+            }
+            XCB_CLIENT_MESSAGE => {
+                let cm = event as *const xcb_client_message_event_t;
+
+                if (*cm).type_ == self.shared.wm_protocols
+                    && (*cm).data.data32()[0] == self.shared.wm_delete_win
+                {
+                    ev_que.push_back(Event::new(EventType::WinClose));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gets the evdev code of an X keycode, the X server offsets them by 8
+#[cfg(target_os = "linux")]
+#[inline]
+fn x_scancode(keycode: u8) -> ScanCode {
+    return ScanCode((keycode as u32).saturating_sub(8));
+}
+
+/// The device id Xkb requests take for the core keyboard
+#[cfg(target_os = "linux")]
+const XKB_USE_CORE_KBD: libc::c_uint = 0x0100;
+
+/// Gets the label of a key from its unshifted keysym, the uppercased character for keys
+/// that type one and the name of the Key for the rest
+#[cfg(target_os = "linux")]
+fn keysym_label(keysym: u32) -> Option<String> {
+    // Latin-1 keysyms are their code point, other characters are it plus 0x1000000
+    let code_point = match keysym {
+        0x21..=0x7E | 0xA1..=0xFF => char::from_u32(keysym),
+        0x1000100..=0x110FFFF => char::from_u32(keysym - 0x1000000),
+        _ => None,
+    };
+
+    if let Some(c) = code_point {
+        return Some(c.to_uppercase().collect());
+    }
+
+    return match Key::from(keysym) {
+        Key::None => None,
+        key => Some(key.get_name().to_string()),
+    };
+}
+
+/// Gets the server time of input events, the ones without a time are left stamped
+/// with when they were read
+#[cfg(target_os = "linux")]
+unsafe fn x_event_time(event: *const xcb_generic_event_t) -> Option<u32> {
+    match (*event).response_type & 0x7f {
+        XCB_KEY_PRESS | XCB_KEY_RELEASE => Some((*(event as *const xcb_key_press_event_t)).time),
+        XCB_BUTTON_PRESS | XCB_BUTTON_RELEASE => Some((*(event as *const xcb_button_press_event_t)).time),
+        XCB_MOTION_NOTIFY => Some((*(event as *const xcb_motion_notify_event_t)).time),
+        XCB_ENTER_NOTIFY | XCB_LEAVE_NOTIFY => Some((*(event as *const xcb_enter_notify_event_t)).time),
+        XCB_GE_GENERIC => Some((*(event as *const XiRawEvent)).time),
+        _ => None,
+    }
+}
+
+/// The buttons used for horizontal scrolling, xcb only names buttons 1 to 5
+#[cfg(target_os = "linux")]
+const BUTTON_INDEX_SCROLL_LEFT: u32 = 6;
+#[cfg(target_os = "linux")]
+const BUTTON_INDEX_SCROLL_RIGHT: u32 = 7;
+
+/// Converts the state field of an X event to Modifiers, using the usual modifier mapping
+/// of Alt to Mod1, NumLock to Mod2 and Super to Mod4
+#[cfg(target_os = "linux")]
+fn modifiers_from_state(state: u16) -> Modifiers {
+    let state = state as u32;
+
+    return Modifiers {
+        shift: state & XCB_MOD_MASK_SHIFT != 0,
+        ctrl: state & XCB_MOD_MASK_CONTROL != 0,
+        alt: state & XCB_MOD_MASK_1 != 0,
+        super_key: state & XCB_MOD_MASK_4 != 0,
+        caps_lock: state & XCB_MOD_MASK_LOCK != 0,
+        num_lock: state & XCB_MOD_MASK_2 != 0,
+    };
+}
+
+/// Interns an atom, returning XCB_ATOM_NONE on failure
+#[cfg(target_os = "linux")]
+unsafe fn intern_atom(connection: *mut xcb_connection_t, name: &[u8]) -> xcb_atom_t {
+    let cookie = xcb_intern_atom(connection, 0, name.len() as u16, name.as_ptr() as _);
+    let reply = xcb_intern_atom_reply(connection, cookie, null_mut());
+
+    if reply.is_null() {
+        log_error!("Failed to intern atom {}", String::from_utf8_lossy(name));
+        return XCB_ATOM_NONE;
+    }
+
+    let atom = (*reply).atom;
+    libc::free(reply as _);
+
+    return atom;
+}
+
+/// Replaces a property on the window with the given 32 bit values
+#[cfg(target_os = "linux")]
+unsafe fn set_property_32(
+    connection: *mut xcb_connection_t,
+    window: u32,
+    property: xcb_atom_t,
+    prop_type: xcb_atom_t,
+    values: &[u32],
+) {
+    xcb_change_property(
+        connection,
+        XCB_PROP_MODE_REPLACE as u8,
+        window,
+        property,
+        prop_type,
+        32,
+        values.len() as u32,
+        values.as_ptr() as _,
+    );
+}
+
+/// Sets the title, WM_CLASS, size hints, decorations and initial state of a window,
+/// this has to be done before the window is mapped for most window managers to honour it
+#[cfg(target_os = "linux")]
+unsafe fn set_window_hints(connection: *mut xcb_connection_t, window: u32, config: &WindowBuilder) {
+    let title = config.title.as_bytes();
+
+    xcb_change_property(
+        connection,
+        XCB_PROP_MODE_REPLACE as u8,
+        window,
+        XCB_ATOM_WM_NAME,
+        XCB_ATOM_STRING,
+        8,
+        title.len() as u32,
+        title.as_ptr() as _,
+    );
+
+    // WM_NAME is latin-1, so modern window managers read the UTF-8 _NET_WM_NAME instead
+    let net_wm_name = intern_atom(connection, b"_NET_WM_NAME");
+    let utf8_string = intern_atom(connection, b"UTF8_STRING");
+
+    xcb_change_property(
+        connection,
+        XCB_PROP_MODE_REPLACE as u8,
+        window,
+        net_wm_name,
+        utf8_string,
+        8,
+        title.len() as u32,
+        title.as_ptr() as _,
+    );
+
+    if let Some((instance, class)) = &config.wm_class {
+        let wm_class = format!("{}\0{}\0", instance, class);
+
+        xcb_change_property(
+            connection,
+            XCB_PROP_MODE_REPLACE as u8,
+            window,
+            XCB_ATOM_WM_CLASS,
+            XCB_ATOM_STRING,
+            8,
+            wm_class.len() as u32,
+            wm_class.as_ptr() as _,
+        );
+    }
+
+    // WM_SIZE_HINTS, see ICCCM 4.1.2.3
+    const US_POSITION: u32 = 1;
+    const US_SIZE: u32 = 1 << 1;
+    const P_MIN_SIZE: u32 = 1 << 4;
+    const P_MAX_SIZE: u32 = 1 << 5;
+
+    let mut size_hints = [0u32; 18];
+    size_hints[0] = US_POSITION | US_SIZE;
+    size_hints[1] = config.x as u32;
+    size_hints[2] = config.y as u32;
+    size_hints[3] = config.width as u32;
+    size_hints[4] = config.height as u32;
+
+    if let Some((width, height)) = config.min_size_limit() {
+        size_hints[0] |= P_MIN_SIZE;
+        size_hints[5] = width as u32;
+        size_hints[6] = height as u32;
+    }
+
+    if let Some((width, height)) = config.max_size_limit() {
+        size_hints[0] |= P_MAX_SIZE;
+        size_hints[7] = width as u32;
+        size_hints[8] = height as u32;
+    }
+
+    set_property_32(
+        connection,
+        window,
+        XCB_ATOM_WM_NORMAL_HINTS,
+        XCB_ATOM_WM_SIZE_HINTS,
+        &size_hints,
+    );
+
+    if !config.decorations {
+        // _MOTIF_WM_HINTS is { flags, functions, decorations, input_mode, status },
+        // flags = MWM_HINTS_DECORATIONS and no decorations
+        let motif_hints = intern_atom(connection, b"_MOTIF_WM_HINTS");
+        set_property_32(connection, window, motif_hints, motif_hints, &[2, 0, 0, 0, 0]);
+    }
+
+    if config.maximized {
+        let net_wm_state = intern_atom(connection, b"_NET_WM_STATE");
+        let states = [
+            intern_atom(connection, b"_NET_WM_STATE_MAXIMIZED_VERT"),
+            intern_atom(connection, b"_NET_WM_STATE_MAXIMIZED_HORZ"),
+        ];
+
+        set_property_32(connection, window, net_wm_state, XCB_ATOM_ATOM, &states);
+    }
+}
+
+/// The composition text reported by the input method callbacks, and the events those
+/// callbacks produced which are passed on by the next update
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct PreeditState {
+    text: Vec<char>,
+    cursor: usize,
+    events: Vec<EventType>,
+}
+
+#[cfg(target_os = "linux")]
+impl PreeditState {
+    fn push_composition(&mut self) {
+        self.events.push(EventType::TextComposition {
+            text: self.text.iter().collect(),
+            cursor: self.cursor,
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn preedit_start_callback(
+    _ic: xlib::XIC,
+    client_data: xlib::XPointer,
+    _call_data: xlib::XPointer,
+) -> xlib::Bool {
+    let state = &mut *(client_data as *mut PreeditState);
+    state.text.clear();
+    state.cursor = 0;
+    state.events.push(EventType::TextCompositionStart);
+
+    // no limit on the length of the preedit text
+    return -1;
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn preedit_done_callback(
+    _ic: xlib::XIC,
+    client_data: xlib::XPointer,
+    _call_data: xlib::XPointer,
+) -> xlib::Bool {
+    let state = &mut *(client_data as *mut PreeditState);
+    state.text.clear();
+    state.cursor = 0;
+    state.events.push(EventType::TextCompositionEnd);
+
+    return 0;
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn preedit_draw_callback(
+    _ic: xlib::XIC,
+    client_data: xlib::XPointer,
+    call_data: xlib::XPointer,
+) -> xlib::Bool {
+    let state = &mut *(client_data as *mut PreeditState);
+    let draw = call_data as *const xlib::XIMPreeditDrawCallbackStruct;
+
+    // the chars from chg_first to chg_first + chg_length are replaced with the new text
+    let first = ((*draw).chg_first.max(0) as usize).min(state.text.len());
+    let last = (first + (*draw).chg_length.max(0) as usize).min(state.text.len());
+
+    let text = (*draw).text;
+    let new_text: Vec<char> = if text.is_null() {
+        Vec::new()
+    } else if (*text).encoding_is_wchar != 0 {
+        let wide = (*text).string.multi_byte as *const libc::wchar_t;
+
+        if wide.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(wide, (*text).length as usize)
+                .iter()
+                .filter_map(|&c| char::from_u32(c as u32))
+                .collect()
+        }
+    } else if (*text).string.multi_byte.is_null() {
+        Vec::new()
+    } else {
+        std::ffi::CStr::from_ptr((*text).string.multi_byte)
+            .to_string_lossy()
+            .chars()
+            .collect()
+    };
+
+    state.text.splice(first..last, new_text);
+    state.cursor = ((*draw).caret.max(0) as usize).min(state.text.len());
+    state.push_composition();
+
+    return 0;
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn preedit_caret_callback(
+    _ic: xlib::XIC,
+    client_data: xlib::XPointer,
+    call_data: xlib::XPointer,
+) -> xlib::Bool {
+    let state = &mut *(client_data as *mut PreeditState);
+    let caret = call_data as *const xlib::XIMPreeditCaretCallbackStruct;
+
+    state.cursor = ((*caret).position.max(0) as usize).min(state.text.len());
+    state.push_composition();
+
+    return 0;
+}
+
+/// The XInput 2 event type of raw motion
+#[cfg(target_os = "linux")]
+const XI_RAW_MOTION: u16 = 17;
+
+/// The fixed part of an XInput 2 raw event, see XI2proto.h. It's followed by the mask of the
+/// axes reported, then a value for each of them with acceleration and then one without
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct XiRawEvent {
+    response_type: u8,
+    extension: u8,
+    sequence: u16,
+    length: u32,
+    event_type: u16,
+    device_id: u16,
+    time: u32,
+    detail: u32,
+    source_id: u16,
+    valuators_len: u16,
+    flags: u32,
+    pad: u32,
+    // xcb puts this after the first 32 bytes, moving the rest of the event along
+    full_sequence: u32,
+}
+
+/// A 32.32 fixed point number
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct Fp3232 {
+    integral: i32,
+    frac: u32,
+}
+
+/// Reads the unaccelerated motion along the x and y axes of a raw motion event
+#[cfg(target_os = "linux")]
+unsafe fn raw_motion_delta(event: *const XiRawEvent) -> (f32, f32) {
+    let mask_len = (*event).valuators_len as usize * 4;
+    let mask = std::slice::from_raw_parts((event as *const u8).add(size_of::<XiRawEvent>()), mask_len);
+    let axes = mask.iter().map(|byte| byte.count_ones() as usize).sum::<usize>();
+
+    // length counts the 4 byte units after the first 32 bytes
+    if mask_len + axes * 2 * size_of::<Fp3232>() > (*event).length as usize * 4 {
+        return (0.0, 0.0);
+    }
+
+    let raw = std::slice::from_raw_parts(
+        (event as *const u8).add(size_of::<XiRawEvent>() + mask_len + axes * size_of::<Fp3232>()) as *const Fp3232,
+        axes,
+    );
+
+    // x and y are axes 0 and 1, so when they're reported their values come first
+    let mut delta = [0.0f32; 2];
+    let mut index = 0;
+
+    for (axis, value) in delta.iter_mut().enumerate() {
+        if mask.first().is_some_and(|byte| byte & (1 << axis) != 0) {
+            *value = (raw[index].integral as f64 + raw[index].frac as f64 / 4294967296.0) as f32;
+            index += 1;
+        }
+    }
+
+    return (delta[0], delta[1]);
+}
+
+/// Mirrors xcb_protocol_request_t from xcbext.h, the xcb crate keeps its fields private
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct XcbProtocolRequest {
+    count: usize,
+    ext: *mut libc::c_void,
+    opcode: u8,
+    isvoid: u8,
+}
+
+/// Sends a request to an extension the xcb crate has no bindings for. The request starts
+/// with the usual 4 byte header, xcb fills in the major opcode and the length.
+/// Returns the sequence number to wait for the reply with
+#[cfg(target_os = "linux")]
+unsafe fn send_extension_request(
+    connection: *mut xcb_connection_t,
+    major_opcode: u8,
+    request: &mut [u8],
+    has_reply: bool,
+) -> libc::c_uint {
+    // xcb may use the part before the request to send a BIG-REQUESTS length
+    let mut parts = [
+        libc::iovec {
+            iov_base: null_mut(),
+            iov_len: 0,
+        },
+        libc::iovec {
+            iov_base: request.as_mut_ptr() as _,
+            iov_len: request.len(),
+        },
+    ];
+
+    let protocol = XcbProtocolRequest {
+        count: 1,
+        ext: null_mut(),
+        opcode: major_opcode,
+        isvoid: !has_reply as u8,
+    };
+
+    return xcb::ffi::ext::xcb_send_request(
+        connection,
+        0,
+        parts.as_mut_ptr().add(1),
+        ptr::addr_of!(protocol) as *const xcb::ffi::ext::xcb_protocol_request_t,
+    );
+}
+
+/// XInput 2 minor opcodes, see XI2proto.h
+#[cfg(target_os = "linux")]
+const XI_SELECT_EVENTS: u8 = 46;
+#[cfg(target_os = "linux")]
+const XI_QUERY_VERSION: u8 = 47;
+/// The device id that selects events from every master pointer and keyboard
+#[cfg(target_os = "linux")]
+const XI_ALL_MASTER_DEVICES: u16 = 1;
+
+/// Asks for raw motion from every mouse, which is only sent to the root window.
+/// Returns the XInput opcode, None if the server doesn't have XInput 2
+#[cfg(target_os = "linux")]
+unsafe fn select_raw_motion(connection: *mut xcb_connection_t, root: u32) -> Option<u8> {
+    let name = b"XInputExtension";
+    let cookie = xcb_query_extension(connection, name.len() as u16, name.as_ptr() as _);
+    let reply = xcb_query_extension_reply(connection, cookie, null_mut());
+
+    if reply.is_null() || (*reply).present == 0 {
+        log_warn!("The X server has no XInput extension, there won't be raw mouse motion");
+        libc::free(reply as _);
+        return None;
+    }
+
+    let opcode = (*reply).major_opcode;
+    libc::free(reply as _);
+
+    // the version has to be asked for before any XInput 2 request, 2.0 has raw motion
+    let mut query = [0u8; 8];
+    query[1] = XI_QUERY_VERSION;
+    query[4..6].copy_from_slice(&2u16.to_ne_bytes());
+
+    let sequence = send_extension_request(connection, opcode, &mut query, true);
+    let reply = xcb::ffi::ext::xcb_wait_for_reply(connection, sequence, null_mut()) as *const u8;
+
+    // the reply has the server's major version after the 8 byte header
+    let major = if reply.is_null() {
+        0
+    } else {
+        u16::from_ne_bytes([*reply.add(8), *reply.add(9)])
+    };
+    libc::free(reply as _);
+
+    if major < 2 {
+        log_warn!("The X server doesn't support XInput 2, there won't be raw mouse motion");
+        return None;
+    }
+
+    // the root window, then one mask of 4 bytes for all the master devices
+    let mut select = [0u8; 20];
+    select[1] = XI_SELECT_EVENTS;
+    select[4..8].copy_from_slice(&root.to_ne_bytes());
+    select[8..10].copy_from_slice(&1u16.to_ne_bytes());
+    select[12..14].copy_from_slice(&XI_ALL_MASTER_DEVICES.to_ne_bytes());
+    select[14..16].copy_from_slice(&1u16.to_ne_bytes());
+    select[16 + (XI_RAW_MOTION / 8) as usize] |= 1 << (XI_RAW_MOTION % 8);
+
+    send_extension_request(connection, opcode, &mut select, false);
+    xcb_flush(connection);
+
+    return Some(opcode);
+}
+
+/// Opens an input method for the display, null if none is available
+#[cfg(target_os = "linux")]
+unsafe fn open_input_method(display: *mut xlib::Display) -> xlib::XIM {
+    // the input method and Xutf8LookupString follow the locale set in the environment
+    libc::setlocale(libc::LC_CTYPE, c"".as_ptr());
+
+    if xlib::XSupportsLocale() == 0 {
+        log_warn!("The current locale isn't supported by Xlib, text input may not work");
+    }
+
+    xlib::XSetLocaleModifiers(c"".as_ptr());
+    let mut im = xlib::XOpenIM(display, null_mut(), null_mut(), null_mut());
+
+    if im.is_null() {
+        // no input method server is running, Xlib's built in one still handles compose keys
+        xlib::XSetLocaleModifiers(c"@im=none".as_ptr());
+        im = xlib::XOpenIM(display, null_mut(), null_mut(), null_mut());
+    }
+
+    if im.is_null() {
+        log_warn!("Failed to open an input method, text input will be limited to latin-1");
+    }
+
+    return im;
+}
+
+/// Creates an input context for the window, preferring one that reports the composition
+/// text back to us. Null if the input method couldn't make one
+#[cfg(target_os = "linux")]
+unsafe fn create_input_context(im: xlib::XIM, window: u32, preedit: *mut PreeditState) -> xlib::XIC {
+    if im.is_null() {
+        return null_mut();
+    }
+
+    let callback = |proc_: xlib::XICProc| xlib::XICCallback {
+        client_data: preedit as xlib::XPointer,
+        callback: proc_,
+    };
+
+    let start = callback(Some(preedit_start_callback));
+    let done = callback(Some(preedit_done_callback));
+    let draw = callback(Some(preedit_draw_callback));
+    let caret = callback(Some(preedit_caret_callback));
+
+    let preedit_attributes = xlib::XVaCreateNestedList(
+        0,
+        xlib::XNPreeditStartCallback_0.as_ptr(),
+        ptr::addr_of!(start),
+        xlib::XNPreeditDoneCallback_0.as_ptr(),
+        ptr::addr_of!(done),
+        xlib::XNPreeditDrawCallback_0.as_ptr(),
+        ptr::addr_of!(draw),
+        xlib::XNPreeditCaretCallback_0.as_ptr(),
+        ptr::addr_of!(caret),
+        null_mut::<libc::c_void>(),
+    );
+
+    let mut ic = xlib::XCreateIC(
+        im,
+        xlib::XNInputStyle_0.as_ptr(),
+        (xlib::XIMPreeditCallbacks | xlib::XIMStatusNothing) as libc::c_ulong,
+        xlib::XNClientWindow_0.as_ptr(),
+        window as libc::c_ulong,
+        xlib::XNFocusWindow_0.as_ptr(),
+        window as libc::c_ulong,
+        xlib::XNPreeditAttributes_0.as_ptr(),
+        preedit_attributes,
+        null_mut::<libc::c_void>(),
+    );
+
+    xlib::XFree(preedit_attributes);
+
+    // fall back to the input method drawing the composition itself, then to no composition
+    for style in [
+        xlib::XIMPreeditNothing | xlib::XIMStatusNothing,
+        xlib::XIMPreeditNone | xlib::XIMStatusNone,
+    ] {
+        if !ic.is_null() {
+            break;
+        }
+
+        ic = xlib::XCreateIC(
+            im,
+            xlib::XNInputStyle_0.as_ptr(),
+            style as libc::c_ulong,
+            xlib::XNClientWindow_0.as_ptr(),
+            window as libc::c_ulong,
+            xlib::XNFocusWindow_0.as_ptr(),
+            window as libc::c_ulong,
+            null_mut::<libc::c_void>(),
+        );
+    }
+
+    if ic.is_null() {
+        log_warn!("Failed to create an input context, text input will be limited to latin-1");
+        return null_mut();
+    }
+
+    xlib::XSetICFocus(ic);
+
+    return ic;
+}
+
+/// Builds the Xlib version of an xcb key press, so it can go through the input method
+#[cfg(target_os = "linux")]
+unsafe fn to_xkey_event(
+    display: *mut xlib::Display,
+    kb_event: *const xcb_key_press_event_t,
+) -> xlib::XEvent {
+    return xlib::XEvent {
+        key: xlib::XKeyEvent {
+            type_: xlib::KeyPress,
+            serial: (*kb_event).sequence as libc::c_ulong,
+            send_event: ((*kb_event).response_type & 0x80 != 0) as xlib::Bool,
+            display,
+            window: (*kb_event).event as xlib::Window,
+            root: (*kb_event).root as xlib::Window,
+            subwindow: (*kb_event).child as xlib::Window,
+            time: (*kb_event).time as xlib::Time,
+            x: (*kb_event).event_x as libc::c_int,
+            y: (*kb_event).event_y as libc::c_int,
+            x_root: (*kb_event).root_x as libc::c_int,
+            y_root: (*kb_event).root_y as libc::c_int,
+            state: (*kb_event).state as libc::c_uint,
+            keycode: (*kb_event).detail as libc::c_uint,
+            same_screen: (*kb_event).same_screen as xlib::Bool,
+        },
+    };
+}
+
+/// Builds the xcb version of an event Xlib read off the connection, for the events windows
+/// handle. Null for the others, or if it couldn't be allocated
+#[cfg(target_os = "linux")]
+unsafe fn to_xcb_event(xevent: &xlib::XEvent) -> *mut xcb_generic_event_t {
+    // the top bit of the type marks events sent by another client
+    let sent: u8 = if xevent.any.send_event != 0 { 0x80 } else { 0 };
+    let sequence = xevent.any.serial as u16;
+
+    // key, button and motion events share a layout in both libraries, apart from the detail
+    macro_rules! input_event {
+        ($response_type:expr, $event:expr, $detail:expr) => {
+            alloc_xcb_event(xcb_key_press_event_t {
+                response_type: $response_type | sent,
+                detail: $detail as u8,
+                sequence,
+                time: $event.time as xcb_timestamp_t,
+                root: $event.root as xcb_window_t,
+                event: $event.window as xcb_window_t,
+                child: $event.subwindow as xcb_window_t,
+                root_x: $event.x_root as i16,
+                root_y: $event.y_root as i16,
+                event_x: $event.x as i16,
+                event_y: $event.y as i16,
+                state: $event.state as u16,
+                same_screen: $event.same_screen as u8,
+                pad0: 0,
+            })
+        };
+    }
+
+    return match xevent.get_type() {
+        xlib::KeyRelease => input_event!(XCB_KEY_RELEASE, xevent.key, xevent.key.keycode),
+        xlib::ButtonPress => input_event!(XCB_BUTTON_PRESS, xevent.button, xevent.button.button),
+        xlib::ButtonRelease => input_event!(XCB_BUTTON_RELEASE, xevent.button, xevent.button.button),
+        xlib::MotionNotify => input_event!(XCB_MOTION_NOTIFY, xevent.motion, xevent.motion.is_hint),
+        xlib::EnterNotify | xlib::LeaveNotify => {
+            let crossing = xevent.crossing;
+            let response_type = if crossing.type_ == xlib::EnterNotify {
+                XCB_ENTER_NOTIFY
+            } else {
+                XCB_LEAVE_NOTIFY
+            };
+
+            alloc_xcb_event(xcb_enter_notify_event_t {
+                response_type: response_type | sent,
+                detail: crossing.detail as u8,
+                sequence,
+                time: crossing.time as xcb_timestamp_t,
+                root: crossing.root as xcb_window_t,
+                event: crossing.window as xcb_window_t,
+                child: crossing.subwindow as xcb_window_t,
+                root_x: crossing.x_root as i16,
+                root_y: crossing.y_root as i16,
+                event_x: crossing.x as i16,
+                event_y: crossing.y as i16,
+                state: crossing.state as u16,
+                mode: crossing.mode as u8,
+                // focus is the lowest bit, same screen the one above it
+                same_screen_focus: (crossing.focus != 0) as u8 | ((crossing.same_screen != 0) as u8) << 1,
+            })
+        }
+        xlib::FocusIn | xlib::FocusOut => {
+            let focus = xevent.focus_change;
+            let response_type = if focus.type_ == xlib::FocusIn { XCB_FOCUS_IN } else { XCB_FOCUS_OUT };
+
+            alloc_xcb_event(xcb_focus_in_event_t {
+                response_type: response_type | sent,
+                detail: focus.detail as u8,
+                sequence,
+                event: focus.window as xcb_window_t,
+                mode: focus.mode as u8,
+                pad0: [0; 3],
+            })
+        }
+        xlib::Expose => {
+            let expose = xevent.expose;
+
+            alloc_xcb_event(xcb_expose_event_t {
+                response_type: XCB_EXPOSE | sent,
+                pad0: 0,
+                sequence,
+                window: expose.window as xcb_window_t,
+                x: expose.x as u16,
+                y: expose.y as u16,
+                width: expose.width as u16,
+                height: expose.height as u16,
+                count: expose.count as u16,
+                pad1: [0; 2],
+            })
+        }
+        xlib::MapNotify => {
+            let map = xevent.map;
+
+            alloc_xcb_event(xcb_map_notify_event_t {
+                response_type: XCB_MAP_NOTIFY | sent,
+                pad0: 0,
+                sequence,
+                event: map.event as xcb_window_t,
+                window: map.window as xcb_window_t,
+                override_redirect: map.override_redirect as u8,
+                pad1: [0; 3],
+            })
+        }
+        xlib::UnmapNotify => {
+            let unmap = xevent.unmap;
+
+            alloc_xcb_event(xcb_unmap_notify_event_t {
+                response_type: XCB_UNMAP_NOTIFY | sent,
+                pad0: 0,
+                sequence,
+                event: unmap.event as xcb_window_t,
+                window: unmap.window as xcb_window_t,
+                from_configure: unmap.from_configure as u8,
+                pad1: [0; 3],
+            })
+        }
+        xlib::ConfigureNotify => {
+            let configure = xevent.configure;
+
+            alloc_xcb_event(xcb_configure_notify_event_t {
+                response_type: XCB_CONFIGURE_NOTIFY | sent,
+                pad0: 0,
+                sequence,
+                event: configure.event as xcb_window_t,
+                window: configure.window as xcb_window_t,
+                above_sibling: configure.above as xcb_window_t,
+                x: configure.x as i16,
+                y: configure.y as i16,
+                width: configure.width as u16,
+                height: configure.height as u16,
+                border_width: configure.border_width as u16,
+                override_redirect: configure.override_redirect as u8,
+                pad1: 0,
+            })
+        }
+        xlib::ClientMessage => {
+            let cm = xevent.client_message;
+            let mut data = xcb_client_message_data_t { data: [0; 20] };
+
+            match cm.format {
+                32 => {
+                    for (i, val) in cm.data.as_longs().iter().enumerate() {
+                        data.data[i * 4..i * 4 + 4].copy_from_slice(&(*val as u32).to_ne_bytes());
+                    }
+                }
+                16 => {
+                    for (i, val) in cm.data.as_shorts().iter().enumerate() {
+                        data.data[i * 2..i * 2 + 2].copy_from_slice(&(*val as u16).to_ne_bytes());
+                    }
+                }
+                _ => {
+                    for (byte, val) in data.data.iter_mut().zip(cm.data.as_bytes()) {
+                        *byte = *val as u8;
+                    }
+                }
+            }
+
+            alloc_xcb_event(xcb_client_message_event_t {
+                response_type: XCB_CLIENT_MESSAGE | sent,
+                format: cm.format as u8,
+                sequence,
+                window: cm.window as xcb_window_t,
+                type_: cm.message_type as xcb_atom_t,
+                data,
+            })
+        }
+        _ => null_mut(),
+    };
+}
+
+/// Copies an event into memory allocated the way xcb allocates the events it returns, so
+/// it's freed the same way
+#[cfg(target_os = "linux")]
+unsafe fn alloc_xcb_event<T>(event: T) -> *mut xcb_generic_event_t {
+    let size = std::mem::size_of::<xcb_generic_event_t>().max(std::mem::size_of::<T>());
+    let ptr = libc::calloc(1, size) as *mut T;
+
+    if !ptr.is_null() {
+        ptr::write(ptr, event);
+    }
+
+    return ptr as *mut xcb_generic_event_t;
+}
+
+/// Looks up the text a key press types, None for keys that don't type anything
+#[cfg(target_os = "linux")]
+unsafe fn lookup_text(ic: xlib::XIC, key: *mut xlib::XKeyEvent) -> Option<String> {
+    let text = if ic.is_null() {
+        let mut buffer = [0u8; 32];
+        let len = xlib::XLookupString(
+            key,
+            buffer.as_mut_ptr() as _,
+            buffer.len() as libc::c_int,
+            null_mut(),
+            null_mut(),
+        );
+
+        // without an input context the text is latin-1, which maps directly onto chars
+        buffer[..len.max(0) as usize].iter().map(|&c| c as char).collect::<String>()
+    } else {
+        let mut buffer = vec![0u8; 32];
+        let mut status = 0;
+        let mut len = xlib::Xutf8LookupString(
+            ic,
+            key,
+            buffer.as_mut_ptr() as _,
+            buffer.len() as libc::c_int,
+            null_mut(),
+            ptr::addr_of_mut!(status),
+        );
+
+        // committed input method text can be longer than a key usually types
+        if status == xlib::XBufferOverflow {
+            buffer = vec![0u8; len as usize];
+            len = xlib::Xutf8LookupString(
+                ic,
+                key,
+                buffer.as_mut_ptr() as _,
+                buffer.len() as libc::c_int,
+                null_mut(),
+                ptr::addr_of_mut!(status),
+            );
+        }
+
+        if status != xlib::XLookupChars && status != xlib::XLookupBoth {
+            return None;
+        }
+
+        String::from_utf8_lossy(&buffer[..len.max(0) as usize]).into_owned()
+    };
+
+    // keys like backspace and enter type control characters, those are left to KeyDown
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+
+    if text.is_empty() {
+        return None;
+    }
+
+    return Some(text);
+}
+
+/// Xlib's XEventsQueued mode that only checks events Xlib has already read
+#[cfg(target_os = "linux")]
+const QUEUED_ALREADY: libc::c_int = 0;
+
+#[cfg(target_os = "linux")]
+impl TPlatformWindow for PlatformWindow {
+    /// creates the window, on the display connection of another window if one is given
+    fn new(config: &WindowBuilder, sharing: Option<&PlatformWindow>) -> Result<PlatformWindow> {
+        let (width, height, x, y) = (config.width, config.height, config.x, config.y);
+
+        let shared = match sharing {
+            Some(other) => other.shared.clone(),
+            None => Rc::new(XConnection::open()?),
+        };
+
+        unsafe {
+            let connection = shared.connection;
+            let screen = shared.screen;
+
+            let win: u32 = xcb_generate_id(connection);
+
+            let event_mask = XCB_CW_BACK_PIXEL | XCB_CW_EVENT_MASK;
+
+            let event_values: u32 = XCB_EVENT_MASK_BUTTON_PRESS
+                | XCB_EVENT_MASK_BUTTON_RELEASE
+                | XCB_EVENT_MASK_KEY_PRESS
+                | XCB_EVENT_MASK_KEY_RELEASE
+                | XCB_EVENT_MASK_EXPOSURE
+                | XCB_EVENT_MASK_POINTER_MOTION
+                | XCB_EVENT_MASK_STRUCTURE_NOTIFY
+                | XCB_EVENT_MASK_FOCUS_CHANGE
+                | XCB_EVENT_MASK_ENTER_WINDOW
+                | XCB_EVENT_MASK_LEAVE_WINDOW;
+
+            let value_list: [u32; 2] = [(*screen).black_pixel, event_values];
+
+            xcb_create_window(
+                connection,
+                XCB_COPY_FROM_PARENT as u8,
+                win,
+                (*screen).root,
+                x,
+                y,
+                width,
+                height,
+                0,
+                XCB_WINDOW_CLASS_INPUT_OUTPUT as u16,
+                (*screen).root_visual,
+                event_mask,
+                ptr::addr_of!(value_list[0]),
+            );
+
+            set_window_hints(connection, win, config);
+
+            // without this the window manager kills the client instead of asking it to close
+            set_property_32(
+                connection,
+                win,
+                shared.wm_protocols,
+                XCB_ATOM_ATOM,
+                &[shared.wm_delete_win],
+            );
+
+            let mut preedit = Box::<PreeditState>::default();
+            let ic = create_input_context(shared.im, win, ptr::addr_of_mut!(*preedit));
+
+            // the input method may need more events than we select, like key releases
+            if !ic.is_null() {
+                let mut filter_events: libc::c_long = 0;
+                xlib::XGetICValues(
+                    ic,
+                    xlib::XNFilterEvents_0.as_ptr(),
+                    ptr::addr_of_mut!(filter_events),
+                    null_mut::<libc::c_void>(),
+                );
+
+                let event_values = event_values | filter_events as u32;
+                xcb_change_window_attributes(connection, win, XCB_CW_EVENT_MASK, ptr::addr_of!(event_values));
+            }
+
+            shared.register(win);
+            xcb_map_window(connection, win);
+
+            let res = xcb_flush(connection);
+
+            if res <= 0 {
+                log_error!("Failed to flush stream (xcb connection)");
+            }
+
+            return Ok(PlatformWindow {
+                shared,
+                window: win,
+                width,
+                height,
+                x,
+                y,
+                ic,
+                preedit,
+                keys_down: [false; 256],
+            });
+        }
+    }
+
+    #[inline]
+    fn get_id(&self) -> WindowId {
+        return WindowId(self.window as u64);
+    }
+
+    fn update(&mut self, ev_que: &mut EventDeque) {
+        unsafe {
+            self.shared.pump();
+
+            while let Some(pending) = self.shared.take_pending(self.window) {
+                match pending {
+                    PendingEvent::Xcb(event) => {
+                        let first_new = ev_que.len();
+                        self.handle_event(event, ev_que);
+
+                        if let Some(time) = x_event_time(event) {
+                            let time = self.shared.clock.borrow_mut().instant_of(time);
+
+                            for event in ev_que.range_mut(first_new..) {
+                                event.time = time;
+                            }
+                        }
+
+                        libc::free(event as _);
+                    }
+                    PendingEvent::Xlib(mut xevent) => {
+                        if let Some(text) = lookup_text(self.ic, ptr::addr_of_mut!(xevent.key)) {
+                            let time = self.shared.clock.borrow_mut().instant_of(xevent.key.time as u32);
+                            ev_que.push_back(Event::at(EventType::TextInput { text }, time));
+                        }
+                    }
+                }
+            }
+
+            // composition may also have changed while other windows were being updated
+            ev_que.extend(self.preedit.events.drain(..).map(Event::new));
+        }
+    }
+
+    fn set_fullscreen(&mut self, mode: Option<Fullscreen>) {
+        // _NET_WM_STATE actions, see the EWMH spec
+        const NET_WM_STATE_REMOVE: u32 = 0;
+        const NET_WM_STATE_ADD: u32 = 1;
+        const SOURCE_APPLICATION: u32 = 1;
+
+        unsafe {
+            match mode {
+                Some(Fullscreen::Exclusive) => {
+                    set_property_32(
+                        self.shared.connection,
+                        self.window,
+                        self.shared.net_wm_bypass_compositor,
+                        XCB_ATOM_CARDINAL,
+                        &[1],
+                    );
+                }
+                _ => {
+                    xcb_delete_property(self.shared.connection, self.window, self.shared.net_wm_bypass_compositor);
+                }
+            }
+
+            let action = if mode.is_some() {
+                NET_WM_STATE_ADD
+            } else {
+                NET_WM_STATE_REMOVE
+            };
+
+            // Once mapped, the window manager owns _NET_WM_STATE so the change has to be requested
+            self.send_root_message(
+                self.shared.net_wm_state,
+                [action, self.shared.net_wm_state_fullscreen, 0, SOURCE_APPLICATION, 0],
+            );
+
+            xcb_flush(self.shared.connection);
+        }
+    }
+
+    fn get_modifiers(&self) -> Modifiers {
+        unsafe {
+            let cookie = xcb_query_pointer(self.shared.connection, self.window);
+            let reply = xcb_query_pointer_reply(self.shared.connection, cookie, null_mut());
+
+            if reply.is_null() {
+                log_error!("Failed to query the pointer for the modifier state");
+                return Modifiers::default();
+            }
+
+            let modifiers = modifiers_from_state((*reply).mask);
+            libc::free(reply as _);
+
+            return modifiers;
+        }
+    }
+
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        let keycode = u8::try_from(scancode.0.checked_add(8)?).ok()?;
+
+        unsafe {
+            // the group is the layout currently in use
+            let mut state: xlib::XkbStateRec = std::mem::zeroed();
+            xlib::XkbGetState(self.shared.display, XKB_USE_CORE_KBD, ptr::addr_of_mut!(state));
+
+            let keysym = xlib::XkbKeycodeToKeysym(self.shared.display, keycode, state.group as libc::c_int, 0);
+            return keysym_label(keysym as u32);
+        }
+    }
+
+    /// Grabs the pointer confined to the window, which fails while another client has it
+    /// grabbed or the window isn't shown
+    fn set_cursor_grab(&mut self, grab: bool) {
+        unsafe {
+            if grab {
+                // owner events keeps the events going to our windows as they normally would
+                let cookie = xcb_grab_pointer(
+                    self.shared.connection,
+                    1,
+                    self.window,
+                    0,
+                    XCB_GRAB_MODE_ASYNC as u8,
+                    XCB_GRAB_MODE_ASYNC as u8,
+                    self.window,
+                    XCB_CURSOR_NONE,
+                    XCB_CURRENT_TIME,
+                );
+
+                let reply = xcb_grab_pointer_reply(self.shared.connection, cookie, null_mut());
+
+                if reply.is_null() || (*reply).status as u32 != XCB_GRAB_STATUS_SUCCESS {
+                    log_warn!("Failed to grab the cursor");
+                }
+                libc::free(reply as _);
+            } else {
+                xcb_ungrab_pointer(self.shared.connection, XCB_CURRENT_TIME);
+            }
+
+            xcb_flush(self.shared.connection);
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        unsafe {
+            // without a cursor of its own the window uses its parent's
+            let cursor = if visible {
+                XCB_CURSOR_NONE
+            } else {
+                self.shared.blank_cursor()
+            };
+
+            xcb_change_window_attributes(self.shared.connection, self.window, XCB_CW_CURSOR, ptr::addr_of!(cursor));
+            xcb_flush(self.shared.connection);
+        }
+    }
+
+    /// destroys the window
+    fn destroy(&self) {
+        unsafe {
+            if !self.ic.is_null() {
+                xlib::XDestroyIC(self.ic);
+            }
+
+            self.shared.unregister(self.window);
+            xcb_destroy_window(self.shared.connection, self.window);
+            xcb_flush(self.shared.connection);
+        }
+    }
+}
+
+/// The Wayland display connection and what comes with it, shared by every window created
+/// from it and closed when the last of them is dropped
+#[cfg(target_os = "linux")]
+struct WlConnection {
+    libs: &'static wl::WaylandLibs,
+    display: *mut wl::wl_display,
+    registry: *mut wl::wl_proxy,
+    // the globals, bound as the registry announces them
+    compositor: Cell<*mut wl::wl_proxy>,
+    wm_base: Cell<*mut wl::wl_proxy>,
+    seat: Cell<*mut wl::wl_proxy>,
+    // optional, without it the compositor decides whether windows get a title bar
+    decoration_manager: Cell<*mut wl::wl_proxy>,
+    // optional, without them there's no raw motion or cursor grab
+    relative_pointer_manager: Cell<*mut wl::wl_proxy>,
+    pointer_constraints: Cell<*mut wl::wl_proxy>,
+    input: RefCell<WlInput>,
+    // the seat's events are routed to these by the surface they happened on
+    windows: RefCell<HashMap<usize, Rc<RefCell<WlWindowState>>>>,
+    // maps the compositor's input event times to Instants
+    clock: RefCell<EventClock>,
+    lost: Cell<bool>,
+}
+
+/// How waiting on a Wayland connection has to go
+#[cfg(target_os = "linux")]
+enum WlWait {
+    /// Poll the fd, a read has been prepared
+    Fd(libc::c_int),
+    /// Events are already queued, there's nothing to wait for
+    Ready,
+    /// The connection is gone, so there's nothing to wait on
+    Lost,
+}
+
+/// The keyboard and pointer of the seat, and what's needed to make sense of them
+#[cfg(target_os = "linux")]
+struct WlInput {
+    pointer: *mut wl::wl_proxy,
+    // null when the compositor has no relative pointer manager
+    relative_pointer: *mut wl::wl_proxy,
+    keyboard: *mut wl::wl_proxy,
+    xkb_context: *mut wl::xkb_context,
+    // null until the compositor sends the keymap
+    keymap: *mut wl::xkb_keymap,
+    xkb_state: *mut wl::xkb_state,
+    modifiers: Modifiers,
+    // the surfaces with keyboard and pointer focus, 0 for none
+    keyboard_focus: usize,
+    pointer_focus: usize,
+    // the cursor can only be changed with the serial of the pointer entering
+    pointer_serial: u32,
+    // key repeat is left to clients on Wayland, the compositor only gives the rate and delay.
+    // A rate of 0 turns repeat off
+    repeat_rate: i32,
+    repeat_delay: i32,
+    repeat: Option<(u32, Instant)>,
+}
+
+/// The key repeat used when the seat is too old to send repeat_info, in keys per second
+/// and milliseconds, matching the usual X server defaults
+#[cfg(target_os = "linux")]
+const WL_DEFAULT_REPEAT_RATE: i32 = 25;
+#[cfg(target_os = "linux")]
+const WL_DEFAULT_REPEAT_DELAY: i32 = 600;
+
+/// What the listeners of a window update, drained by the window's update
+#[cfg(target_os = "linux")]
+struct WlWindowState {
+    libs: &'static wl::WaylandLibs,
+    events: VecDeque<Event>,
+    width: u16,
+    height: u16,
+    // the size of the configure that's being sent, applied once it's complete
+    pending_size: Option<(u16, u16)>,
+    configured: bool,
+    cursor_hidden: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl WlConnection {
+    /// Connects to the compositor named by WAYLAND_DISPLAY and binds the globals
+    fn open() -> Result<Rc<WlConnection>> {
+        let libs = match wl::libs() {
+            Some(libs) => libs,
+            None => {
+                return Err(Error::WindowCreation(String::from(
+                    "libwayland-client or libxkbcommon couldn't be loaded",
+                )));
+            }
+        };
+
+        unsafe {
+            let display = (libs.wl_display_connect)(null());
+
+            if display.is_null() {
+                return Err(Error::WindowCreation(String::from(
+                    "couldn't connect to the Wayland compositor",
+                )));
+            }
+
+            let shared = Rc::new(WlConnection {
+                libs,
+                display,
+                registry: libs.get_registry(display),
+                compositor: Cell::new(null_mut()),
+                wm_base: Cell::new(null_mut()),
+                seat: Cell::new(null_mut()),
+                decoration_manager: Cell::new(null_mut()),
+                relative_pointer_manager: Cell::new(null_mut()),
+                pointer_constraints: Cell::new(null_mut()),
+                input: RefCell::new(WlInput {
+                    pointer: null_mut(),
+                    relative_pointer: null_mut(),
+                    keyboard: null_mut(),
+                    xkb_context: (libs.xkb_context_new)(0),
+                    keymap: null_mut(),
+                    xkb_state: null_mut(),
+                    modifiers: Modifiers::default(),
+                    keyboard_focus: 0,
+                    pointer_focus: 0,
+                    pointer_serial: 0,
+                    repeat_rate: WL_DEFAULT_REPEAT_RATE,
+                    repeat_delay: WL_DEFAULT_REPEAT_DELAY,
+                    repeat: None,
+                }),
+                windows: RefCell::new(HashMap::new()),
+                clock: RefCell::new(EventClock::default()),
+                lost: Cell::new(false),
+            });
+
+            (libs.wl_proxy_add_listener)(
+                shared.registry,
+                ptr::addr_of!(REGISTRY_LISTENER) as _,
+                Rc::as_ptr(&shared) as _,
+            );
+
+            // the first roundtrip announces the globals, the second gets the seat's
+            // capabilities and the keymap
+            (libs.wl_display_roundtrip)(display);
+
+            if shared.compositor.get().is_null() || shared.wm_base.get().is_null() {
+                return Err(Error::WindowCreation(String::from(
+                    "the Wayland compositor doesn't support xdg-shell",
+                )));
+            }
+
+            (libs.wl_display_roundtrip)(display);
+
+            return Ok(shared);
+        }
+    }
+
+    /// Reads whatever the compositor has sent without blocking, and dispatches it to the
+    /// listeners, which queue the events up for their windows
+    fn pump(&self) {
+        if self.lost.get() {
+            return;
+        }
+
+        let libs = self.libs;
+
+        unsafe {
+            // a read can only be prepared once everything already read has been dispatched
+            while (libs.wl_display_prepare_read)(self.display) != 0 {
+                (libs.wl_display_dispatch_pending)(self.display);
+            }
+
+            (libs.wl_display_flush)(self.display);
+
+            let mut poll_fd = libc::pollfd {
+                fd: (libs.wl_display_get_fd)(self.display),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+
+            if libc::poll(ptr::addr_of_mut!(poll_fd), 1, 0) > 0 {
+                (libs.wl_display_read_events)(self.display);
+            } else {
+                (libs.wl_display_cancel_read)(self.display);
+            }
+
+            if (libs.wl_display_dispatch_pending)(self.display) < 0 {
+                log_error!("The connection to the Wayland compositor was lost");
+                self.lost.set(true);
+
+                for state in self.windows.borrow().values() {
+                    state.borrow_mut().events.push_back(Event::new(EventType::WinClose));
+                }
+                return;
+            }
+        }
+
+        self.repeat_key();
+    }
+
+    /// Sends a KeyDown for the held key once its repeat is due, at most one per update
+    /// so a slow frame doesn't flood the queue
+    fn repeat_key(&self) {
+        let mut input = self.input.borrow_mut();
+
+        let (keycode, due) = match input.repeat {
+            Some(repeat) => repeat,
+            None => return,
+        };
+
+        let now = Instant::now();
+        if now < due {
+            return;
+        }
+
+        if input.repeat_rate <= 0 {
+            input.repeat = None;
+            return;
+        }
+
+        let interval = Duration::from_secs(1) / input.repeat_rate as u32;
+        input.repeat = Some((keycode, (due + interval).max(now)));
+
+        unsafe {
+            let focus = input.keyboard_focus;
+
+            // stamped with when the repeat was due rather than when it was noticed
+            self.push_event_at(
+                focus,
+                EventType::KeyDown {
+                    key: Key::from(input.keysym(self.libs, keycode)),
+                    scancode: ScanCode(keycode - 8),
+                    modifiers: input.modifiers,
+                    is_repeat: true,
+                },
+                due,
+            );
+
+            if let Some(text) = input.key_text(self.libs, keycode) {
+                self.push_event_at(focus, EventType::TextInput { text }, due);
+            }
+        }
+    }
+
+    /// When the held key is next due to repeat
+    fn next_repeat(&self) -> Option<Instant> {
+        return self.input.borrow().repeat.map(|(_, due)| due);
+    }
+
+    /// Prepares to block until the compositor sends something, a read prepared here has
+    /// to be finished with finish_wait
+    fn prepare_wait(&self) -> WlWait {
+        if self.lost.get() {
+            return WlWait::Lost;
+        }
+
+        let libs = self.libs;
+
+        unsafe {
+            while (libs.wl_display_prepare_read)(self.display) != 0 {
+                (libs.wl_display_dispatch_pending)(self.display);
+            }
+
+            (libs.wl_display_flush)(self.display);
+
+            if self.windows.borrow().values().any(|state| !state.borrow().events.is_empty()) {
+                (libs.wl_display_cancel_read)(self.display);
+                return WlWait::Ready;
+            }
+
+            return WlWait::Fd((libs.wl_display_get_fd)(self.display));
+        }
+    }
+
+    /// Reads what arrived while waiting, it's dispatched by the next update
+    fn finish_wait(&self, readable: bool) {
+        unsafe {
+            if readable {
+                (self.libs.wl_display_read_events)(self.display);
+            } else {
+                (self.libs.wl_display_cancel_read)(self.display);
+            }
+        }
+    }
+
+    /// Queues an event for the window with the given surface, if it's still open
+    #[inline]
+    fn push_event(&self, surface: usize, e_type: EventType) {
+        self.push_event_at(surface, e_type, Instant::now());
+    }
+
+    /// Queues an event that happened at time, see push_event
+    fn push_event_at(&self, surface: usize, e_type: EventType, time: Instant) {
+        if let Some(state) = self.windows.borrow().get(&surface) {
+            state.borrow_mut().events.push_back(Event::at(e_type, time));
+        }
+    }
+
+    /// Converts the millisecond time of an input event
+    #[inline]
+    fn event_time(&self, time: u32) -> Instant {
+        return self.clock.borrow_mut().instant_of(time);
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for WlConnection {
+    fn drop(&mut self) {
+        let libs = self.libs;
+
+        unsafe {
+            let input = self.input.get_mut();
+
+            if !input.relative_pointer.is_null() {
+                libs.request(input.relative_pointer, wl::ZWP_RELATIVE_POINTER_V1_DESTROY, true);
+            }
+            release_input_device(libs, input.pointer, wl::WL_POINTER_RELEASE);
+            release_input_device(libs, input.keyboard, wl::WL_KEYBOARD_RELEASE);
+
+            if !input.xkb_state.is_null() {
+                (libs.xkb_state_unref)(input.xkb_state);
+                (libs.xkb_keymap_unref)(input.keymap);
+            }
+            if !input.xkb_context.is_null() {
+                (libs.xkb_context_unref)(input.xkb_context);
+            }
+
+            release_input_device(libs, self.seat.get(), wl::WL_SEAT_RELEASE);
+
+            if !self.decoration_manager.get().is_null() {
+                libs.request(self.decoration_manager.get(), wl::ZXDG_DECORATION_MANAGER_V1_DESTROY, true);
+            }
+            if !self.relative_pointer_manager.get().is_null() {
+                libs.request(
+                    self.relative_pointer_manager.get(),
+                    wl::ZWP_RELATIVE_POINTER_MANAGER_V1_DESTROY,
+                    true,
+                );
+            }
+            if !self.pointer_constraints.get().is_null() {
+                libs.request(self.pointer_constraints.get(), wl::ZWP_POINTER_CONSTRAINTS_V1_DESTROY, true);
+            }
+            if !self.wm_base.get().is_null() {
+                libs.request(self.wm_base.get(), wl::XDG_WM_BASE_DESTROY, true);
+            }
+            if !self.compositor.get().is_null() {
+                (libs.wl_proxy_destroy)(self.compositor.get());
+            }
+
+            (libs.wl_proxy_destroy)(self.registry);
+            (libs.wl_display_disconnect)(self.display);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl WlInput {
+    /// Replaces the keymap, and the state tracking it
+    unsafe fn set_keymap(&mut self, libs: &wl::WaylandLibs, keymap: *mut wl::xkb_keymap) {
+        if !self.xkb_state.is_null() {
+            (libs.xkb_state_unref)(self.xkb_state);
+            (libs.xkb_keymap_unref)(self.keymap);
+        }
+
+        self.keymap = keymap;
+        self.xkb_state = (libs.xkb_state_new)(keymap);
+    }
+
+    /// The unshifted keysym of a key, shift is reported through the modifiers like on X11
+    unsafe fn keysym(&self, libs: &wl::WaylandLibs, keycode: u32) -> u32 {
+        let layout = (libs.xkb_state_key_get_layout)(self.xkb_state, keycode);
+        let mut syms: *const u32 = null();
+
+        if (libs.xkb_keymap_key_get_syms_by_level)(self.keymap, keycode, layout, 0, ptr::addr_of_mut!(syms)) > 0 {
+            return *syms;
+        }
+
+        return 0;
+    }
+
+    /// The text a key types with the current modifiers
+    unsafe fn key_text(&self, libs: &wl::WaylandLibs, keycode: u32) -> Option<String> {
+        let mut buffer = [0u8; 64];
+        let len = (libs.xkb_state_key_get_utf8)(self.xkb_state, keycode, buffer.as_mut_ptr() as _, buffer.len());
+
+        let len = (len.max(0) as usize).min(buffer.len() - 1);
+        let text = String::from_utf8_lossy(&buffer[..len]);
+
+        // keys like backspace and enter type control characters, those are left to KeyDown
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+
+        if text.is_empty() {
+            return None;
+        }
+
+        return Some(text);
+    }
+
+    unsafe fn update_modifiers(&mut self, libs: &wl::WaylandLibs) {
+        let is_active = |name: &CStr| {
+            (libs.xkb_state_mod_name_is_active)(self.xkb_state, name.as_ptr(), wl::XKB_STATE_MODS_EFFECTIVE) > 0
+        };
+
+        // the names xkbcommon gives the real modifiers, see xkbcommon-names.h
+        self.modifiers = Modifiers {
+            shift: is_active(c"Shift"),
+            ctrl: is_active(c"Control"),
+            alt: is_active(c"Mod1"),
+            super_key: is_active(c"Mod4"),
+            caps_lock: is_active(c"Lock"),
+            num_lock: is_active(c"Mod2"),
+        };
+    }
+}
+
+/// Releases a keyboard, pointer or seat, which can only be done politely from version 3
+/// (version 5 for a seat), older ones are just forgotten
+#[cfg(target_os = "linux")]
+unsafe fn release_input_device(libs: &wl::WaylandLibs, proxy: *mut wl::wl_proxy, release: u32) {
+    if proxy.is_null() {
+        return;
+    }
+
+    let polite_from = if release == wl::WL_SEAT_RELEASE { 5 } else { 3 };
+
+    if (libs.wl_proxy_get_version)(proxy) >= polite_from {
+        libs.request(proxy, release, true);
+    } else {
+        (libs.wl_proxy_destroy)(proxy);
+    }
+}
+
+#[cfg(target_os = "linux")]
+static REGISTRY_LISTENER: wl::wl_registry_listener = wl::wl_registry_listener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn registry_global(
+    data: *mut libc::c_void,
+    registry: *mut wl::wl_proxy,
+    name: u32,
+    interface: *const libc::c_char,
+    version: u32,
+) {
+    let shared = &*(data as *const WlConnection);
+    let libs = shared.libs;
+
+    match CStr::from_ptr(interface).to_bytes() {
+        b"wl_compositor" => {
+            shared.compositor.set(libs.bind(registry, name, libs.wl_compositor_interface, version.min(4)));
+        }
+        b"xdg_wm_base" => {
+            let wm_base = libs.bind(registry, name, ptr::addr_of!(wl::XDG_WM_BASE_INTERFACE), 1);
+            (libs.wl_proxy_add_listener)(wm_base, ptr::addr_of!(WM_BASE_LISTENER) as _, data);
+            shared.wm_base.set(wm_base);
+        }
+        b"zxdg_decoration_manager_v1" => {
+            shared.decoration_manager.set(libs.bind(
+                registry,
+                name,
+                ptr::addr_of!(wl::ZXDG_DECORATION_MANAGER_V1_INTERFACE),
+                1,
+            ));
+        }
+        b"zwp_relative_pointer_manager_v1" => {
+            shared.relative_pointer_manager.set(libs.bind(
+                registry,
+                name,
+                ptr::addr_of!(wl::ZWP_RELATIVE_POINTER_MANAGER_V1_INTERFACE),
+                1,
+            ));
+        }
+        b"zwp_pointer_constraints_v1" => {
+            shared.pointer_constraints.set(libs.bind(
+                registry,
+                name,
+                ptr::addr_of!(wl::ZWP_POINTER_CONSTRAINTS_V1_INTERFACE),
+                1,
+            ));
+        }
+        // only the first seat is used, like X11's core keyboard and pointer
+        b"wl_seat" if shared.seat.get().is_null() => {
+            let seat = libs.bind(registry, name, libs.wl_seat_interface, version.min(5));
+            (libs.wl_proxy_add_listener)(seat, ptr::addr_of!(SEAT_LISTENER) as _, data);
+            shared.seat.set(seat);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn registry_global_remove(_data: *mut libc::c_void, _registry: *mut wl::wl_proxy, _name: u32) {}
+
+#[cfg(target_os = "linux")]
+static WM_BASE_LISTENER: wl::xdg_wm_base_listener = wl::xdg_wm_base_listener { ping: wm_base_ping };
+
+/// The compositor checks the client is still responsive
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn wm_base_ping(data: *mut libc::c_void, wm_base: *mut wl::wl_proxy, serial: u32) {
+    let shared = &*(data as *const WlConnection);
+    shared.libs.request_uint(wm_base, wl::XDG_WM_BASE_PONG, serial);
+}
+
+#[cfg(target_os = "linux")]
+static SEAT_LISTENER: wl::wl_seat_listener = wl::wl_seat_listener {
+    capabilities: seat_capabilities,
+    name: seat_name,
+};
+
+/// Gets or releases the keyboard and pointer as they're plugged in and out
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn seat_capabilities(data: *mut libc::c_void, seat: *mut wl::wl_proxy, capabilities: u32) {
+    let shared = &*(data as *const WlConnection);
+    let libs = shared.libs;
+    let mut input = shared.input.borrow_mut();
+
+    let has_pointer = capabilities & wl::WL_SEAT_CAPABILITY_POINTER != 0;
+
+    if has_pointer && input.pointer.is_null() {
+        input.pointer = libs.create(seat, wl::WL_SEAT_GET_POINTER, libs.wl_pointer_interface);
+        (libs.wl_proxy_add_listener)(input.pointer, ptr::addr_of!(POINTER_LISTENER) as _, data);
+
+        // the globals are all bound by the first roundtrip, before the seat's capabilities
+        let manager = shared.relative_pointer_manager.get();
+        if !manager.is_null() {
+            input.relative_pointer = libs.get_relative_pointer(manager, input.pointer);
+            (libs.wl_proxy_add_listener)(
+                input.relative_pointer,
+                ptr::addr_of!(RELATIVE_POINTER_LISTENER) as _,
+                data,
+            );
+        }
+    } else if !has_pointer && !input.pointer.is_null() {
+        if !input.relative_pointer.is_null() {
+            libs.request(input.relative_pointer, wl::ZWP_RELATIVE_POINTER_V1_DESTROY, true);
+            input.relative_pointer = null_mut();
+        }
+        release_input_device(libs, input.pointer, wl::WL_POINTER_RELEASE);
+        input.pointer = null_mut();
+        input.pointer_focus = 0;
+    }
+
+    let has_keyboard = capabilities & wl::WL_SEAT_CAPABILITY_KEYBOARD != 0;
+
+    if has_keyboard && input.keyboard.is_null() {
+        input.keyboard = libs.create(seat, wl::WL_SEAT_GET_KEYBOARD, libs.wl_keyboard_interface);
+        (libs.wl_proxy_add_listener)(input.keyboard, ptr::addr_of!(KEYBOARD_LISTENER) as _, data);
+    } else if !has_keyboard && !input.keyboard.is_null() {
+        release_input_device(libs, input.keyboard, wl::WL_KEYBOARD_RELEASE);
+        input.keyboard = null_mut();
+        input.keyboard_focus = 0;
+        input.repeat = None;
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn seat_name(_data: *mut libc::c_void, _seat: *mut wl::wl_proxy, _name: *const libc::c_char) {}
+
+#[cfg(target_os = "linux")]
+static POINTER_LISTENER: wl::wl_pointer_listener = wl::wl_pointer_listener {
+    enter: pointer_enter,
+    leave: pointer_leave,
+    motion: pointer_motion,
+    button: pointer_button,
+    axis: pointer_axis,
+    frame: pointer_frame,
+    axis_source: pointer_axis_source,
+    axis_stop: pointer_axis_stop,
+    axis_discrete: pointer_axis_discrete,
+};
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_enter(
+    data: *mut libc::c_void,
+    pointer: *mut wl::wl_proxy,
+    serial: u32,
+    surface: *mut wl::wl_proxy,
+    x: wl::wl_fixed_t,
+    y: wl::wl_fixed_t,
+) {
+    let shared = &*(data as *const WlConnection);
+
+    {
+        let mut input = shared.input.borrow_mut();
+        input.pointer_focus = surface as usize;
+        input.pointer_serial = serial;
+    }
+
+    // the cursor is set each time the pointer enters, so a hidden one has to be hidden again
+    let hidden = match shared.windows.borrow().get(&(surface as usize)) {
+        Some(state) => state.borrow().cursor_hidden,
+        None => false,
+    };
+    if hidden {
+        shared.libs.set_cursor(pointer, serial, null_mut());
+    }
+
+    shared.push_event(surface as usize, EventType::MouseEnter);
+    shared.push_event(
+        surface as usize,
+        EventType::MouseMove {
+            x: wl::fixed_to_f64(x) as i16,
+            y: wl::fixed_to_f64(y) as i16,
+        },
+    );
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_leave(
+    data: *mut libc::c_void,
+    _pointer: *mut wl::wl_proxy,
+    _serial: u32,
+    surface: *mut wl::wl_proxy,
+) {
+    let shared = &*(data as *const WlConnection);
+    shared.input.borrow_mut().pointer_focus = 0;
+    shared.push_event(surface as usize, EventType::MouseLeave);
+}
+
+/// Wayland has no global coordinates, the position is always relative to the window
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_motion(
+    data: *mut libc::c_void,
+    _pointer: *mut wl::wl_proxy,
+    time: u32,
+    x: wl::wl_fixed_t,
+    y: wl::wl_fixed_t,
+) {
+    let shared = &*(data as *const WlConnection);
+    let focus = shared.input.borrow().pointer_focus;
+
+    shared.push_event_at(
+        focus,
+        EventType::MouseMove {
+            x: wl::fixed_to_f64(x) as i16,
+            y: wl::fixed_to_f64(y) as i16,
+        },
+        shared.event_time(time),
+    );
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_button(
+    data: *mut libc::c_void,
+    _pointer: *mut wl::wl_proxy,
+    _serial: u32,
+    time: u32,
+    button: u32,
+    state: u32,
+) {
+    let shared = &*(data as *const WlConnection);
+    let (focus, modifiers) = {
+        let input = shared.input.borrow();
+        (input.pointer_focus, input.modifiers)
+    };
+
+    let pressed = state == wl::WL_POINTER_BUTTON_STATE_PRESSED;
+
+    let e_type = match (button, pressed) {
+        (wl::BTN_LEFT, true) => EventType::MouseLeftBtnDown { modifiers },
+        (wl::BTN_LEFT, false) => EventType::MouseLeftBtnUp { modifiers },
+        (wl::BTN_MIDDLE, true) => EventType::MouseMidBtnDown { modifiers },
+        (wl::BTN_MIDDLE, false) => EventType::MouseMidBtnUp { modifiers },
+        (wl::BTN_RIGHT, true) => EventType::MouseRightBtnDown { modifiers },
+        (wl::BTN_RIGHT, false) => EventType::MouseRightBtnUp { modifiers },
+        _ => return,
+    };
+
+    shared.push_event_at(focus, e_type, shared.event_time(time));
+}
+
+/// The scroll distance of one wheel notch, in the surface pixels the axis is measured in
+#[cfg(target_os = "linux")]
+const WHEEL_NOTCH_DISTANCE: f64 = 10.0;
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_axis(
+    data: *mut libc::c_void,
+    _pointer: *mut wl::wl_proxy,
+    time: u32,
+    axis: u32,
+    value: wl::wl_fixed_t,
+) {
+    let shared = &*(data as *const WlConnection);
+    let focus = shared.input.borrow().pointer_focus;
+
+    // positive values scroll down and right, the opposite of MouseWheel's vertical axis
+    let notches = (wl::fixed_to_f64(value) / WHEEL_NOTCH_DISTANCE) as f32;
+
+    let e_type = match axis {
+        wl::WL_POINTER_AXIS_VERTICAL_SCROLL => EventType::MouseWheel {
+            delta_x: 0.0,
+            delta_y: -notches,
+        },
+        wl::WL_POINTER_AXIS_HORIZONTAL_SCROLL => EventType::MouseWheel {
+            delta_x: notches,
+            delta_y: 0.0,
+        },
+        _ => return,
+    };
+
+    shared.push_event_at(focus, e_type, shared.event_time(time));
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_frame(_data: *mut libc::c_void, _pointer: *mut wl::wl_proxy) {}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_axis_source(_data: *mut libc::c_void, _pointer: *mut wl::wl_proxy, _source: u32) {}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_axis_stop(_data: *mut libc::c_void, _pointer: *mut wl::wl_proxy, _time: u32, _axis: u32) {}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn pointer_axis_discrete(
+    _data: *mut libc::c_void,
+    _pointer: *mut wl::wl_proxy,
+    _axis: u32,
+    _discrete: i32,
+) {
+}
+
+#[cfg(target_os = "linux")]
+static RELATIVE_POINTER_LISTENER: wl::zwp_relative_pointer_v1_listener = wl::zwp_relative_pointer_v1_listener {
+    relative_motion: relative_pointer_motion,
+};
+
+/// Raw motion goes to the window under the cursor, as the rest of the pointer's events do
+#[cfg(target_os = "linux")]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn relative_pointer_motion(
+    data: *mut libc::c_void,
+    _relative_pointer: *mut wl::wl_proxy,
+    utime_hi: u32,
+    utime_lo: u32,
+    _dx: wl::wl_fixed_t,
+    _dy: wl::wl_fixed_t,
+    dx_unaccel: wl::wl_fixed_t,
+    dy_unaccel: wl::wl_fixed_t,
+) {
+    let shared = &*(data as *const WlConnection);
+    let focus = shared.input.borrow().pointer_focus;
+
+    // the time is in microseconds, the other input events are stamped in milliseconds
+    let time = (((utime_hi as u64) << 32 | utime_lo as u64) / 1000) as u32;
+
+    shared.push_event_at(
+        focus,
+        EventType::MouseRawMotion {
+            delta_x: wl::fixed_to_f64(dx_unaccel) as f32,
+            delta_y: wl::fixed_to_f64(dy_unaccel) as f32,
+        },
+        shared.event_time(time),
+    );
+}
+
+#[cfg(target_os = "linux")]
+static KEYBOARD_LISTENER: wl::wl_keyboard_listener = wl::wl_keyboard_listener {
+    keymap: keyboard_keymap,
+    enter: keyboard_enter,
+    leave: keyboard_leave,
+    key: keyboard_key,
+    modifiers: keyboard_modifiers,
+    repeat_info: keyboard_repeat_info,
+};
+
+/// The keymap is shared as a file, mapped here and compiled by xkbcommon
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn keyboard_keymap(
+    data: *mut libc::c_void,
+    _keyboard: *mut wl::wl_proxy,
+    format: u32,
+    fd: libc::c_int,
+    size: u32,
+) {
+    let shared = &*(data as *const WlConnection);
+    let libs = shared.libs;
+
+    if format == wl::WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1 {
+        let map = libc::mmap(null_mut(), size as usize, libc::PROT_READ, libc::MAP_PRIVATE, fd, 0);
+
+        if map == libc::MAP_FAILED {
+            log_error!("Failed to map the keymap from the Wayland compositor");
+        } else {
+            let mut input = shared.input.borrow_mut();
+            let keymap = (libs.xkb_keymap_new_from_string)(
+                input.xkb_context,
+                map as *const libc::c_char,
+                wl::XKB_KEYMAP_FORMAT_TEXT_V1,
+                0,
+            );
+            libc::munmap(map, size as usize);
+
+            if keymap.is_null() {
+                log_error!("The keymap from the Wayland compositor couldn't be compiled");
+            } else {
+                input.set_keymap(libs, keymap);
+            }
+        }
+    }
+
+    libc::close(fd);
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn keyboard_enter(
+    data: *mut libc::c_void,
+    _keyboard: *mut wl::wl_proxy,
+    _serial: u32,
+    surface: *mut wl::wl_proxy,
+    _keys: *mut wl::wl_array,
+) {
+    let shared = &*(data as *const WlConnection);
+    shared.input.borrow_mut().keyboard_focus = surface as usize;
+    shared.push_event(surface as usize, EventType::WinFocusGained);
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn keyboard_leave(
+    data: *mut libc::c_void,
+    _keyboard: *mut wl::wl_proxy,
+    _serial: u32,
+    surface: *mut wl::wl_proxy,
+) {
+    let shared = &*(data as *const WlConnection);
+
+    {
+        let mut input = shared.input.borrow_mut();
+        input.keyboard_focus = 0;
+        input.repeat = None;
+    }
+
+    shared.push_event(surface as usize, EventType::WinFocusLost);
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn keyboard_key(
+    data: *mut libc::c_void,
+    _keyboard: *mut wl::wl_proxy,
+    _serial: u32,
+    time: u32,
+    key: u32,
+    state: u32,
+) {
+    let shared = &*(data as *const WlConnection);
+    let libs = shared.libs;
+    let time = shared.event_time(time);
+    let mut input = shared.input.borrow_mut();
+
+    if input.xkb_state.is_null() {
+        return;
+    }
+
+    // Wayland sends evdev codes, xkb keycodes are offset by 8 like X11's
+    let keycode = key + 8;
+    let scancode = ScanCode(key);
+    let key = Key::from(input.keysym(libs, keycode));
+    let focus = input.keyboard_focus;
+
+    if state == wl::WL_KEYBOARD_KEY_STATE_PRESSED {
+        shared.push_event_at(
+            focus,
+            EventType::KeyDown {
+                key,
+                scancode,
+                modifiers: input.modifiers,
+                is_repeat: false,
+            },
+            time,
+        );
+
+        if let Some(text) = input.key_text(libs, keycode) {
+            shared.push_event_at(focus, EventType::TextInput { text }, time);
+        }
+
+        if input.repeat_rate > 0 && (libs.xkb_keymap_key_repeats)(input.keymap, keycode) != 0 {
+            let due = time + Duration::from_millis(input.repeat_delay.max(0) as u64);
+            input.repeat = Some((keycode, due));
+        }
+    } else {
+        if matches!(input.repeat, Some((repeating, _)) if repeating == keycode) {
+            input.repeat = None;
+        }
+
+        shared.push_event_at(
+            focus,
+            EventType::KeyUp {
+                key,
+                scancode,
+                modifiers: input.modifiers,
+            },
+            time,
+        );
+    }
+}
+
 #[cfg(target_os = "linux")]
-impl From<u32> for Key {
-    fn from(val: u32) -> Self {
-        return match val {
-            x11::keysym::XK_BackSpace => Key::Backspace,
-            x11::keysym::XK_Return => Key::Enter,
-            x11::keysym::XK_Tab => Key::Tab,
-            x11::keysym::XK_Pause => Key::Pause,
-            x11::keysym::XK_Caps_Lock => Key::Capital,
-            x11::keysym::XK_Escape => Key::Escape,
-            x11::keysym::XK_Mode_switch => Key::ModeChange,
-            x11::keysym::XK_space => Key::Space,
-            x11::keysym::XK_Prior => Key::Prior,
-            x11::keysym::XK_Next => Key::Next,
-            x11::keysym::XK_End => Key::End,
-            x11::keysym::XK_Home => Key::Home,
-            x11::keysym::XK_Left => Key::Left,
-            x11::keysym::XK_Up => Key::Up,
-            x11::keysym::XK_Right => Key::Right,
-            x11::keysym::XK_Down => Key::Down,
-            x11::keysym::XK_Select => Key::Select,
-            x11::keysym::XK_Print => Key::Print,
-            x11::keysym::XK_Execute => Key::Execute,
-            x11::keysym::XK_Insert => Key::Insert,
-            x11::keysym::XK_Delete => Key::Delete,
-            x11::keysym::XK_Help => Key::Help,
-            x11::keysym::XK_Meta_L => Key::Lwin,
-            x11::keysym::XK_Meta_R => Key::Rwin,
-            x11::keysym::XK_KP_0 => Key::Numpad0,
-            x11::keysym::XK_KP_1 => Key::Numpad1,
-            x11::keysym::XK_KP_2 => Key::Numpad2,
-            x11::keysym::XK_KP_3 => Key::Numpad3,
-            x11::keysym::XK_KP_4 => Key::Numpad4,
-            x11::keysym::XK_KP_5 => Key::Numpad5,
-            x11::keysym::XK_KP_6 => Key::Numpad6,
-            x11::keysym::XK_KP_7 => Key::Numpad7,
-            x11::keysym::XK_KP_8 => Key::Numpad8,
-            x11::keysym::XK_KP_9 => Key::Numpad9,
-            x11::keysym::XK_multiply => Key::Multiply,
-            x11::keysym::XK_KP_Add => Key::Add,
-            x11::keysym::XK_KP_Separator => Key::Separator,
-            x11::keysym::XK_KP_Subtract => Key::Subtract,
-            x11::keysym::XK_KP_Decimal => Key::Decimal,
-            x11::keysym::XK_KP_Divide => Key::Divide,
-            x11::keysym::XK_F1 => Key::F1,
-            x11::keysym::XK_F2 => Key::F2,
-            x11::keysym::XK_F3 => Key::F3,
-            x11::keysym::XK_F4 => Key::F4,
-            x11::keysym::XK_F5 => Key::F5,
-            x11::keysym::XK_F6 => Key::F6,
-            x11::keysym::XK_F7 => Key::F7,
-            x11::keysym::XK_F8 => Key::F8,
-            x11::keysym::XK_F9 => Key::F9,
-            x11::keysym::XK_F10 => Key::F10,
-            x11::keysym::XK_F11 => Key::F11,
-            x11::keysym::XK_F12 => Key::F12,
-            x11::keysym::XK_F13 => Key::F13,
-            x11::keysym::XK_F14 => Key::F14,
-            x11::keysym::XK_F15 => Key::F15,
-            x11::keysym::XK_F16 => Key::F16,
-            x11::keysym::XK_F17 => Key::F17,
-            x11::keysym::XK_F18 => Key::F18,
-            x11::keysym::XK_F19 => Key::F19,
-            x11::keysym::XK_F20 => Key::F20,
-            x11::keysym::XK_F21 => Key::F21,
-            x11::keysym::XK_F22 => Key::F22,
-            x11::keysym::XK_F23 => Key::F23,
-            x11::keysym::XK_F24 => Key::F24,
-            x11::keysym::XK_Num_Lock => Key::Numlock,
-            x11::keysym::XK_Scroll_Lock => Key::ScrollLock,
-            x11::keysym::XK_KP_Equal => Key::NumpadEqual,
-            x11::keysym::XK_Shift_L => Key::LShift,
-            x11::keysym::XK_Shift_R => Key::RShift,
-            x11::keysym::XK_Control_L => Key::LControl,
-            x11::keysym::XK_Control_R => Key::RControl,
-            x11::keysym::XK_Alt_L => Key::LAlt,
-            x11::keysym::XK_Alt_R => Key::RAlt,
-            x11::keysym::XK_semicolon => Key::Semicolon,
-            x11::keysym::XK_plus => Key::Plus,
-            x11::keysym::XK_comma => Key::Comma,
-            x11::keysym::XK_minus => Key::Minus,
-            x11::keysym::XK_period => Key::Period,
-            x11::keysym::XK_slash => Key::Slash,
-            x11::keysym::XK_grave => Key::Grave,
-            x11::keysym::XK_a | x11::keysym::XK_A => Key::A,
-            x11::keysym::XK_b | x11::keysym::XK_B => Key::B,
-            x11::keysym::XK_c | x11::keysym::XK_C => Key::C,
-            x11::keysym::XK_d | x11::keysym::XK_D => Key::D,
-            x11::keysym::XK_e | x11::keysym::XK_E => Key::E,
-            x11::keysym::XK_f | x11::keysym::XK_F => Key::F,
-            x11::keysym::XK_g | x11::keysym::XK_G => Key::G,
-            x11::keysym::XK_h | x11::keysym::XK_H => Key::H,
-            x11::keysym::XK_i | x11::keysym::XK_I => Key::I,
-            x11::keysym::XK_j | x11::keysym::XK_J => Key::J,
-            x11::keysym::XK_k | x11::keysym::XK_K => Key::K,
-            x11::keysym::XK_l | x11::keysym::XK_L => Key::L,
-            x11::keysym::XK_m | x11::keysym::XK_M => Key::M,
-            x11::keysym::XK_n | x11::keysym::XK_N => Key::N,
-            x11::keysym::XK_o | x11::keysym::XK_O => Key::O,
-            x11::keysym::XK_p | x11::keysym::XK_P => Key::P,
-            x11::keysym::XK_q | x11::keysym::XK_Q => Key::Q,
-            x11::keysym::XK_r | x11::keysym::XK_R => Key::R,
-            x11::keysym::XK_s | x11::keysym::XK_S => Key::S,
-            x11::keysym::XK_t | x11::keysym::XK_T => Key::T,
-            x11::keysym::XK_u | x11::keysym::XK_U => Key::U,
-            x11::keysym::XK_v | x11::keysym::XK_V => Key::V,
-            x11::keysym::XK_w | x11::keysym::XK_W => Key::W,
-            x11::keysym::XK_x | x11::keysym::XK_X => Key::X,
-            x11::keysym::XK_y | x11::keysym::XK_Y => Key::Y,
-            x11::keysym::XK_z | x11::keysym::XK_Z => Key::Z,
-            _ => Key::None,
-        };
+unsafe extern "C" fn keyboard_modifiers(
+    data: *mut libc::c_void,
+    _keyboard: *mut wl::wl_proxy,
+    _serial: u32,
+    depressed: u32,
+    latched: u32,
+    locked: u32,
+    group: u32,
+) {
+    let shared = &*(data as *const WlConnection);
+    let libs = shared.libs;
+    let mut input = shared.input.borrow_mut();
+
+    if input.xkb_state.is_null() {
+        return;
     }
+
+    (libs.xkb_state_update_mask)(input.xkb_state, depressed, latched, locked, 0, 0, group);
+    input.update_modifiers(libs);
 }
 
 #[cfg(target_os = "linux")]
-#[inline]
-pub fn sleep(ms: u32) {
-    thread::sleep(time::Duration::from_millis(ms as u64));
+unsafe extern "C" fn keyboard_repeat_info(data: *mut libc::c_void, _keyboard: *mut wl::wl_proxy, rate: i32, delay: i32) {
+    let shared = &*(data as *const WlConnection);
+    let mut input = shared.input.borrow_mut();
+
+    input.repeat_rate = rate;
+    input.repeat_delay = delay;
+
+    // the key already held stops repeating too
+    if rate <= 0 {
+        input.repeat = None;
+    }
 }
 
 #[cfg(target_os = "linux")]
-pub struct PlatformWindow {
-    pub display: *mut xlib::Display,
-    pub connection: *mut xcb_connection_t,
-    pub window: u32,
-    screen: *mut xcb_screen_t,
-    wm_protocols: xcb_atom_t,
-    wm_delete_win: xcb_atom_t,
-}
+static XDG_SURFACE_LISTENER: wl::xdg_surface_listener = wl::xdg_surface_listener {
+    configure: xdg_surface_configure,
+};
 
+/// Ends a configure sequence, the size it carried is applied once it's acknowledged
 #[cfg(target_os = "linux")]
-impl TPlatformWindow for PlatformWindow {
-    /// creates the window
-    fn new(name: &'static str, width: u16, height: u16, x: i16, y: i16) -> Option<PlatformWindow> {
-        unsafe {
-            let display = xlib::XOpenDisplay(null());
+unsafe extern "C" fn xdg_surface_configure(data: *mut libc::c_void, xdg_surface: *mut wl::wl_proxy, serial: u32) {
+    let mut state = (*(data as *const RefCell<WlWindowState>)).borrow_mut();
 
-            if display.is_null() {
-                log_fatal!("Could not get display");
-                return None;
-            }
+    state.libs.request_uint(xdg_surface, wl::XDG_SURFACE_ACK_CONFIGURE, serial);
 
-            xlib::XAutoRepeatOff(display);
+    if let Some((width, height)) = state.pending_size.take() {
+        if width != state.width || height != state.height {
+            state.width = width;
+            state.height = height;
+            state.events.push_back(Event::new(EventType::WinResize { width, height }));
+        }
+    }
 
-            let connection: *mut xcb_connection_t =
-                x11::xlib_xcb::XGetXCBConnection(display) as *mut xcb_connection_t;
+    // the first configure is what lets the window be drawn, any later one wants a new frame
+    state.configured = true;
+    state.events.push_back(Event::new(EventType::WinExpose));
+}
 
-            if xcb_connection_has_error(connection) != 0 {
-                log_fatal!("Unable to connect to X server, have you set one up?");
-                return None;
-            }
+#[cfg(target_os = "linux")]
+static XDG_TOPLEVEL_LISTENER: wl::xdg_toplevel_listener = wl::xdg_toplevel_listener {
+    configure: xdg_toplevel_configure,
+    close: xdg_toplevel_close,
+};
 
-            let setup = xcb_get_setup(connection);
-            let screen: *mut xcb_screen_t;
+/// A size of 0 leaves it to the client, which keeps the size it has
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn xdg_toplevel_configure(
+    data: *mut libc::c_void,
+    _toplevel: *mut wl::wl_proxy,
+    width: i32,
+    height: i32,
+    _states: *mut wl::wl_array,
+) {
+    let mut state = (*(data as *const RefCell<WlWindowState>)).borrow_mut();
 
-            {
-                let mut iterator = xcb_setup_roots_iterator(setup);
-                screen = iterator.data;
-            }
+    if width > 0 && height > 0 {
+        state.pending_size = Some((width.min(u16::MAX as i32) as u16, height.min(u16::MAX as i32) as u16));
+    }
+}
 
-            let win: u32 = xcb_generate_id(connection);
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn xdg_toplevel_close(data: *mut libc::c_void, _toplevel: *mut wl::wl_proxy) {
+    let mut state = (*(data as *const RefCell<WlWindowState>)).borrow_mut();
+    state.events.push_back(Event::new(EventType::WinClose));
+}
 
-            let event_mask = XCB_CW_BACK_PIXEL | XCB_CW_EVENT_MASK;
+/// A window on a Wayland compositor, made of a wl_surface given the xdg-shell toplevel role.
+/// Wayland doesn't let clients know or pick where their windows are, so the position is
+/// left at what the WindowBuilder asked for and WinMove is never sent
+#[cfg(target_os = "linux")]
+struct WaylandWindow {
+    shared: Rc<WlConnection>,
+    surface: *mut wl::wl_proxy,
+    xdg_surface: *mut wl::wl_proxy,
+    toplevel: *mut wl::wl_proxy,
+    // null when the compositor has no decoration manager
+    decoration: *mut wl::wl_proxy,
+    // null unless the cursor is grabbed
+    confined_pointer: *mut wl::wl_proxy,
+    state: Rc<RefCell<WlWindowState>>,
+}
 
-            let event_values: u32 = XCB_EVENT_MASK_BUTTON_PRESS
-                | XCB_EVENT_MASK_BUTTON_RELEASE
-                | XCB_EVENT_MASK_KEY_PRESS
-                | XCB_EVENT_MASK_KEY_RELEASE
-                | XCB_EVENT_MASK_EXPOSURE
-                | XCB_EVENT_MASK_POINTER_MOTION
-                | XCB_EVENT_MASK_STRUCTURE_NOTIFY;
+#[cfg(target_os = "linux")]
+impl TPlatformWindow for WaylandWindow {
+    /// creates the window, on the connection of another window if one is given
+    fn new(config: &WindowBuilder, sharing: Option<&WaylandWindow>) -> Result<WaylandWindow> {
+        let shared = match sharing {
+            Some(other) => other.shared.clone(),
+            None => WlConnection::open()?,
+        };
 
-            let value_list: [u32; 2] = [(*screen).black_pixel, event_values];
+        let libs = shared.libs;
 
-            let cookie = xcb_create_window(
-                connection,
-                XCB_COPY_FROM_PARENT as u8,
-                win,
-                (*screen).root,
-                x,
-                y,
-                width,
-                height,
-                0,
-                XCB_WINDOW_CLASS_INPUT_OUTPUT as u16,
-                (*screen).root_visual,
-                event_mask,
-                ptr::addr_of!(value_list[0]),
+        unsafe {
+            let surface = libs.create(
+                shared.compositor.get(),
+                wl::WL_COMPOSITOR_CREATE_SURFACE,
+                libs.wl_surface_interface,
+            );
+            let xdg_surface = libs.get_xdg_surface(shared.wm_base.get(), surface);
+            let toplevel = libs.create(
+                xdg_surface,
+                wl::XDG_SURFACE_GET_TOPLEVEL,
+                ptr::addr_of!(wl::XDG_TOPLEVEL_INTERFACE),
             );
 
-            xcb_change_property(
-                connection,
-                XCB_PROP_MODE_REPLACE as u8,
-                win,
-                XCB_ATOM_WM_NAME,
-                XCB_ATOM_STRING,
-                8 as u8,
-                name.len() as u32,
-                name.as_ptr() as _,
+            let state = Rc::new(RefCell::new(WlWindowState {
+                libs,
+                events: VecDeque::new(),
+                width: config.width,
+                height: config.height,
+                pending_size: None,
+                configured: false,
+                cursor_hidden: false,
+            }));
+
+            (libs.wl_proxy_add_listener)(
+                xdg_surface,
+                ptr::addr_of!(XDG_SURFACE_LISTENER) as _,
+                Rc::as_ptr(&state) as _,
+            );
+            (libs.wl_proxy_add_listener)(
+                toplevel,
+                ptr::addr_of!(XDG_TOPLEVEL_LISTENER) as _,
+                Rc::as_ptr(&state) as _,
             );
 
-            let del_str = b"WM_DELETE_WINDOW";
+            let title = CString::new(config.title.as_str()).unwrap_or_default();
+            libs.request_ptr(toplevel, wl::XDG_TOPLEVEL_SET_TITLE, title.as_ptr() as _);
 
-            let wm_delete_cookie =
-                xcb_intern_atom(connection, 0, del_str.len() as u16, del_str.as_ptr() as _);
+            // the app id is what compositors group windows and find their desktop file by
+            if let Some((_, class)) = &config.wm_class {
+                let app_id = CString::new(class.as_str()).unwrap_or_default();
+                libs.request_ptr(toplevel, wl::XDG_TOPLEVEL_SET_APP_ID, app_id.as_ptr() as _);
+            }
 
-            let proto_str = b"WM_PROTOCOLS";
+            if let Some((width, height)) = config.min_size_limit() {
+                libs.request_int_pair(toplevel, wl::XDG_TOPLEVEL_SET_MIN_SIZE, width as i32, height as i32);
+            }
+            if let Some((width, height)) = config.max_size_limit() {
+                libs.request_int_pair(toplevel, wl::XDG_TOPLEVEL_SET_MAX_SIZE, width as i32, height as i32);
+            }
+            if config.maximized {
+                libs.request(toplevel, wl::XDG_TOPLEVEL_SET_MAXIMIZED, false);
+            }
 
-            let wm_protocols_cookie = xcb_intern_atom(
-                connection,
-                0,
-                proto_str.len() as u16,
-                proto_str.as_ptr() as _,
-            );
+            // Without a decoration manager it's up to the compositor, some draw no title bar
+            // at all and expect the client to draw its own
+            let mut decoration = null_mut();
+            if !shared.decoration_manager.get().is_null() {
+                decoration = libs.get_toplevel_decoration(shared.decoration_manager.get(), toplevel);
 
-            let wm_delete_reply = xcb_intern_atom_reply(connection, wm_delete_cookie, null_mut());
+                let mode = if config.decorations {
+                    wl::ZXDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE
+                } else {
+                    wl::ZXDG_TOPLEVEL_DECORATION_V1_MODE_CLIENT_SIDE
+                };
+                libs.request_uint(decoration, wl::ZXDG_TOPLEVEL_DECORATION_V1_SET_MODE, mode);
+            }
 
-            let wm_proto_reply = xcb_intern_atom_reply(connection, wm_protocols_cookie, null_mut());
+            shared.windows.borrow_mut().insert(surface as usize, state.clone());
 
-            xcb_map_window(connection, win);
+            let window = WaylandWindow {
+                shared,
+                surface,
+                xdg_surface,
+                toplevel,
+                decoration,
+                confined_pointer: null_mut(),
+                state,
+            };
 
-            let res = xcb_flush(connection);
+            // committing without a buffer asks for the first configure, which has to be
+            // acknowledged before anything can be drawn to the surface
+            libs.request(surface, wl::WL_SURFACE_COMMIT, false);
 
-            if res <= 0 {
-                log_error!("Failed to flush stream (xcb connection)");
+            while !window.state.borrow().configured {
+                if (libs.wl_display_roundtrip)(window.shared.display) < 0 {
+                    window.destroy();
+                    return Err(Error::WindowCreation(String::from(
+                        "the Wayland compositor never configured the window",
+                    )));
+                }
             }
 
-            return Some(PlatformWindow {
-                display: display,
-                connection: connection,
-                window: win,
-                screen: screen,
-                wm_protocols: (*wm_proto_reply).atom,
-                wm_delete_win: (*wm_delete_reply).atom,
-            });
+            return Ok(window);
         }
     }
 
-    fn update(&self, ev_que: &mut EventDeque) {
-        let mut event: *mut xcb_generic_event_t;
-        let mut cm: *mut xcb_client_message_event_t;
+    #[inline]
+    fn get_id(&self) -> WindowId {
+        return WindowId(self.surface as u64);
+    }
 
-        loop {
-            unsafe {
-                event = xcb_poll_for_event(self.connection);
-                if event.is_null() {
-                    break;
-                }
+    fn update(&mut self, ev_que: &mut EventDeque) {
+        self.shared.pump();
+        ev_que.extend(self.state.borrow_mut().events.drain(..));
+    }
 
-                let event_enum: u8 = (*event).response_type as u8 & 0x7f;
+    /// Both modes are the same to xdg-shell, the compositor picks the output
+    fn set_fullscreen(&mut self, mode: Option<Fullscreen>) {
+        let libs = self.shared.libs;
 
-                match event_enum {
-                    XCB_KEY_PRESS => {
-                        let kb_event = event as *const xcb_key_press_event_t;
+        unsafe {
+            match mode {
+                Some(_) => libs.request_ptr(self.toplevel, wl::XDG_TOPLEVEL_SET_FULLSCREEN, null()),
+                None => libs.request(self.toplevel, wl::XDG_TOPLEVEL_UNSET_FULLSCREEN, false),
+            }
 
-                        let key = xlib::XKeycodeToKeysym(
-                            self.display,
-                            (*kb_event).detail as u8,
-                            (((*kb_event).detail as u32) & xlib::ShiftMask) as i32,
-                        );
+            (libs.wl_display_flush)(self.shared.display);
+        }
+    }
 
-                        ev_que.push_back(Event {
-                            e_type: EventType::KeyDown,
-                            data: EventData {
-                                m_u32: key as u32,
-                            }
-                        });
-                    }
-                    XCB_KEY_RELEASE => {
-                        let kb_event = event as *const xcb_key_press_event_t;
-
-                        let key = xlib::XKeycodeToKeysym(
-                            self.display,
-                            (*kb_event).detail as u8,
-                            (((*kb_event).detail as u32) & xlib::ShiftMask) as i32,
-                        );
-
-                        ev_que.push_back(Event {
-                            e_type: EventType::KeyUp,
-                            data: EventData {
-                                m_u32: key as u32,
-                            }
-                        });
-                    }
-                    XCB_MOTION_NOTIFY => {
-                        let motion = event as *const xcb_motion_notify_event_t;
-                        ev_que.push_back(Event {
-                            e_type: EventType::MouseMove,
-                            data: EventData {
-                                m_i32: [
-                                    (*motion).root_x as i32,
-                                    (*motion).root_y as i32
-                                ]
-                            }
-                        });
-                    }
-                    XCB_BUTTON_PRESS => {
-                        let button_event = event as *mut xcb_button_press_event_t;
-
-                        match (*button_event).detail as u32 {
-                            XCB_BUTTON_INDEX_1 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseLeftBtnDown,
-                                    data: EventData::default(),
-                                });
-                            }
-                            XCB_BUTTON_INDEX_2 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseMidBtnDown,
-                                    data: EventData::default(),
-                                });
-                            }
-                            XCB_BUTTON_INDEX_3 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseRightBtnDown,
-                                    data: EventData::default(),
-                                });
-                            }
-                            _ => {}
-                        }
-                    }
-                    XCB_BUTTON_RELEASE => {
-                        let button_event = event as *mut xcb_button_press_event_t;
-
-                        match (*button_event).detail as u32 {
-                            XCB_BUTTON_INDEX_1 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseLeftBtnUp,
-                                    data: EventData::default(),
-                                });
-                            }
-                            XCB_BUTTON_INDEX_2 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseMidBtnUp,
-                                    data: EventData::default(),
-                                });
-                            }
-                            XCB_BUTTON_INDEX_3 => {
-                                ev_que.push_back(Event {
-                                    e_type: EventType::MouseRightBtnUp,
-                                    data: EventData::default(),
-                                });
-                            }
-                            _ => {}
-                        }
-                    }
-                    XCB_CLIENT_MESSAGE => {
-                        cm = event as *mut xcb_client_message_event_t;
+    #[inline]
+    fn get_modifiers(&self) -> Modifiers {
+        return self.shared.input.borrow().modifiers;
+    }
 
-                        log_info!("Client Message");
+    fn get_key_label(&self, scancode: ScanCode) -> Option<String> {
+        let input = self.shared.input.borrow();
 
-                        if (*cm).data.data32()[0] == self.wm_delete_win {
-                            ev_que.push_back(Event {
-                                e_type: EventType::WinClose,
-                                data: EventData::default(),
-                            });
-                        }
-                    }
-                    _ => {}
+        // there's no keymap until the compositor has sent one
+        if input.xkb_state.is_null() {
+            return None;
+        }
+
+        let keysym = unsafe { input.keysym(self.shared.libs, scancode.0.checked_add(8)?) };
+        return keysym_label(keysym);
+    }
+
+    /// The compositor keeps the pointer confined for as long as the window lives, lifting
+    /// it while the window isn't focused
+    fn set_cursor_grab(&mut self, grab: bool) {
+        let libs = self.shared.libs;
+
+        unsafe {
+            if grab && self.confined_pointer.is_null() {
+                let constraints = self.shared.pointer_constraints.get();
+                let pointer = self.shared.input.borrow().pointer;
+
+                if constraints.is_null() || pointer.is_null() {
+                    log_warn!("The Wayland compositor can't confine the cursor");
+                    return;
                 }
 
-                libc::free(event as _);
+                self.confined_pointer = libs.confine_pointer(
+                    constraints,
+                    self.surface,
+                    pointer,
+                    wl::ZWP_POINTER_CONSTRAINTS_V1_LIFETIME_PERSISTENT,
+                );
+            } else if !grab && !self.confined_pointer.is_null() {
+                libs.request(self.confined_pointer, wl::ZWP_CONFINED_POINTER_V1_DESTROY, true);
+                self.confined_pointer = null_mut();
+            }
+
+            (libs.wl_display_flush)(self.shared.display);
+        }
+    }
+
+    /// Hiding takes effect straight away. There's no cursor theme to set it back from, so
+    /// once shown again the cursor comes back when it next enters the window
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.state.borrow_mut().cursor_hidden = !visible;
+
+        let input = self.shared.input.borrow();
+
+        if !visible && !input.pointer.is_null() && input.pointer_focus == self.surface as usize {
+            unsafe {
+                self.shared.libs.set_cursor(input.pointer, input.pointer_serial, null_mut());
+                (self.shared.libs.wl_display_flush)(self.shared.display);
             }
         }
     }
 
     /// destroys the window
     fn destroy(&self) {
+        let libs = self.shared.libs;
+        let key = self.surface as usize;
+
+        {
+            let mut input = self.shared.input.borrow_mut();
+
+            if input.keyboard_focus == key {
+                input.keyboard_focus = 0;
+                input.repeat = None;
+            }
+            if input.pointer_focus == key {
+                input.pointer_focus = 0;
+            }
+        }
+
+        self.shared.windows.borrow_mut().remove(&key);
+
         unsafe {
-            xlib::XAutoRepeatOn(self.display);
-            xcb_destroy_window(self.connection, self.window);
+            if !self.confined_pointer.is_null() {
+                libs.request(self.confined_pointer, wl::ZWP_CONFINED_POINTER_V1_DESTROY, true);
+            }
+            if !self.decoration.is_null() {
+                libs.request(self.decoration, wl::ZXDG_TOPLEVEL_DECORATION_V1_DESTROY, true);
+            }
+
+            libs.request(self.toplevel, wl::XDG_TOPLEVEL_DESTROY, true);
+            libs.request(self.xdg_surface, wl::XDG_SURFACE_DESTROY, true);
+            libs.request(self.surface, wl::WL_SURFACE_DESTROY, true);
+
+            (libs.wl_display_flush)(self.shared.display);
         }
     }
 }
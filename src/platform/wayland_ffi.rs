@@ -0,0 +1,571 @@
+//! The parts of libwayland-client, libxkbcommon and the Wayland protocols the backend uses,
+//! loaded when first needed rather than linked so the binary still runs with only X11
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+use std::ptr::{null, null_mut};
+use std::sync::OnceLock;
+
+pub enum wl_display {}
+pub enum wl_proxy {}
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+
+/// A 24.8 fixed point number
+pub type wl_fixed_t = i32;
+
+#[repr(C)]
+pub struct wl_message {
+    pub name: *const c_char,
+    pub signature: *const c_char,
+    pub types: *const *const wl_interface,
+}
+
+#[repr(C)]
+pub struct wl_interface {
+    pub name: *const c_char,
+    pub version: c_int,
+    pub method_count: c_int,
+    pub methods: *const wl_message,
+    pub event_count: c_int,
+    pub events: *const wl_message,
+}
+
+#[repr(C)]
+pub struct wl_array {
+    pub size: usize,
+    pub alloc: usize,
+    pub data: *mut c_void,
+}
+
+// the interface tables are immutable, libwayland only reads them
+unsafe impl Sync for wl_message {}
+unsafe impl Sync for wl_interface {}
+
+pub const WL_MARSHAL_FLAG_DESTROY: u32 = 1;
+
+// request opcodes, the position of the request in its interface
+pub const WL_COMPOSITOR_CREATE_SURFACE: u32 = 0;
+pub const WL_SURFACE_DESTROY: u32 = 0;
+pub const WL_SURFACE_COMMIT: u32 = 6;
+pub const WL_SEAT_GET_POINTER: u32 = 0;
+pub const WL_SEAT_GET_KEYBOARD: u32 = 1;
+pub const WL_SEAT_RELEASE: u32 = 3;
+pub const WL_POINTER_SET_CURSOR: u32 = 0;
+pub const WL_POINTER_RELEASE: u32 = 1;
+pub const WL_KEYBOARD_RELEASE: u32 = 0;
+pub const XDG_WM_BASE_DESTROY: u32 = 0;
+pub const XDG_WM_BASE_PONG: u32 = 3;
+pub const XDG_SURFACE_DESTROY: u32 = 0;
+pub const XDG_SURFACE_GET_TOPLEVEL: u32 = 1;
+pub const XDG_SURFACE_ACK_CONFIGURE: u32 = 4;
+pub const XDG_TOPLEVEL_DESTROY: u32 = 0;
+pub const XDG_TOPLEVEL_SET_TITLE: u32 = 2;
+pub const XDG_TOPLEVEL_SET_APP_ID: u32 = 3;
+pub const XDG_TOPLEVEL_SET_MAX_SIZE: u32 = 7;
+pub const XDG_TOPLEVEL_SET_MIN_SIZE: u32 = 8;
+pub const XDG_TOPLEVEL_SET_MAXIMIZED: u32 = 9;
+pub const XDG_TOPLEVEL_SET_FULLSCREEN: u32 = 11;
+pub const XDG_TOPLEVEL_UNSET_FULLSCREEN: u32 = 12;
+pub const ZXDG_DECORATION_MANAGER_V1_DESTROY: u32 = 0;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_DESTROY: u32 = 0;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_SET_MODE: u32 = 1;
+pub const ZWP_RELATIVE_POINTER_MANAGER_V1_DESTROY: u32 = 0;
+pub const ZWP_RELATIVE_POINTER_V1_DESTROY: u32 = 0;
+pub const ZWP_POINTER_CONSTRAINTS_V1_DESTROY: u32 = 0;
+pub const ZWP_CONFINED_POINTER_V1_DESTROY: u32 = 0;
+
+pub const WL_SEAT_CAPABILITY_POINTER: u32 = 1;
+pub const WL_SEAT_CAPABILITY_KEYBOARD: u32 = 2;
+
+pub const WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1: u32 = 1;
+pub const WL_KEYBOARD_KEY_STATE_PRESSED: u32 = 1;
+
+pub const WL_POINTER_BUTTON_STATE_PRESSED: u32 = 1;
+pub const WL_POINTER_AXIS_VERTICAL_SCROLL: u32 = 0;
+pub const WL_POINTER_AXIS_HORIZONTAL_SCROLL: u32 = 1;
+
+/// Linux input event codes of the mouse buttons
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+pub const ZXDG_TOPLEVEL_DECORATION_V1_MODE_CLIENT_SIDE: u32 = 1;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE: u32 = 2;
+
+/// The constraint comes back whenever the pointer does, rather than ending when it leaves
+pub const ZWP_POINTER_CONSTRAINTS_V1_LIFETIME_PERSISTENT: u32 = 2;
+
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+pub const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+
+/// Converts from wl_fixed_t
+#[inline]
+pub fn fixed_to_f64(value: wl_fixed_t) -> f64 {
+    return value as f64 / 256.0;
+}
+
+/// Argument types are only checked when they aren't null, and the interface of a new
+/// object is passed when it's created, so every message can share this
+struct NullTypes([*const wl_interface; 8]);
+unsafe impl Sync for NullTypes {}
+static NULL_TYPES: NullTypes = NullTypes([null(); 8]);
+
+macro_rules! message {
+    ($name:literal, $signature:literal) => {
+        wl_message {
+            name: concat!($name, "\0").as_ptr() as *const c_char,
+            signature: concat!($signature, "\0").as_ptr() as *const c_char,
+            types: NULL_TYPES.0.as_ptr(),
+        }
+    };
+}
+
+macro_rules! interface {
+    ($name:literal, $version:literal, $methods:ident, $events:ident) => {
+        wl_interface {
+            name: concat!($name, "\0").as_ptr() as *const c_char,
+            version: $version,
+            method_count: $methods.len() as c_int,
+            methods: $methods.as_ptr(),
+            event_count: $events.len() as c_int,
+            events: $events.as_ptr(),
+        }
+    };
+}
+
+static XDG_WM_BASE_REQUESTS: [wl_message; 4] = [
+    message!("destroy", ""),
+    message!("create_positioner", "n"),
+    message!("get_xdg_surface", "no"),
+    message!("pong", "u"),
+];
+static XDG_WM_BASE_EVENTS: [wl_message; 1] = [message!("ping", "u")];
+pub static XDG_WM_BASE_INTERFACE: wl_interface =
+    interface!("xdg_wm_base", 1, XDG_WM_BASE_REQUESTS, XDG_WM_BASE_EVENTS);
+
+static XDG_SURFACE_REQUESTS: [wl_message; 5] = [
+    message!("destroy", ""),
+    message!("get_toplevel", "n"),
+    message!("get_popup", "n?oo"),
+    message!("set_window_geometry", "iiii"),
+    message!("ack_configure", "u"),
+];
+static XDG_SURFACE_EVENTS: [wl_message; 1] = [message!("configure", "u")];
+pub static XDG_SURFACE_INTERFACE: wl_interface =
+    interface!("xdg_surface", 1, XDG_SURFACE_REQUESTS, XDG_SURFACE_EVENTS);
+
+static XDG_TOPLEVEL_REQUESTS: [wl_message; 14] = [
+    message!("destroy", ""),
+    message!("set_parent", "?o"),
+    message!("set_title", "s"),
+    message!("set_app_id", "s"),
+    message!("show_window_menu", "ouii"),
+    message!("move", "ou"),
+    message!("resize", "ouu"),
+    message!("set_max_size", "ii"),
+    message!("set_min_size", "ii"),
+    message!("set_maximized", ""),
+    message!("unset_maximized", ""),
+    message!("set_fullscreen", "?o"),
+    message!("unset_fullscreen", ""),
+    message!("set_minimized", ""),
+];
+static XDG_TOPLEVEL_EVENTS: [wl_message; 2] = [message!("configure", "iia"), message!("close", "")];
+pub static XDG_TOPLEVEL_INTERFACE: wl_interface =
+    interface!("xdg_toplevel", 1, XDG_TOPLEVEL_REQUESTS, XDG_TOPLEVEL_EVENTS);
+
+static ZXDG_DECORATION_MANAGER_V1_REQUESTS: [wl_message; 2] =
+    [message!("destroy", ""), message!("get_toplevel_decoration", "no")];
+static ZXDG_DECORATION_MANAGER_V1_EVENTS: [wl_message; 0] = [];
+pub static ZXDG_DECORATION_MANAGER_V1_INTERFACE: wl_interface = interface!(
+    "zxdg_decoration_manager_v1",
+    1,
+    ZXDG_DECORATION_MANAGER_V1_REQUESTS,
+    ZXDG_DECORATION_MANAGER_V1_EVENTS
+);
+
+static ZXDG_TOPLEVEL_DECORATION_V1_REQUESTS: [wl_message; 3] =
+    [message!("destroy", ""), message!("set_mode", "u"), message!("unset_mode", "")];
+static ZXDG_TOPLEVEL_DECORATION_V1_EVENTS: [wl_message; 1] = [message!("configure", "u")];
+pub static ZXDG_TOPLEVEL_DECORATION_V1_INTERFACE: wl_interface = interface!(
+    "zxdg_toplevel_decoration_v1",
+    1,
+    ZXDG_TOPLEVEL_DECORATION_V1_REQUESTS,
+    ZXDG_TOPLEVEL_DECORATION_V1_EVENTS
+);
+
+static ZWP_RELATIVE_POINTER_MANAGER_V1_REQUESTS: [wl_message; 2] =
+    [message!("destroy", ""), message!("get_relative_pointer", "no")];
+static ZWP_RELATIVE_POINTER_MANAGER_V1_EVENTS: [wl_message; 0] = [];
+pub static ZWP_RELATIVE_POINTER_MANAGER_V1_INTERFACE: wl_interface = interface!(
+    "zwp_relative_pointer_manager_v1",
+    1,
+    ZWP_RELATIVE_POINTER_MANAGER_V1_REQUESTS,
+    ZWP_RELATIVE_POINTER_MANAGER_V1_EVENTS
+);
+
+static ZWP_RELATIVE_POINTER_V1_REQUESTS: [wl_message; 1] = [message!("destroy", "")];
+static ZWP_RELATIVE_POINTER_V1_EVENTS: [wl_message; 1] = [message!("relative_motion", "uuffff")];
+pub static ZWP_RELATIVE_POINTER_V1_INTERFACE: wl_interface = interface!(
+    "zwp_relative_pointer_v1",
+    1,
+    ZWP_RELATIVE_POINTER_V1_REQUESTS,
+    ZWP_RELATIVE_POINTER_V1_EVENTS
+);
+
+static ZWP_POINTER_CONSTRAINTS_V1_REQUESTS: [wl_message; 3] = [
+    message!("destroy", ""),
+    message!("lock_pointer", "noo?ou"),
+    message!("confine_pointer", "noo?ou"),
+];
+static ZWP_POINTER_CONSTRAINTS_V1_EVENTS: [wl_message; 0] = [];
+pub static ZWP_POINTER_CONSTRAINTS_V1_INTERFACE: wl_interface = interface!(
+    "zwp_pointer_constraints_v1",
+    1,
+    ZWP_POINTER_CONSTRAINTS_V1_REQUESTS,
+    ZWP_POINTER_CONSTRAINTS_V1_EVENTS
+);
+
+static ZWP_CONFINED_POINTER_V1_REQUESTS: [wl_message; 2] = [message!("destroy", ""), message!("set_region", "?o")];
+static ZWP_CONFINED_POINTER_V1_EVENTS: [wl_message; 2] = [message!("confined", ""), message!("unconfined", "")];
+pub static ZWP_CONFINED_POINTER_V1_INTERFACE: wl_interface = interface!(
+    "zwp_confined_pointer_v1",
+    1,
+    ZWP_CONFINED_POINTER_V1_REQUESTS,
+    ZWP_CONFINED_POINTER_V1_EVENTS
+);
+
+#[repr(C)]
+pub struct wl_registry_listener {
+    pub global: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *const c_char, u32),
+    pub global_remove: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+pub struct wl_seat_listener {
+    pub capabilities: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+    pub name: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, *const c_char),
+}
+
+/// The events of wl_pointer up to version 5
+#[repr(C)]
+pub struct wl_pointer_listener {
+    pub enter: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy, wl_fixed_t, wl_fixed_t),
+    pub leave: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy),
+    pub motion: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, wl_fixed_t, wl_fixed_t),
+    pub button: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32),
+    pub axis: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, wl_fixed_t),
+    pub frame: unsafe extern "C" fn(*mut c_void, *mut wl_proxy),
+    pub axis_source: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+    pub axis_stop: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32),
+    pub axis_discrete: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, i32),
+}
+
+/// The events of wl_keyboard up to version 5
+#[repr(C)]
+pub struct wl_keyboard_listener {
+    pub keymap: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, c_int, u32),
+    pub enter: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy, *mut wl_array),
+    pub leave: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy),
+    pub key: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32),
+    pub modifiers: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32, u32),
+    pub repeat_info: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, i32, i32),
+}
+
+/// The times are the high and low halves of a microsecond timestamp, the first deltas
+/// are accelerated and the second pair are the mouse's own
+#[repr(C)]
+pub struct zwp_relative_pointer_v1_listener {
+    pub relative_motion: unsafe extern "C" fn(
+        *mut c_void,
+        *mut wl_proxy,
+        u32,
+        u32,
+        wl_fixed_t,
+        wl_fixed_t,
+        wl_fixed_t,
+        wl_fixed_t,
+    ),
+}
+
+#[repr(C)]
+pub struct xdg_wm_base_listener {
+    pub ping: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+pub struct xdg_surface_listener {
+    pub configure: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+pub struct xdg_toplevel_listener {
+    pub configure: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, i32, i32, *mut wl_array),
+    pub close: unsafe extern "C" fn(*mut c_void, *mut wl_proxy),
+}
+
+/// The functions and interfaces loaded from libwayland-client and libxkbcommon
+pub struct WaylandLibs {
+    pub wl_display_connect: unsafe extern "C" fn(*const c_char) -> *mut wl_display,
+    pub wl_display_disconnect: unsafe extern "C" fn(*mut wl_display),
+    pub wl_display_get_fd: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_display_roundtrip: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_display_flush: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_display_prepare_read: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_display_read_events: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_display_cancel_read: unsafe extern "C" fn(*mut wl_display),
+    pub wl_display_dispatch_pending: unsafe extern "C" fn(*mut wl_display) -> c_int,
+    pub wl_proxy_marshal_flags:
+        unsafe extern "C" fn(*mut wl_proxy, u32, *const wl_interface, u32, u32, ...) -> *mut wl_proxy,
+    pub wl_proxy_add_listener: unsafe extern "C" fn(*mut wl_proxy, *const c_void, *mut c_void) -> c_int,
+    pub wl_proxy_get_version: unsafe extern "C" fn(*mut wl_proxy) -> u32,
+    pub wl_proxy_destroy: unsafe extern "C" fn(*mut wl_proxy),
+
+    pub wl_registry_interface: *const wl_interface,
+    pub wl_compositor_interface: *const wl_interface,
+    pub wl_surface_interface: *const wl_interface,
+    pub wl_seat_interface: *const wl_interface,
+    pub wl_pointer_interface: *const wl_interface,
+    pub wl_keyboard_interface: *const wl_interface,
+
+    pub xkb_context_new: unsafe extern "C" fn(c_int) -> *mut xkb_context,
+    pub xkb_context_unref: unsafe extern "C" fn(*mut xkb_context),
+    pub xkb_keymap_new_from_string:
+        unsafe extern "C" fn(*mut xkb_context, *const c_char, c_int, c_int) -> *mut xkb_keymap,
+    pub xkb_keymap_unref: unsafe extern "C" fn(*mut xkb_keymap),
+    pub xkb_keymap_key_get_syms_by_level:
+        unsafe extern "C" fn(*mut xkb_keymap, u32, u32, u32, *mut *const u32) -> c_int,
+    pub xkb_keymap_key_repeats: unsafe extern "C" fn(*mut xkb_keymap, u32) -> c_int,
+    pub xkb_state_new: unsafe extern "C" fn(*mut xkb_keymap) -> *mut xkb_state,
+    pub xkb_state_unref: unsafe extern "C" fn(*mut xkb_state),
+    pub xkb_state_update_mask: unsafe extern "C" fn(*mut xkb_state, u32, u32, u32, u32, u32, u32) -> c_int,
+    pub xkb_state_key_get_layout: unsafe extern "C" fn(*mut xkb_state, u32) -> u32,
+    pub xkb_state_key_get_utf8: unsafe extern "C" fn(*mut xkb_state, u32, *mut c_char, usize) -> c_int,
+    pub xkb_state_mod_name_is_active: unsafe extern "C" fn(*mut xkb_state, *const c_char, c_int) -> c_int,
+}
+
+// the libraries stay loaded for the life of the process and the fields never change
+unsafe impl Sync for WaylandLibs {}
+unsafe impl Send for WaylandLibs {}
+
+static LIBS: OnceLock<Option<WaylandLibs>> = OnceLock::new();
+
+/// Gets the loaded libraries, loading them the first time. None if either is missing
+pub fn libs() -> Option<&'static WaylandLibs> {
+    return LIBS.get_or_init(|| unsafe { WaylandLibs::load() }).as_ref();
+}
+
+unsafe fn open_library(name: &CStr) -> Option<*mut c_void> {
+    let handle = libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+
+    if handle.is_null() {
+        return None;
+    }
+
+    return Some(handle);
+}
+
+/// Looks up a function or static in a library, T is the type of the function or a pointer
+/// to the static
+unsafe fn symbol<T>(lib: *mut c_void, name: &CStr) -> Option<T> {
+    let symbol = libc::dlsym(lib, name.as_ptr());
+
+    if symbol.is_null() {
+        return None;
+    }
+
+    return Some(std::mem::transmute_copy::<*mut c_void, T>(&symbol));
+}
+
+macro_rules! symbol {
+    ($lib:expr, $name:ident) => {
+        symbol($lib, CStr::from_bytes_with_nul_unchecked(concat!(stringify!($name), "\0").as_bytes()))?
+    };
+}
+
+impl WaylandLibs {
+    unsafe fn load() -> Option<WaylandLibs> {
+        let client = open_library(c"libwayland-client.so.0")?;
+        let xkb = open_library(c"libxkbcommon.so.0")?;
+
+        return Some(WaylandLibs {
+            wl_display_connect: symbol!(client, wl_display_connect),
+            wl_display_disconnect: symbol!(client, wl_display_disconnect),
+            wl_display_get_fd: symbol!(client, wl_display_get_fd),
+            wl_display_roundtrip: symbol!(client, wl_display_roundtrip),
+            wl_display_flush: symbol!(client, wl_display_flush),
+            wl_display_prepare_read: symbol!(client, wl_display_prepare_read),
+            wl_display_read_events: symbol!(client, wl_display_read_events),
+            wl_display_cancel_read: symbol!(client, wl_display_cancel_read),
+            wl_display_dispatch_pending: symbol!(client, wl_display_dispatch_pending),
+            wl_proxy_marshal_flags: symbol!(client, wl_proxy_marshal_flags),
+            wl_proxy_add_listener: symbol!(client, wl_proxy_add_listener),
+            wl_proxy_get_version: symbol!(client, wl_proxy_get_version),
+            wl_proxy_destroy: symbol!(client, wl_proxy_destroy),
+
+            wl_registry_interface: symbol!(client, wl_registry_interface),
+            wl_compositor_interface: symbol!(client, wl_compositor_interface),
+            wl_surface_interface: symbol!(client, wl_surface_interface),
+            wl_seat_interface: symbol!(client, wl_seat_interface),
+            wl_pointer_interface: symbol!(client, wl_pointer_interface),
+            wl_keyboard_interface: symbol!(client, wl_keyboard_interface),
+
+            xkb_context_new: symbol!(xkb, xkb_context_new),
+            xkb_context_unref: symbol!(xkb, xkb_context_unref),
+            xkb_keymap_new_from_string: symbol!(xkb, xkb_keymap_new_from_string),
+            xkb_keymap_unref: symbol!(xkb, xkb_keymap_unref),
+            xkb_keymap_key_get_syms_by_level: symbol!(xkb, xkb_keymap_key_get_syms_by_level),
+            xkb_keymap_key_repeats: symbol!(xkb, xkb_keymap_key_repeats),
+            xkb_state_new: symbol!(xkb, xkb_state_new),
+            xkb_state_unref: symbol!(xkb, xkb_state_unref),
+            xkb_state_update_mask: symbol!(xkb, xkb_state_update_mask),
+            xkb_state_key_get_layout: symbol!(xkb, xkb_state_key_get_layout),
+            xkb_state_key_get_utf8: symbol!(xkb, xkb_state_key_get_utf8),
+            xkb_state_mod_name_is_active: symbol!(xkb, xkb_state_mod_name_is_active),
+        });
+    }
+
+    /// Sends a request that takes no arguments, destroying the proxy if it's a destructor
+    pub unsafe fn request(&self, proxy: *mut wl_proxy, opcode: u32, destructor: bool) {
+        let flags = if destructor { WL_MARSHAL_FLAG_DESTROY } else { 0 };
+        (self.wl_proxy_marshal_flags)(proxy, opcode, null(), (self.wl_proxy_get_version)(proxy), flags);
+    }
+
+    /// Sends a request that creates a new object of the given interface
+    pub unsafe fn create(
+        &self,
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+    ) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            proxy,
+            opcode,
+            interface,
+            (self.wl_proxy_get_version)(proxy),
+            0,
+            null_mut::<c_void>(),
+        );
+    }
+
+    /// wl_display.get_registry
+    pub unsafe fn get_registry(&self, display: *mut wl_display) -> *mut wl_proxy {
+        // wl_display.get_registry is request 1
+        return self.create(display as *mut wl_proxy, 1, self.wl_registry_interface);
+    }
+
+    /// wl_registry.bind
+    pub unsafe fn bind(
+        &self,
+        registry: *mut wl_proxy,
+        name: u32,
+        interface: *const wl_interface,
+        version: u32,
+    ) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            registry,
+            0,
+            interface,
+            version,
+            0,
+            name,
+            (*interface).name,
+            version,
+            null_mut::<c_void>(),
+        );
+    }
+
+    /// xdg_wm_base.get_xdg_surface
+    pub unsafe fn get_xdg_surface(&self, wm_base: *mut wl_proxy, surface: *mut wl_proxy) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            wm_base,
+            2,
+            &XDG_SURFACE_INTERFACE,
+            (self.wl_proxy_get_version)(wm_base),
+            0,
+            null_mut::<c_void>(),
+            surface,
+        );
+    }
+
+    /// zxdg_decoration_manager_v1.get_toplevel_decoration
+    pub unsafe fn get_toplevel_decoration(&self, manager: *mut wl_proxy, toplevel: *mut wl_proxy) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            manager,
+            1,
+            &ZXDG_TOPLEVEL_DECORATION_V1_INTERFACE,
+            (self.wl_proxy_get_version)(manager),
+            0,
+            null_mut::<c_void>(),
+            toplevel,
+        );
+    }
+
+    /// zwp_relative_pointer_manager_v1.get_relative_pointer
+    pub unsafe fn get_relative_pointer(&self, manager: *mut wl_proxy, pointer: *mut wl_proxy) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            manager,
+            1,
+            &ZWP_RELATIVE_POINTER_V1_INTERFACE,
+            (self.wl_proxy_get_version)(manager),
+            0,
+            null_mut::<c_void>(),
+            pointer,
+        );
+    }
+
+    /// zwp_pointer_constraints_v1.confine_pointer, to the whole surface
+    pub unsafe fn confine_pointer(
+        &self,
+        constraints: *mut wl_proxy,
+        surface: *mut wl_proxy,
+        pointer: *mut wl_proxy,
+        lifetime: u32,
+    ) -> *mut wl_proxy {
+        return (self.wl_proxy_marshal_flags)(
+            constraints,
+            2,
+            &ZWP_CONFINED_POINTER_V1_INTERFACE,
+            (self.wl_proxy_get_version)(constraints),
+            0,
+            null_mut::<c_void>(),
+            surface,
+            pointer,
+            null_mut::<c_void>(),
+            lifetime,
+        );
+    }
+
+    /// wl_pointer.set_cursor, a null surface hides the cursor
+    pub unsafe fn set_cursor(&self, pointer: *mut wl_proxy, serial: u32, surface: *mut wl_proxy) {
+        (self.wl_proxy_marshal_flags)(
+            pointer,
+            WL_POINTER_SET_CURSOR,
+            null(),
+            (self.wl_proxy_get_version)(pointer),
+            0,
+            serial,
+            surface,
+            0 as c_int,
+            0 as c_int,
+        );
+    }
+
+    /// Sends a request with a single unsigned argument, like xdg_wm_base.pong
+    pub unsafe fn request_uint(&self, proxy: *mut wl_proxy, opcode: u32, value: u32) {
+        (self.wl_proxy_marshal_flags)(proxy, opcode, null(), (self.wl_proxy_get_version)(proxy), 0, value);
+    }
+
+    /// Sends a request with two integer arguments, like xdg_toplevel.set_min_size
+    pub unsafe fn request_int_pair(&self, proxy: *mut wl_proxy, opcode: u32, first: i32, second: i32) {
+        (self.wl_proxy_marshal_flags)(proxy, opcode, null(), (self.wl_proxy_get_version)(proxy), 0, first, second);
+    }
+
+    /// Sends a request with a single string or nullable object argument
+    pub unsafe fn request_ptr(&self, proxy: *mut wl_proxy, opcode: u32, value: *const c_void) {
+        (self.wl_proxy_marshal_flags)(proxy, opcode, null(), (self.wl_proxy_get_version)(proxy), 0, value);
+    }
+}
@@ -0,0 +1,926 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::error::{Error, Result};
+use crate::event::{Event, EventDeque, EventType};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use crate::keys::{Key, Modifiers, ScanCode};
+use crate::platform::WindowId;
+
+/// The start of every recording file, followed by the format version
+const MAGIC: &[u8; 4] = b"RVRC";
+const VERSION: u8 = 4;
+
+/// An event as it was recorded, placed by the frame and time since recording started
+#[derive(Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// Frames started with App::next_frame since recording started
+    pub frame: u64,
+    /// Time since recording started
+    pub offset: Duration,
+    pub window: Option<WindowId>,
+    pub e_type: EventType,
+}
+
+/// The events an App polled while it was recording, which can be saved to a file and
+/// replayed through App::start_replay
+#[derive(Clone, PartialEq, Default)]
+pub struct Recording {
+    // the app's main window when recording, mapped to the main window when replaying
+    main_window: Option<WindowId>,
+    events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    #[inline]
+    pub fn get_events(&self) -> &[RecordedEvent] {
+        return &self.events;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        return self.events.len();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.events.is_empty();
+    }
+
+    /// Gets how long the recording runs for, up to its last event
+    #[inline]
+    pub fn get_duration(&self) -> Duration {
+        return self.events.last().map_or(Duration::ZERO, |event| event.offset);
+    }
+
+    /// Writes the recording to a file, replacing it if it exists
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path).map_err(Error::RecordingIo)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write_to(&mut writer)?;
+        return writer.flush().map_err(Error::RecordingIo);
+    }
+
+    /// Reads a recording written by save
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording> {
+        let file = File::open(path).map_err(Error::RecordingIo)?;
+        return Recording::read_from(&mut BufReader::new(file));
+    }
+
+    /// Writes the recording in the format save uses
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+
+        encode_window(&mut out, self.main_window, None);
+        write_varint(&mut out, self.events.len() as u64);
+
+        let mut last_frame = 0;
+        let mut last_offset = 0;
+
+        // frames and offsets only go up, so they're stored as the difference to the last event
+        for event in self.events.iter() {
+            let offset = event.offset.as_micros() as u64;
+
+            write_varint(&mut out, event.frame - last_frame);
+            write_varint(&mut out, offset - last_offset);
+            encode_window(&mut out, event.window, self.main_window);
+            encode_event(&mut out, &event.e_type);
+
+            last_frame = event.frame;
+            last_offset = offset;
+        }
+
+        return writer.write_all(&out).map_err(Error::RecordingIo);
+    }
+
+    /// Reads a recording in the format save uses
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Recording> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(Error::RecordingIo)?;
+
+        let mut input = Decoder { data: &data, pos: 0 };
+
+        if input.bytes(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidRecording("not a rovella recording".to_string()));
+        }
+
+        let version = input.byte()?;
+        if version != VERSION {
+            return Err(Error::InvalidRecording(format!("unsupported version {}", version)));
+        }
+
+        let main_window = input.window(None)?;
+        let count = input.varint()?;
+
+        // each event takes at least four bytes, so a corrupt count can't allocate much
+        let mut events = Vec::with_capacity(count.min(data.len() as u64 / 4) as usize);
+        let mut frame: u64 = 0;
+        let mut offset: u64 = 0;
+
+        for _ in 0..count {
+            frame = frame.saturating_add(input.varint()?);
+            offset = offset.saturating_add(input.varint()?);
+
+            let window = input.window(main_window)?;
+            let e_type = input.event()?;
+
+            events.push(RecordedEvent {
+                frame,
+                offset: Duration::from_micros(offset),
+                window,
+                e_type,
+            });
+        }
+
+        if input.pos != data.len() {
+            return Err(Error::InvalidRecording("trailing data after the last event".to_string()));
+        }
+
+        return Ok(Recording { main_window, events });
+    }
+}
+
+/// Adds the events an app polls to a recording
+pub(crate) struct Recorder {
+    recording: Recording,
+    start_frame: u64,
+    start_time: Instant,
+}
+
+impl Recorder {
+    pub(crate) fn new(main_window: WindowId, frame: u64) -> Recorder {
+        Recorder {
+            recording: Recording {
+                main_window: Some(main_window),
+                events: Vec::new(),
+            },
+            start_frame: frame,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Records an event polled during frame, user events are skipped as their data
+    /// can't be written out
+    pub(crate) fn record(&mut self, event: &Event, frame: u64) {
+        if let EventType::User(_) = event.e_type {
+            return;
+        }
+
+        // events can be stamped out of order across windows, offsets are kept in order
+        let last_offset = self.recording.events.last().map_or(Duration::ZERO, |event| event.offset);
+
+        // whole microseconds, as they're saved
+        let offset = event.time.saturating_duration_since(self.start_time).as_micros() as u64;
+
+        self.recording.events.push(RecordedEvent {
+            frame: frame - self.start_frame,
+            offset: Duration::from_micros(offset).max(last_offset),
+            window: event.window,
+            e_type: event.e_type.clone(),
+        });
+    }
+
+    #[inline]
+    pub(crate) fn finish(self) -> Recording {
+        return self.recording;
+    }
+}
+
+/// Feeds a recording's events back to an app, frame by frame
+pub(crate) struct Player {
+    recording: Recording,
+    next: usize,
+    main_window: WindowId,
+    start_frame: u64,
+    start_time: Instant,
+    keep_live_events: bool,
+}
+
+impl Player {
+    pub(crate) fn new(recording: Recording, main_window: WindowId, frame: u64, keep_live_events: bool) -> Player {
+        Player {
+            recording,
+            next: 0,
+            main_window,
+            start_frame: frame,
+            start_time: Instant::now(),
+            keep_live_events,
+        }
+    }
+
+    /// Queues the events recorded up to the current frame, stamped as if they happened
+    /// as long after the replay started as they did after recording started
+    pub(crate) fn play(&mut self, frame: u64, que: &mut EventDeque) {
+        let frame = frame - self.start_frame;
+
+        while let Some(recorded) = self.recording.events.get(self.next) {
+            if recorded.frame > frame {
+                break;
+            }
+
+            let mut event = Event::at(recorded.e_type.clone(), self.start_time + recorded.offset);
+            event.window = match recorded.window {
+                Some(window) if Some(window) == self.recording.main_window => Some(self.main_window),
+                window => window,
+            };
+
+            que.push_back(event);
+            self.next += 1;
+        }
+    }
+
+    /// Whether the events the platform sends are delivered alongside the replayed ones
+    #[inline]
+    pub(crate) fn keeps_live_events(&self) -> bool {
+        return self.keep_live_events;
+    }
+
+    #[inline]
+    pub(crate) fn is_finished(&self) -> bool {
+        return self.next >= self.recording.events.len();
+    }
+}
+
+// the tags events are written with, these can't change without bumping VERSION
+const TAG_WIN_SHOW: u8 = 0;
+const TAG_WIN_HIDE: u8 = 1;
+const TAG_WIN_CLOSE: u8 = 2;
+const TAG_WIN_EXPOSE: u8 = 3;
+const TAG_WIN_FOCUS_GAINED: u8 = 4;
+const TAG_WIN_FOCUS_LOST: u8 = 5;
+const TAG_WIN_RESIZE: u8 = 6;
+const TAG_WIN_MOVE: u8 = 7;
+const TAG_KEY_DOWN: u8 = 8;
+const TAG_KEY_UP: u8 = 9;
+const TAG_MOUSE_MOVE: u8 = 10;
+const TAG_MOUSE_ENTER: u8 = 11;
+const TAG_MOUSE_LEAVE: u8 = 12;
+const TAG_MOUSE_WHEEL: u8 = 13;
+const TAG_MOUSE_MID_BTN_UP: u8 = 14;
+const TAG_MOUSE_MID_BTN_DOWN: u8 = 15;
+const TAG_MOUSE_LEFT_BTN_UP: u8 = 16;
+const TAG_MOUSE_LEFT_BTN_DOWN: u8 = 17;
+const TAG_MOUSE_RIGHT_BTN_UP: u8 = 18;
+const TAG_MOUSE_RIGHT_BTN_DOWN: u8 = 19;
+const TAG_TEXT_INPUT: u8 = 20;
+const TAG_TEXT_COMPOSITION_START: u8 = 21;
+const TAG_TEXT_COMPOSITION: u8 = 22;
+const TAG_TEXT_COMPOSITION_END: u8 = 23;
+const TAG_NONE: u8 = 24;
+const TAG_GAMEPAD_CONNECTED: u8 = 25;
+const TAG_GAMEPAD_DISCONNECTED: u8 = 26;
+const TAG_GAMEPAD_BUTTON_DOWN: u8 = 27;
+const TAG_GAMEPAD_BUTTON_UP: u8 = 28;
+const TAG_GAMEPAD_AXIS_MOTION: u8 = 29;
+const TAG_MOUSE_RAW_MOTION: u8 = 30;
+
+// how the window of an event is written, the main window is usually the only one
+const WINDOW_NONE: u8 = 0;
+const WINDOW_MAIN: u8 = 1;
+const WINDOW_OTHER: u8 = 2;
+
+/// LEB128, so the small numbers most fields hold take a byte
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    out.push(value as u8);
+}
+
+/// Zigzag encoded, so small negative numbers stay small too
+#[inline]
+fn write_signed(out: &mut Vec<u8>, value: i64) {
+    write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn write_str(out: &mut Vec<u8>, text: &str) {
+    write_varint(out, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn encode_window(out: &mut Vec<u8>, window: Option<WindowId>, main_window: Option<WindowId>) {
+    match window {
+        None => out.push(WINDOW_NONE),
+        Some(window) if Some(window) == main_window => out.push(WINDOW_MAIN),
+        Some(window) => {
+            out.push(WINDOW_OTHER);
+            write_varint(out, window.0);
+        }
+    }
+}
+
+fn encode_modifiers(modifiers: Modifiers) -> u8 {
+    return modifiers.shift as u8
+        | (modifiers.ctrl as u8) << 1
+        | (modifiers.alt as u8) << 2
+        | (modifiers.super_key as u8) << 3
+        | (modifiers.caps_lock as u8) << 4
+        | (modifiers.num_lock as u8) << 5;
+}
+
+fn decode_modifiers(bits: u8) -> Modifiers {
+    return Modifiers {
+        shift: bits & 1 != 0,
+        ctrl: bits & 1 << 1 != 0,
+        alt: bits & 1 << 2 != 0,
+        super_key: bits & 1 << 3 != 0,
+        caps_lock: bits & 1 << 4 != 0,
+        num_lock: bits & 1 << 5 != 0,
+    };
+}
+
+fn encode_event(out: &mut Vec<u8>, e_type: &EventType) {
+    match e_type {
+        EventType::WinShow => out.push(TAG_WIN_SHOW),
+        EventType::WinHide => out.push(TAG_WIN_HIDE),
+        EventType::WinClose => out.push(TAG_WIN_CLOSE),
+        EventType::WinExpose => out.push(TAG_WIN_EXPOSE),
+        EventType::WinFocusGained => out.push(TAG_WIN_FOCUS_GAINED),
+        EventType::WinFocusLost => out.push(TAG_WIN_FOCUS_LOST),
+        EventType::WinResize { width, height } => {
+            out.push(TAG_WIN_RESIZE);
+            write_varint(out, *width as u64);
+            write_varint(out, *height as u64);
+        }
+        EventType::WinMove { x, y } => {
+            out.push(TAG_WIN_MOVE);
+            write_signed(out, *x as i64);
+            write_signed(out, *y as i64);
+        }
+        EventType::KeyDown { key, scancode, modifiers, is_repeat } => {
+            out.push(TAG_KEY_DOWN);
+            write_varint(out, *key as u64);
+            write_varint(out, scancode.0 as u64);
+            out.push(encode_modifiers(*modifiers));
+            out.push(*is_repeat as u8);
+        }
+        EventType::KeyUp { key, scancode, modifiers } => {
+            out.push(TAG_KEY_UP);
+            write_varint(out, *key as u64);
+            write_varint(out, scancode.0 as u64);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseMove { x, y } => {
+            out.push(TAG_MOUSE_MOVE);
+            write_signed(out, *x as i64);
+            write_signed(out, *y as i64);
+        }
+        EventType::MouseRawMotion { delta_x, delta_y } => {
+            out.push(TAG_MOUSE_RAW_MOTION);
+            out.extend_from_slice(&delta_x.to_le_bytes());
+            out.extend_from_slice(&delta_y.to_le_bytes());
+        }
+        EventType::MouseEnter => out.push(TAG_MOUSE_ENTER),
+        EventType::MouseLeave => out.push(TAG_MOUSE_LEAVE),
+        EventType::MouseWheel { delta_x, delta_y } => {
+            out.push(TAG_MOUSE_WHEEL);
+            out.extend_from_slice(&delta_x.to_le_bytes());
+            out.extend_from_slice(&delta_y.to_le_bytes());
+        }
+        EventType::MouseMidBtnUp { modifiers } => {
+            out.push(TAG_MOUSE_MID_BTN_UP);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseMidBtnDown { modifiers } => {
+            out.push(TAG_MOUSE_MID_BTN_DOWN);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseLeftBtnUp { modifiers } => {
+            out.push(TAG_MOUSE_LEFT_BTN_UP);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseLeftBtnDown { modifiers } => {
+            out.push(TAG_MOUSE_LEFT_BTN_DOWN);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseRightBtnUp { modifiers } => {
+            out.push(TAG_MOUSE_RIGHT_BTN_UP);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::MouseRightBtnDown { modifiers } => {
+            out.push(TAG_MOUSE_RIGHT_BTN_DOWN);
+            out.push(encode_modifiers(*modifiers));
+        }
+        EventType::TextInput { text } => {
+            out.push(TAG_TEXT_INPUT);
+            write_str(out, text);
+        }
+        EventType::TextCompositionStart => out.push(TAG_TEXT_COMPOSITION_START),
+        EventType::TextComposition { text, cursor } => {
+            out.push(TAG_TEXT_COMPOSITION);
+            write_str(out, text);
+            write_varint(out, *cursor as u64);
+        }
+        EventType::TextCompositionEnd => out.push(TAG_TEXT_COMPOSITION_END),
+        EventType::GamepadConnected { id } => {
+            out.push(TAG_GAMEPAD_CONNECTED);
+            write_varint(out, id.0 as u64);
+        }
+        EventType::GamepadDisconnected { id } => {
+            out.push(TAG_GAMEPAD_DISCONNECTED);
+            write_varint(out, id.0 as u64);
+        }
+        EventType::GamepadButtonDown { id, button } => {
+            out.push(TAG_GAMEPAD_BUTTON_DOWN);
+            write_varint(out, id.0 as u64);
+            out.push(*button as u8);
+        }
+        EventType::GamepadButtonUp { id, button } => {
+            out.push(TAG_GAMEPAD_BUTTON_UP);
+            write_varint(out, id.0 as u64);
+            out.push(*button as u8);
+        }
+        EventType::GamepadAxisMotion { id, axis, value } => {
+            out.push(TAG_GAMEPAD_AXIS_MOTION);
+            write_varint(out, id.0 as u64);
+            out.push(*axis as u8);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        // never recorded
+        EventType::User(_) => out.push(TAG_NONE),
+        EventType::None => out.push(TAG_NONE),
+    }
+}
+
+/// Reads a recording back, failing on anything cut short or out of range
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.pos < len {
+            return Err(Error::InvalidRecording("unexpected end of data".to_string()));
+        }
+
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+
+        return Ok(bytes);
+    }
+
+    #[inline]
+    fn byte(&mut self) -> Result<u8> {
+        return Ok(self.bytes(1)?[0]);
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        return Err(Error::InvalidRecording("number too long".to_string()));
+    }
+
+    #[inline]
+    fn signed(&mut self) -> Result<i64> {
+        let value = self.varint()?;
+        return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        return u16::try_from(self.varint()?)
+            .map_err(|_| Error::InvalidRecording("size out of range".to_string()));
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        return i16::try_from(self.signed()?)
+            .map_err(|_| Error::InvalidRecording("position out of range".to_string()));
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        let bytes = self.bytes(4)?;
+        return Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.varint()?;
+        let len = usize::try_from(len).map_err(|_| Error::InvalidRecording("text too long".to_string()))?;
+
+        return String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| Error::InvalidRecording("text isn't valid UTF-8".to_string()));
+    }
+
+    fn key(&mut self) -> Result<Key> {
+        let index = self.varint()?;
+
+        return Key::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| Error::InvalidRecording(format!("unknown key {}", index)));
+    }
+
+    fn scancode(&mut self) -> Result<ScanCode> {
+        return u32::try_from(self.varint()?)
+            .map(ScanCode)
+            .map_err(|_| Error::InvalidRecording("scancode out of range".to_string()));
+    }
+
+    fn gamepad(&mut self) -> Result<GamepadId> {
+        return u32::try_from(self.varint()?)
+            .map(GamepadId)
+            .map_err(|_| Error::InvalidRecording("gamepad id out of range".to_string()));
+    }
+
+    fn gamepad_button(&mut self) -> Result<GamepadButton> {
+        let index = self.byte()?;
+
+        return GamepadButton::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| Error::InvalidRecording(format!("unknown gamepad button {}", index)));
+    }
+
+    fn gamepad_axis(&mut self) -> Result<GamepadAxis> {
+        let index = self.byte()?;
+
+        return GamepadAxis::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| Error::InvalidRecording(format!("unknown gamepad axis {}", index)));
+    }
+
+    #[inline]
+    fn modifiers(&mut self) -> Result<Modifiers> {
+        return Ok(decode_modifiers(self.byte()?));
+    }
+
+    fn window(&mut self, main_window: Option<WindowId>) -> Result<Option<WindowId>> {
+        return match self.byte()? {
+            WINDOW_NONE => Ok(None),
+            WINDOW_MAIN => match main_window {
+                Some(main_window) => Ok(Some(main_window)),
+                None => Err(Error::InvalidRecording("event from a missing main window".to_string())),
+            },
+            WINDOW_OTHER => Ok(Some(WindowId(self.varint()?))),
+            tag => Err(Error::InvalidRecording(format!("unknown window tag {}", tag))),
+        };
+    }
+
+    fn event(&mut self) -> Result<EventType> {
+        let e_type = match self.byte()? {
+            TAG_WIN_SHOW => EventType::WinShow,
+            TAG_WIN_HIDE => EventType::WinHide,
+            TAG_WIN_CLOSE => EventType::WinClose,
+            TAG_WIN_EXPOSE => EventType::WinExpose,
+            TAG_WIN_FOCUS_GAINED => EventType::WinFocusGained,
+            TAG_WIN_FOCUS_LOST => EventType::WinFocusLost,
+            TAG_WIN_RESIZE => EventType::WinResize {
+                width: self.u16()?,
+                height: self.u16()?,
+            },
+            TAG_WIN_MOVE => EventType::WinMove {
+                x: self.i16()?,
+                y: self.i16()?,
+            },
+            TAG_KEY_DOWN => EventType::KeyDown {
+                key: self.key()?,
+                scancode: self.scancode()?,
+                modifiers: self.modifiers()?,
+                is_repeat: self.byte()? != 0,
+            },
+            TAG_KEY_UP => EventType::KeyUp {
+                key: self.key()?,
+                scancode: self.scancode()?,
+                modifiers: self.modifiers()?,
+            },
+            TAG_MOUSE_MOVE => EventType::MouseMove {
+                x: self.i16()?,
+                y: self.i16()?,
+            },
+            TAG_MOUSE_RAW_MOTION => EventType::MouseRawMotion {
+                delta_x: self.f32()?,
+                delta_y: self.f32()?,
+            },
+            TAG_MOUSE_ENTER => EventType::MouseEnter,
+            TAG_MOUSE_LEAVE => EventType::MouseLeave,
+            TAG_MOUSE_WHEEL => EventType::MouseWheel {
+                delta_x: self.f32()?,
+                delta_y: self.f32()?,
+            },
+            TAG_MOUSE_MID_BTN_UP => EventType::MouseMidBtnUp { modifiers: self.modifiers()? },
+            TAG_MOUSE_MID_BTN_DOWN => EventType::MouseMidBtnDown { modifiers: self.modifiers()? },
+            TAG_MOUSE_LEFT_BTN_UP => EventType::MouseLeftBtnUp { modifiers: self.modifiers()? },
+            TAG_MOUSE_LEFT_BTN_DOWN => EventType::MouseLeftBtnDown { modifiers: self.modifiers()? },
+            TAG_MOUSE_RIGHT_BTN_UP => EventType::MouseRightBtnUp { modifiers: self.modifiers()? },
+            TAG_MOUSE_RIGHT_BTN_DOWN => EventType::MouseRightBtnDown { modifiers: self.modifiers()? },
+            TAG_TEXT_INPUT => EventType::TextInput { text: self.string()? },
+            TAG_TEXT_COMPOSITION_START => EventType::TextCompositionStart,
+            TAG_TEXT_COMPOSITION => EventType::TextComposition {
+                text: self.string()?,
+                cursor: self.varint()? as usize,
+            },
+            TAG_TEXT_COMPOSITION_END => EventType::TextCompositionEnd,
+            TAG_NONE => EventType::None,
+            TAG_GAMEPAD_CONNECTED => EventType::GamepadConnected { id: self.gamepad()? },
+            TAG_GAMEPAD_DISCONNECTED => EventType::GamepadDisconnected { id: self.gamepad()? },
+            TAG_GAMEPAD_BUTTON_DOWN => EventType::GamepadButtonDown {
+                id: self.gamepad()?,
+                button: self.gamepad_button()?,
+            },
+            TAG_GAMEPAD_BUTTON_UP => EventType::GamepadButtonUp {
+                id: self.gamepad()?,
+                button: self.gamepad_button()?,
+            },
+            TAG_GAMEPAD_AXIS_MOTION => EventType::GamepadAxisMotion {
+                id: self.gamepad()?,
+                axis: self.gamepad_axis()?,
+                value: self.f32()?,
+            },
+            tag => return Err(Error::InvalidRecording(format!("unknown event tag {}", tag))),
+        };
+
+        return Ok(e_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::UserEvent;
+
+    const MAIN: WindowId = WindowId(7);
+    const OTHER: WindowId = WindowId(300);
+
+    /// Fails to build when a variant is added, so it can't be left out of every_event
+    fn tag_of(e_type: &EventType) -> u8 {
+        return match e_type {
+            EventType::WinShow => TAG_WIN_SHOW,
+            EventType::WinHide => TAG_WIN_HIDE,
+            EventType::WinClose => TAG_WIN_CLOSE,
+            EventType::WinExpose => TAG_WIN_EXPOSE,
+            EventType::WinFocusGained => TAG_WIN_FOCUS_GAINED,
+            EventType::WinFocusLost => TAG_WIN_FOCUS_LOST,
+            EventType::WinResize { .. } => TAG_WIN_RESIZE,
+            EventType::WinMove { .. } => TAG_WIN_MOVE,
+            EventType::KeyDown { .. } => TAG_KEY_DOWN,
+            EventType::KeyUp { .. } => TAG_KEY_UP,
+            EventType::MouseMove { .. } => TAG_MOUSE_MOVE,
+            EventType::MouseRawMotion { .. } => TAG_MOUSE_RAW_MOTION,
+            EventType::MouseEnter => TAG_MOUSE_ENTER,
+            EventType::MouseLeave => TAG_MOUSE_LEAVE,
+            EventType::MouseWheel { .. } => TAG_MOUSE_WHEEL,
+            EventType::MouseMidBtnUp { .. } => TAG_MOUSE_MID_BTN_UP,
+            EventType::MouseMidBtnDown { .. } => TAG_MOUSE_MID_BTN_DOWN,
+            EventType::MouseLeftBtnUp { .. } => TAG_MOUSE_LEFT_BTN_UP,
+            EventType::MouseLeftBtnDown { .. } => TAG_MOUSE_LEFT_BTN_DOWN,
+            EventType::MouseRightBtnUp { .. } => TAG_MOUSE_RIGHT_BTN_UP,
+            EventType::MouseRightBtnDown { .. } => TAG_MOUSE_RIGHT_BTN_DOWN,
+            EventType::TextInput { .. } => TAG_TEXT_INPUT,
+            EventType::TextCompositionStart => TAG_TEXT_COMPOSITION_START,
+            EventType::TextComposition { .. } => TAG_TEXT_COMPOSITION,
+            EventType::TextCompositionEnd => TAG_TEXT_COMPOSITION_END,
+            EventType::GamepadConnected { .. } => TAG_GAMEPAD_CONNECTED,
+            EventType::GamepadDisconnected { .. } => TAG_GAMEPAD_DISCONNECTED,
+            EventType::GamepadButtonDown { .. } => TAG_GAMEPAD_BUTTON_DOWN,
+            EventType::GamepadButtonUp { .. } => TAG_GAMEPAD_BUTTON_UP,
+            EventType::GamepadAxisMotion { .. } => TAG_GAMEPAD_AXIS_MOTION,
+            EventType::User(_) | EventType::None => TAG_NONE,
+        };
+    }
+
+    /// One of every event that can be recorded, with values that need more than a byte
+    fn every_event() -> Vec<EventType> {
+        let modifiers = Modifiers {
+            shift: true,
+            ctrl: false,
+            alt: true,
+            super_key: false,
+            caps_lock: true,
+            num_lock: true,
+        };
+        let last_key = *Key::ALL.last().unwrap();
+        let pad = GamepadId(70000);
+
+        return vec![
+            EventType::WinShow,
+            EventType::WinHide,
+            EventType::WinClose,
+            EventType::WinExpose,
+            EventType::WinFocusGained,
+            EventType::WinFocusLost,
+            EventType::WinResize { width: u16::MAX, height: 200 },
+            EventType::WinMove { x: i16::MIN, y: -1 },
+            EventType::KeyDown { key: last_key, scancode: ScanCode(0xE05B), modifiers, is_repeat: true },
+            EventType::KeyUp { key: Key::A, scancode: ScanCode(30), modifiers },
+            EventType::MouseMove { x: i16::MAX, y: -300 },
+            EventType::MouseRawMotion { delta_x: -0.5, delta_y: 1234.25 },
+            EventType::MouseEnter,
+            EventType::MouseLeave,
+            EventType::MouseWheel { delta_x: 0.0, delta_y: -3.0 },
+            EventType::MouseMidBtnUp { modifiers },
+            EventType::MouseMidBtnDown { modifiers },
+            EventType::MouseLeftBtnUp { modifiers },
+            EventType::MouseLeftBtnDown { modifiers: Modifiers::default() },
+            EventType::MouseRightBtnUp { modifiers },
+            EventType::MouseRightBtnDown { modifiers },
+            EventType::TextInput { text: "héllo 🦀".to_string() },
+            EventType::TextCompositionStart,
+            EventType::TextComposition { text: "にほん".to_string(), cursor: 2 },
+            EventType::TextCompositionEnd,
+            EventType::GamepadConnected { id: pad },
+            EventType::GamepadDisconnected { id: pad },
+            EventType::GamepadButtonDown { id: pad, button: *GamepadButton::ALL.last().unwrap() },
+            EventType::GamepadButtonUp { id: GamepadId(0), button: GamepadButton::ALL[0] },
+            EventType::GamepadAxisMotion { id: pad, axis: *GamepadAxis::ALL.last().unwrap(), value: -1.0 },
+            EventType::None,
+        ];
+    }
+
+    fn recording_of(events: Vec<EventType>) -> Recording {
+        let windows = [Some(MAIN), None, Some(OTHER)];
+
+        return Recording {
+            main_window: Some(MAIN),
+            events: events
+                .into_iter()
+                .enumerate()
+                .map(|(index, e_type)| RecordedEvent {
+                    frame: index as u64 / 3 * 200,
+                    offset: Duration::from_micros(index as u64 * 70_001),
+                    window: windows[index % windows.len()],
+                    e_type,
+                })
+                .collect(),
+        };
+    }
+
+    fn encode(recording: &Recording) -> Vec<u8> {
+        let mut data = Vec::new();
+        recording.write_to(&mut data).unwrap();
+        return data;
+    }
+
+    fn decode(data: &[u8]) -> Result<Recording> {
+        return Recording::read_from(&mut &data[..]);
+    }
+
+    fn is_invalid(res: Result<Recording>) -> bool {
+        return matches!(res, Err(Error::InvalidRecording(_)));
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        let events = every_event();
+
+        // every tag is covered once
+        let mut tags: Vec<u8> = events.iter().map(tag_of).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        assert_eq!(tags.len(), events.len());
+        assert_eq!(tags.len(), TAG_MOUSE_RAW_MOTION as usize + 1);
+
+        let recording = recording_of(events);
+        let read = decode(&encode(&recording)).unwrap();
+
+        assert!(read == recording);
+    }
+
+    #[test]
+    fn empty_recording_round_trips() {
+        let recording = Recording::default();
+        assert!(decode(&encode(&recording)).unwrap() == recording);
+    }
+
+    #[test]
+    fn truncated_data_is_invalid() {
+        let data = encode(&recording_of(every_event()));
+
+        for len in 0..data.len() {
+            assert!(is_invalid(decode(&data[..len])), "cut to {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn trailing_data_is_invalid() {
+        let mut data = encode(&recording_of(vec![EventType::WinShow]));
+        data.push(0);
+
+        assert!(is_invalid(decode(&data)));
+    }
+
+    #[test]
+    fn bad_tags_are_invalid() {
+        let recording = Recording {
+            main_window: Some(MAIN),
+            events: vec![RecordedEvent {
+                frame: 0,
+                offset: Duration::ZERO,
+                window: None,
+                e_type: EventType::WinShow,
+            }],
+        };
+        let data = encode(&recording);
+
+        // the event is the last 4 bytes, frame, offset, window and tag
+        let event_start = data.len() - 4;
+
+        let mut bad_window = data.clone();
+        bad_window[event_start + 2] = 9;
+        assert!(is_invalid(decode(&bad_window)));
+
+        let mut bad_event = data.clone();
+        bad_event[event_start + 3] = 200;
+        assert!(is_invalid(decode(&bad_event)));
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        assert!(is_invalid(decode(&bad_magic)));
+
+        let mut bad_version = data;
+        bad_version[MAGIC.len()] = VERSION + 1;
+        assert!(is_invalid(decode(&bad_version)));
+    }
+
+    #[test]
+    fn out_of_range_values_are_invalid() {
+        let mut data = encode(&recording_of(vec![EventType::KeyUp {
+            key: Key::A,
+            scancode: ScanCode(1),
+            modifiers: Modifiers::default(),
+        }]));
+
+        // KeyUp ends with the key, scancode and modifiers, each a byte here
+        let key_pos = data.len() - 3;
+        data[key_pos] = Key::ALL.len() as u8;
+
+        assert!(is_invalid(decode(&data)));
+    }
+
+    #[test]
+    fn signed_numbers_round_trip() {
+        for value in [0, 1, -1, 63, -64, 64, i16::MIN as i64, i64::MAX, i64::MIN] {
+            let mut out = Vec::new();
+            write_signed(&mut out, value);
+
+            let mut input = Decoder { data: &out, pos: 0 };
+            assert_eq!(input.signed().unwrap(), value);
+            assert_eq!(input.pos, out.len());
+        }
+    }
+
+    #[test]
+    fn player_releases_events_by_frame() {
+        let recording = Recording {
+            main_window: Some(MAIN),
+            events: [(0, Some(MAIN)), (0, Some(OTHER)), (2, None)]
+                .iter()
+                .map(|&(frame, window)| RecordedEvent {
+                    frame,
+                    offset: Duration::from_millis(frame * 16),
+                    window,
+                    e_type: EventType::WinExpose,
+                })
+                .collect(),
+        };
+
+        let replay_main = WindowId(1);
+        let mut player = Player::new(recording, replay_main, 10, false);
+        let mut que = EventDeque::new();
+
+        player.play(10, &mut que);
+        assert_eq!(que.len(), 2);
+        assert_eq!(que[0].window, Some(replay_main));
+        assert_eq!(que[1].window, Some(OTHER));
+        assert!(que[1].time == que[0].time);
+
+        player.play(11, &mut que);
+        assert_eq!(que.len(), 2);
+        assert!(!player.is_finished());
+
+        player.play(12, &mut que);
+        assert_eq!(que.len(), 3);
+        assert_eq!(que[2].window, None);
+        assert_eq!(que[2].time - que[0].time, Duration::from_millis(32));
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn recorder_skips_user_events() {
+        let mut recorder = Recorder::new(MAIN, 5);
+
+        let mut event = Event::new(EventType::WinShow);
+        event.window = Some(MAIN);
+        recorder.record(&event, 6);
+        recorder.record(&Event::new(EventType::User(UserEvent::new(1u8))), 6);
+
+        let recording = recorder.finish();
+        assert_eq!(recording.len(), 1);
+        assert_eq!(recording.get_events()[0].frame, 1);
+        assert_eq!(recording.get_events()[0].window, Some(MAIN));
+    }
+}